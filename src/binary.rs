@@ -0,0 +1,1100 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Context, Result};
+use fn_error_context::context;
+use hitman_commons::resourcelib::{
+	BlueprintSubEntity, EntityBlueprint, EntityFactory, EntityReference, EntitySubset, ExposedEntity,
+	ExternalPinConnection, FactorySubEntity, PinConnection, PlatformSpecificProperty, PropertyAlias, PropertyID,
+	PropertyOverride, PropertyValue
+};
+use serde_json::{from_value, to_value, Map, Number, Value};
+use tryvial::try_fn;
+
+use crate::error::QuickEntityError;
+use crate::property_names::property_id;
+use crate::util_structs::ZRuntimeResourceIDPropertyValue;
+
+// Binary resources are in the BIN1 format used by ResourceLib for HITMAN 3. They start with a header:
+//
+// - the magic `BIN1`
+// - a zero byte, the alignment of the data (8), the number of segments and another zero byte
+// - the length of the data, as a big-endian u32
+// - four zero bytes
+//
+// This is followed by the data, which is the resource's root struct (laid out as the game lays it out in memory)
+// followed by everything it points to, and then the segments. Pointers are stored as offsets into the data, with
+// u64::MAX for null. Each segment is a u32 ID and a u32 size followed by its contents.
+
+/// The size of the BIN1 header before the data.
+const HEADER_SIZE: usize = 16;
+
+/// The alignment of the data, as given in the header.
+const ALIGNMENT: u8 = 8;
+
+/// The segment listing the offset of every pointer in the data, so that the game can relocate them when loading.
+const RELOCATION_SEGMENT: u32 = 0x12EBA5ED;
+
+/// The segment listing the offset of every ZVariant's type in the data, followed by the table of types they refer to.
+const TYPE_ID_SEGMENT: u32 = 0x3989BF9F;
+
+/// The segment listing the offset of every ZRuntimeResourceID in the data, since they refer to the resource's references.
+const RUNTIME_RESOURCE_ID_SEGMENT: u32 = 0x578FBCEE;
+
+/// Stored in a pointer's place for null pointers, such as those of empty arrays.
+const NULL_POINTER: u64 = u64::MAX;
+
+/// Set on the length of serialised ZStrings, marking their characters as not owned by the string.
+const STATIC_STRING_FLAG: u32 = 0x4000_0000;
+
+/// The size of a TArray: pointers to its start, its end and the end of its allocation.
+const ARRAY_SIZE: usize = 24;
+
+/// The size of a ZString: its length, then a pointer to its characters.
+const STRING_SIZE: usize = 16;
+
+/// The size of an SEntityTemplateReference.
+const REFERENCE_SIZE: usize = 32;
+
+/// The size of an SEntityTemplateProperty: its ID, then its value as a ZVariant.
+const PROPERTY_SIZE: usize = 24;
+
+/// The size of an SEntityTemplatePlatformSpecificProperty: an SEntityTemplateProperty, then its platform as a u32 EVirtualPlatformID and whether it's post-init.
+const PLATFORM_SPECIFIC_PROPERTY_SIZE: usize = 0x20;
+
+/// Read a factory (TEMP) from the binary format the game uses (as of HITMAN 3).
+///
+/// Property values are read according to their type, which must be one of the types that `write_binary_factory` supports. Enum values are read as integers, and platforms as the number of their EVirtualPlatformID, since the binary format doesn't store their names.
+#[try_fn]
+#[context("Failure reading binary factory")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn read_binary_factory(data: &[u8]) -> Result<EntityFactory, QuickEntityError> {
+	let resource = Resource::parse(data)?;
+
+	EntityFactory {
+		sub_type: resource.i32(0)? as _,
+		blueprint_index_in_resource_header: resource.i32(0x4)? as _,
+		root_entity_index: resource.i32(0x8)? as _,
+		sub_entities: resource
+			.array(0x10, 0x70)?
+			.map(|at| {
+				Ok(FactorySubEntity {
+					logical_parent: resource.reference(at)?,
+					entity_type_resource_index: resource.i32(at + 0x20)? as _,
+					property_values: resource.properties(at + 0x28)?,
+					post_init_property_values: resource.properties(at + 0x40)?,
+					platform_specific_property_values: resource
+						.array(at + 0x58, PLATFORM_SPECIFIC_PROPERTY_SIZE)?
+						.map(|at| {
+							Ok(PlatformSpecificProperty {
+								property_value: resource.property(at)?,
+								platform: resource.u32(at + 0x18)?.to_string(),
+								post_init: resource.bool(at + 0x1C)?
+							})
+						})
+						.collect::<Result<_>>()?
+				})
+			})
+			.collect::<Result<_>>()?,
+		property_overrides: resource
+			.array(0x28, 0x38)?
+			.map(|at| {
+				Ok(PropertyOverride {
+					property_owner: resource.reference(at)?,
+					property_value: resource.property(at + 0x20)?
+				})
+			})
+			.collect::<Result<_>>()?,
+		external_scene_type_indices_in_resource_header: resource.indices(0x40)?
+	}
+}
+
+/// Write a factory (TEMP) in the binary format the game uses (as of HITMAN 3).
+///
+/// Property values can be of any primitive type, ZString, SVector2/3/4, SColorRGB(A), SMatrix43, ZGuid, ZRepositoryID, SEntityTemplateReference, ZRuntimeResourceID, an enum, or a TArray of those; other types can't be written.
+///
+/// The binary format stores enum values and platforms as numbers rather than by name, and QuickEntity doesn't know the names' numbers, so enum values must be given as integers and platforms as the number of their EVirtualPlatformID (as `read_binary_factory` gives them).
+#[try_fn]
+#[context("Failure writing binary factory")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn write_binary_factory(factory: &EntityFactory) -> Result<Vec<u8>, QuickEntityError> {
+	let mut writer = Writer::new(0x58);
+
+	writer.i32(0, factory.sub_type as _);
+	writer.i32(0x4, factory.blueprint_index_in_resource_header as _);
+	writer.i32(0x8, factory.root_entity_index as _);
+
+	writer.array(0x10, &factory.sub_entities, 0x70, 8, |writer, at, sub_entity| {
+		writer.reference(at, &sub_entity.logical_parent)?;
+		writer.i32(at + 0x20, sub_entity.entity_type_resource_index as _);
+		writer.properties(at + 0x28, &sub_entity.property_values)?;
+		writer.properties(at + 0x40, &sub_entity.post_init_property_values)?;
+
+		writer.array(
+			at + 0x58,
+			&sub_entity.platform_specific_property_values,
+			PLATFORM_SPECIFIC_PROPERTY_SIZE,
+			8,
+			|writer, at, property| {
+				writer.property(at, &property.property_value)?;
+
+				writer.u32(
+					at + 0x18,
+					property.platform.parse().with_context(|| {
+						format!(
+							"Platform {} can't be stored in binary resources; it must be the number of its \
+							 EVirtualPlatformID",
+							property.platform
+						)
+					})?
+				);
+
+				writer.data[at + 0x1C] = property.post_init.into();
+
+				Ok(())
+			}
+		)
+	})?;
+
+	writer.array(
+		0x28,
+		&factory.property_overrides,
+		0x38,
+		8,
+		|writer, at, property_override| {
+			writer.reference(at, &property_override.property_owner)?;
+			writer.property(at + 0x20, &property_override.property_value)
+		}
+	)?;
+
+	writer.indices(0x40, &factory.external_scene_type_indices_in_resource_header)?;
+
+	writer.finish()
+}
+
+/// Read a blueprint (TBLU) from the binary format the game uses (as of HITMAN 3).
+///
+/// Constant pin values are read according to their type, which must be one of the types that `write_binary_factory` supports.
+#[try_fn]
+#[context("Failure reading binary blueprint")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn read_binary_blueprint(data: &[u8]) -> Result<EntityBlueprint, QuickEntityError> {
+	let resource = Resource::parse(data)?;
+
+	EntityBlueprint {
+		sub_type: resource.i32(0)? as _,
+		root_entity_index: resource.i32(0x4)? as _,
+		sub_entities: resource
+			.array(0x8, 0xA8)?
+			.map(|at| {
+				Ok(BlueprintSubEntity {
+					logical_parent: resource.reference(at)?,
+					entity_type_resource_index: resource.i32(at + 0x20)? as _,
+					entity_id: resource.u64(at + 0x28)?,
+					editor_only: resource.bool(at + 0x30)?,
+					entity_name: resource.string(at + 0x38)?,
+					property_aliases: resource
+						.array(at + 0x48, 0x28)?
+						.map(|at| {
+							Ok(PropertyAlias {
+								s_alias_name: resource.string(at)?,
+								entity_id: resource.i32(at + 0x10)? as _,
+								s_property_name: resource.string(at + 0x18)?
+							})
+						})
+						.collect::<Result<_>>()?,
+					exposed_entities: resource
+						.array(at + 0x60, 0x30)?
+						.map(|at| {
+							Ok(ExposedEntity {
+								s_name: resource.string(at)?,
+								b_is_array: resource.bool(at + 0x10)?,
+								a_targets: resource
+									.array(at + 0x18, REFERENCE_SIZE)?
+									.map(|at| resource.reference(at))
+									.collect::<Result<_>>()?
+							})
+						})
+						.collect::<Result<_>>()?,
+					exposed_interfaces: resource
+						.array(at + 0x78, 0x18)?
+						.map(|at| Ok((resource.string(at)?, resource.i32(at + 0x10)? as _)))
+						.collect::<Result<_>>()?,
+					entity_subsets: resource
+						.array(at + 0x90, 0x28)?
+						.map(|at| {
+							Ok((
+								resource.string(at)?,
+								EntitySubset {
+									entities: resource.indices(at + 0x10)?
+								}
+							))
+						})
+						.collect::<Result<_>>()?
+				})
+			})
+			.collect::<Result<_>>()?,
+		pin_connections: resource.pin_connections(0x20)?,
+		input_pin_forwardings: resource.pin_connections(0x38)?,
+		output_pin_forwardings: resource.pin_connections(0x50)?,
+		override_deletes: resource
+			.array(0x68, REFERENCE_SIZE)?
+			.map(|at| resource.reference(at))
+			.collect::<Result<_>>()?,
+		pin_connection_overrides: resource.external_pin_connections(0x80)?,
+		pin_connection_override_deletes: resource.external_pin_connections(0x98)?,
+		external_scene_type_indices_in_resource_header: resource.indices(0xB0)?
+	}
+}
+
+/// Write a blueprint (TBLU) in the binary format the game uses (as of HITMAN 3).
+///
+/// Constant pin values can be of the same types as property values in `write_binary_factory`.
+#[try_fn]
+#[context("Failure writing binary blueprint")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn write_binary_blueprint(blueprint: &EntityBlueprint) -> Result<Vec<u8>, QuickEntityError> {
+	let mut writer = Writer::new(0xC8);
+
+	writer.i32(0, blueprint.sub_type as _);
+	writer.i32(0x4, blueprint.root_entity_index as _);
+
+	writer.array(0x8, &blueprint.sub_entities, 0xA8, 8, |writer, at, sub_entity| {
+		writer.reference(at, &sub_entity.logical_parent)?;
+		writer.i32(at + 0x20, sub_entity.entity_type_resource_index as _);
+		writer.u64(at + 0x28, sub_entity.entity_id);
+		writer.data[at + 0x30] = sub_entity.editor_only.into();
+		writer.string(at + 0x38, &sub_entity.entity_name);
+
+		writer.array(at + 0x48, &sub_entity.property_aliases, 0x28, 8, |writer, at, alias| {
+			writer.string(at, &alias.s_alias_name);
+			writer.i32(at + 0x10, alias.entity_id as _);
+			writer.string(at + 0x18, &alias.s_property_name);
+			Ok(())
+		})?;
+
+		writer.array(
+			at + 0x60,
+			&sub_entity.exposed_entities,
+			0x30,
+			8,
+			|writer, at, exposed_entity| {
+				writer.string(at, &exposed_entity.s_name);
+				writer.data[at + 0x10] = exposed_entity.b_is_array.into();
+
+				writer.array(
+					at + 0x18,
+					&exposed_entity.a_targets,
+					REFERENCE_SIZE,
+					8,
+					|writer, at, target| writer.reference(at, target)
+				)
+			}
+		)?;
+
+		writer.array(
+			at + 0x78,
+			&sub_entity.exposed_interfaces,
+			0x18,
+			8,
+			|writer, at, (name, index)| {
+				writer.string(at, name);
+				writer.i32(at + 0x10, *index as _);
+				Ok(())
+			}
+		)?;
+
+		writer.array(
+			at + 0x90,
+			&sub_entity.entity_subsets,
+			0x28,
+			8,
+			|writer, at, (name, subset)| {
+				writer.string(at, name);
+				writer.indices(at + 0x10, &subset.entities)
+			}
+		)
+	})?;
+
+	writer.pin_connections(0x20, &blueprint.pin_connections)?;
+	writer.pin_connections(0x38, &blueprint.input_pin_forwardings)?;
+	writer.pin_connections(0x50, &blueprint.output_pin_forwardings)?;
+
+	writer.array(
+		0x68,
+		&blueprint.override_deletes,
+		REFERENCE_SIZE,
+		8,
+		|writer, at, reference| writer.reference(at, reference)
+	)?;
+
+	writer.external_pin_connections(0x80, &blueprint.pin_connection_overrides)?;
+	writer.external_pin_connections(0x98, &blueprint.pin_connection_override_deletes)?;
+
+	writer.indices(0xB0, &blueprint.external_scene_type_indices_in_resource_header)?;
+
+	writer.finish()
+}
+
+/// Get the length of the data in a binary resource, which is the size the resource takes up in memory once loaded.
+#[try_fn]
+#[context("Failure reading binary resource header")]
+pub fn binary_data_size(data: &[u8]) -> Result<u32, QuickEntityError> {
+	Resource::header(data)?.1 as u32
+}
+
+/// The size and alignment of a value of a property type, if it's a type that can be serialised.
+#[try_fn]
+fn layout(property_type: &str) -> Result<(usize, usize)> {
+	match property_type {
+		"bool" | "int8" | "uint8" => (1, 1),
+		"int16" | "uint16" => (2, 2),
+		"int32" | "uint32" | "float32" => (4, 4),
+		"int64" | "uint64" | "float64" => (8, 8),
+		"SVector2" => (8, 4),
+		"SVector3" | "SColorRGB" => (12, 4),
+		"SVector4" | "SColorRGBA" | "ZGuid" | "ZRepositoryID" => (16, 4),
+		"SMatrix43" => (48, 4),
+		"ZRuntimeResourceID" => (8, 8),
+		"ZString" => (STRING_SIZE, 8),
+		"SEntityTemplateReference" => (REFERENCE_SIZE, 8),
+		x if is_enum(x) => (4, 4),
+		x if array_element_type(x).is_some() => (ARRAY_SIZE, 8),
+		x => bail!("Property type {} can't be stored in binary resources", x)
+	}
+}
+
+/// Whether a property type is an enum, going by the convention that enum names (without the class they're in) are E followed by a capital letter. Enums are stored as an int32.
+fn is_enum(property_type: &str) -> bool {
+	let name = property_type.rsplit('.').next().unwrap_or(property_type);
+
+	let mut chars = name.chars();
+	chars.next() == Some('E') && chars.next().is_some_and(|x| x.is_ascii_uppercase())
+}
+
+fn array_element_type(property_type: &str) -> Option<&str> {
+	property_type.strip_prefix("TArray<").and_then(|x| x.strip_suffix('>'))
+}
+
+/// The keys of the components of the types made up of floats.
+fn float_components(property_type: &str) -> Option<&'static [&'static str]> {
+	match property_type {
+		"SVector2" => Some(&["x", "y"]),
+		"SVector3" => Some(&["x", "y", "z"]),
+		"SVector4" => Some(&["x", "y", "z", "w"]),
+		"SColorRGB" => Some(&["r", "g", "b"]),
+		"SColorRGBA" => Some(&["r", "g", "b", "a"]),
+		_ => None
+	}
+}
+
+const MATRIX_AXES: [&str; 4] = ["XAxis", "YAxis", "ZAxis", "Trans"];
+
+const GUID_BYTES: [&str; 8] = ["_d", "_e", "_f", "_g", "_h", "_i", "_j", "_k"];
+
+/// A parsed binary resource.
+struct Resource<'a> {
+	data: &'a [u8],
+
+	/// The names of the types ZVariants refer to, by their index in the type table.
+	types: HashMap<u64, String>
+}
+
+impl<'a> Resource<'a> {
+	/// Check a resource's header, returning its segment count and the length of its data.
+	#[try_fn]
+	fn header(data: &[u8]) -> Result<(u8, usize)> {
+		if data.get(0..4) != Some(b"BIN1") {
+			bail!("Resource is not in the BIN1 format");
+		}
+
+		let segment_count = *data.get(6).context("Resource header is truncated")?;
+		let data_length = u32::from_be_bytes(data.get(8..12).context("Resource header is truncated")?.try_into()?);
+
+		(segment_count, data_length as usize)
+	}
+
+	#[try_fn]
+	fn parse(data: &'a [u8]) -> Result<Self> {
+		let (segment_count, data_length) = Self::header(data)?;
+
+		let body = data
+			.get(HEADER_SIZE..HEADER_SIZE + data_length)
+			.context("Resource data is truncated")?;
+
+		let mut types = HashMap::new();
+		let mut position = HEADER_SIZE + data_length;
+
+		for _ in 0..segment_count {
+			let segment = Resource {
+				data,
+				types: HashMap::new()
+			};
+			let id = segment.u32(position)?;
+			let size = segment.u32(position + 4)? as usize;
+
+			if id == TYPE_ID_SEGMENT {
+				let contents = Resource {
+					data: segment.bytes(position + 8, size)?,
+					types: HashMap::new()
+				};
+
+				// The offsets of the types in the data aren't needed, since they're found by following the structure
+				let type_count_at = 4 + contents.u32(0)? as usize * 4;
+				let mut at = type_count_at + 4;
+
+				for _ in 0..contents.u32(type_count_at)? {
+					let index = contents.u32(at)?;
+					let name_length = contents.u32(at + 8)? as usize;
+					let name = contents.bytes(at + 12, name_length)?;
+
+					types.insert(
+						u64::from(index),
+						String::from_utf8(name.strip_suffix(&[0]).unwrap_or(name).to_owned())
+							.context("Type name is not valid UTF-8")?
+					);
+
+					at = (at + 12 + name_length).next_multiple_of(4);
+				}
+			}
+
+			position += 8 + size;
+		}
+
+		Resource { data: body, types }
+	}
+
+	#[try_fn]
+	fn bytes(&self, at: usize, len: usize) -> Result<&'a [u8]> {
+		let Some(bytes) = self.data.get(at..at + len) else {
+			bail!("Unexpected end of data at offset {}", at);
+		};
+
+		bytes
+	}
+
+	#[try_fn]
+	fn array_bytes<const N: usize>(&self, at: usize) -> Result<[u8; N]> {
+		self.bytes(at, N)?.try_into()?
+	}
+
+	#[try_fn]
+	fn bool(&self, at: usize) -> Result<bool> {
+		self.bytes(at, 1)?[0] != 0
+	}
+
+	#[try_fn]
+	fn i32(&self, at: usize) -> Result<i32> {
+		i32::from_le_bytes(self.array_bytes(at)?)
+	}
+
+	#[try_fn]
+	fn u32(&self, at: usize) -> Result<u32> {
+		u32::from_le_bytes(self.array_bytes(at)?)
+	}
+
+	#[try_fn]
+	fn u64(&self, at: usize) -> Result<u64> {
+		u64::from_le_bytes(self.array_bytes(at)?)
+	}
+
+	/// Read a float32 as the shortest float64 that represents it, which is how ResourceTool writes them.
+	#[try_fn]
+	fn f32(&self, at: usize) -> Result<Value> {
+		let value = f32::from_le_bytes(self.array_bytes(at)?);
+
+		Number::from_f64(value.to_string().parse()?)
+			.with_context(|| format!("Invalid float at offset {}", at))?
+			.into()
+	}
+
+	#[try_fn]
+	fn pointer(&self, at: usize) -> Result<Option<usize>> {
+		match self.u64(at)? {
+			NULL_POINTER => None,
+			pointer => Some(pointer as usize)
+		}
+	}
+
+	/// Get the offsets of the elements of a TArray.
+	#[try_fn]
+	fn array(&self, at: usize, element_size: usize) -> Result<impl ExactSizeIterator<Item = usize>> {
+		let (start, end) = match (self.pointer(at)?, self.pointer(at + 8)?) {
+			(Some(start), Some(end)) if end >= start => (start, end),
+			(None, None) => (0, 0),
+			_ => bail!("Invalid array at offset {}", at)
+		};
+
+		if (end - start) % element_size != 0 {
+			bail!("Array at offset {} isn't a whole number of elements", at);
+		}
+
+		(start..end).step_by(element_size)
+	}
+
+	#[try_fn]
+	fn string(&self, at: usize) -> Result<String> {
+		let length = (self.u32(at)? & !STATIC_STRING_FLAG) as usize;
+
+		match self.pointer(at + 8)? {
+			Some(chars) => String::from_utf8(self.bytes(chars, length)?.to_owned())
+				.with_context(|| format!("String at offset {} is not valid UTF-8", at))?,
+
+			None => String::new()
+		}
+	}
+
+	#[try_fn]
+	fn indices<T: TryFrom<i32>>(&self, at: usize) -> Result<Vec<T>> {
+		self.array(at, 4)?
+			.map(|at| T::try_from(self.i32(at)?).ok().context("Invalid index"))
+			.collect::<Result<_>>()?
+	}
+
+	#[try_fn]
+	fn reference(&self, at: usize) -> Result<EntityReference> {
+		EntityReference {
+			entity_id: self.u64(at)?,
+			external_scene_index: self.i32(at + 0x8)? as _,
+			entity_index: self.i32(at + 0xC)? as _,
+			exposed_entity: self.string(at + 0x10)?
+		}
+	}
+
+	#[try_fn]
+	fn property(&self, at: usize) -> Result<hitman_commons::resourcelib::Property> {
+		let id = self.u32(at)?;
+
+		hitman_commons::resourcelib::Property {
+			n_property_id: PropertyID::Int(id.into()),
+			value: self
+				.variant(at + 0x8)
+				.with_context(|| format!("Couldn't read property {}", id))?
+		}
+	}
+
+	#[try_fn]
+	fn properties(&self, at: usize) -> Result<Vec<hitman_commons::resourcelib::Property>> {
+		self.array(at, PROPERTY_SIZE)?
+			.map(|at| self.property(at))
+			.collect::<Result<_>>()?
+	}
+
+	#[try_fn]
+	fn variant(&self, at: usize) -> Result<PropertyValue> {
+		let type_index = self.u64(at)?;
+
+		let property_type = self
+			.types
+			.get(&type_index)
+			.with_context(|| format!("Unknown type index {}", type_index))?;
+
+		PropertyValue {
+			property_type: property_type.to_owned(),
+			property_value: match self.pointer(at + 8)? {
+				Some(value) => self.value(value, property_type)?,
+				None => Value::Null
+			}
+		}
+	}
+
+	#[try_fn]
+	fn value(&self, at: usize, property_type: &str) -> Result<Value> {
+		match property_type {
+			"bool" => self.bool(at)?.into(),
+			"int8" => (self.bytes(at, 1)?[0] as i8).into(),
+			"uint8" => self.bytes(at, 1)?[0].into(),
+			"int16" => i16::from_le_bytes(self.array_bytes(at)?).into(),
+			"uint16" => u16::from_le_bytes(self.array_bytes(at)?).into(),
+			"int32" => self.i32(at)?.into(),
+			"uint32" => self.u32(at)?.into(),
+			"int64" => i64::from_le_bytes(self.array_bytes(at)?).into(),
+			"uint64" => self.u64(at)?.into(),
+			"float32" => self.f32(at)?,
+			"float64" => f64::from_le_bytes(self.array_bytes(at)?).into(),
+			"ZString" => self.string(at)?.into(),
+
+			"SMatrix43" => Value::Object(
+				MATRIX_AXES
+					.iter()
+					.enumerate()
+					.map(|(index, axis)| Ok((axis.to_string(), self.floats(at + index * 12, &["x", "y", "z"])?)))
+					.collect::<Result<_>>()?
+			),
+
+			"ZGuid" => {
+				let mut guid = Map::new();
+				guid.insert("_a".into(), self.u32(at)?.into());
+				guid.insert("_b".into(), u16::from_le_bytes(self.array_bytes(at + 4)?).into());
+				guid.insert("_c".into(), u16::from_le_bytes(self.array_bytes(at + 6)?).into());
+
+				for (byte, key) in self.bytes(at + 8, 8)?.iter().zip(GUID_BYTES) {
+					guid.insert(key.into(), (*byte).into());
+				}
+
+				Value::Object(guid)
+			}
+
+			"ZRepositoryID" => {
+				let bytes = self.bytes(at + 8, 8)?;
+
+				format!(
+					"{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{}",
+					self.u32(at)?,
+					u16::from_le_bytes(self.array_bytes(at + 4)?),
+					u16::from_le_bytes(self.array_bytes(at + 6)?),
+					bytes[0],
+					bytes[1],
+					bytes[2..].iter().map(|x| format!("{:02x}", x)).collect::<String>()
+				)
+				.into()
+			}
+
+			"SEntityTemplateReference" => to_value(self.reference(at)?)?,
+
+			"ZRuntimeResourceID" => to_value(ZRuntimeResourceIDPropertyValue {
+				m_IDHigh: self.u32(at)?,
+				m_IDLow: self.u32(at + 4)?
+			})?,
+
+			x if is_enum(x) => self.i32(at)?.into(),
+
+			x => match (float_components(x), array_element_type(x)) {
+				(Some(components), _) => self.floats(at, components)?,
+
+				(None, Some(element_type)) => {
+					let (element_size, _) = layout(element_type)?;
+
+					Value::Array(
+						self.array(at, element_size)?
+							.map(|at| self.value(at, element_type))
+							.collect::<Result<_>>()?
+					)
+				}
+
+				(None, None) => bail!("Property type {} can't be read from binary resources", x)
+			}
+		}
+	}
+
+	#[try_fn]
+	fn floats(&self, at: usize, components: &[&str]) -> Result<Value> {
+		Value::Object(
+			components
+				.iter()
+				.enumerate()
+				.map(|(index, component)| Ok((component.to_string(), self.f32(at + index * 4)?)))
+				.collect::<Result<_>>()?
+		)
+	}
+
+	#[try_fn]
+	fn pin_connections(&self, at: usize) -> Result<Vec<PinConnection>> {
+		self.array(at, 0x38)?
+			.map(|at| {
+				Ok(PinConnection {
+					from_id: self.i32(at)? as _,
+					to_id: self.i32(at + 0x4)? as _,
+					from_pin_name: self.string(at + 0x8)?,
+					to_pin_name: self.string(at + 0x18)?,
+					constant_pin_value: self.variant(at + 0x28)?
+				})
+			})
+			.collect::<Result<_>>()?
+	}
+
+	#[try_fn]
+	fn external_pin_connections(&self, at: usize) -> Result<Vec<ExternalPinConnection>> {
+		self.array(at, 0x70)?
+			.map(|at| {
+				Ok(ExternalPinConnection {
+					from_entity: self.reference(at)?,
+					to_entity: self.reference(at + 0x20)?,
+					from_pin_name: self.string(at + 0x40)?,
+					to_pin_name: self.string(at + 0x50)?,
+					constant_pin_value: self.variant(at + 0x60)?
+				})
+			})
+			.collect::<Result<_>>()?
+	}
+}
+
+/// Builds the data of a binary resource, appending whatever a struct points to after it.
+struct Writer {
+	data: Vec<u8>,
+	relocations: Vec<u32>,
+	type_offsets: Vec<u32>,
+	types: Vec<String>,
+	runtime_resource_ids: Vec<u32>
+}
+
+impl Writer {
+	/// Start a resource with a root struct of the given size.
+	fn new(root_size: usize) -> Self {
+		Self {
+			data: vec![0; root_size],
+			relocations: vec![],
+			type_offsets: vec![],
+			types: vec![],
+			runtime_resource_ids: vec![]
+		}
+	}
+
+	/// Add space to the end of the data, returning its offset.
+	fn reserve(&mut self, size: usize, align: usize) -> usize {
+		let at = self.data.len().next_multiple_of(align);
+		self.data.resize(at + size, 0);
+		at
+	}
+
+	fn bytes(&mut self, at: usize, bytes: &[u8]) {
+		self.data[at..at + bytes.len()].copy_from_slice(bytes);
+	}
+
+	fn i32(&mut self, at: usize, value: i32) {
+		self.bytes(at, &value.to_le_bytes());
+	}
+
+	fn u32(&mut self, at: usize, value: u32) {
+		self.bytes(at, &value.to_le_bytes());
+	}
+
+	fn u64(&mut self, at: usize, value: u64) {
+		self.bytes(at, &value.to_le_bytes());
+	}
+
+	fn pointer(&mut self, at: usize, target: Option<usize>) {
+		match target {
+			Some(target) => {
+				self.u64(at, target as u64);
+				self.relocations.push(at as u32);
+			}
+
+			None => self.u64(at, NULL_POINTER)
+		}
+	}
+
+	/// Write a TArray, with its elements (preceded by their count) at the end of the data.
+	#[try_fn]
+	fn array<T>(
+		&mut self,
+		at: usize,
+		items: &[T],
+		element_size: usize,
+		align: usize,
+		mut write: impl FnMut(&mut Self, usize, &T) -> Result<()>
+	) -> Result<()> {
+		if items.is_empty() {
+			self.pointer(at, None);
+			self.pointer(at + 8, None);
+			self.pointer(at + 16, None);
+			return Ok(());
+		}
+
+		// The count goes just before the elements, which must still be aligned
+		let start = (self.data.len() + 4).next_multiple_of(align.max(4));
+		self.data.resize(start + items.len() * element_size, 0);
+		self.u32(start - 4, items.len() as u32);
+
+		let end = start + items.len() * element_size;
+		self.pointer(at, Some(start));
+		self.pointer(at + 8, Some(end));
+		self.pointer(at + 16, Some(end));
+
+		for (index, item) in items.iter().enumerate() {
+			write(self, start + index * element_size, item)?;
+		}
+	}
+
+	/// Write a ZString, with its characters (preceded by their length and followed by a null terminator) at the end of the data.
+	fn string(&mut self, at: usize, value: &str) {
+		let chars = self.reserve(4 + value.len() + 1, 8) + 4;
+		self.u32(chars - 4, value.len() as u32);
+		self.bytes(chars, value.as_bytes());
+
+		self.u32(at, value.len() as u32 | STATIC_STRING_FLAG);
+		self.pointer(at + 8, Some(chars));
+	}
+
+	#[try_fn]
+	fn indices<T: Copy + TryInto<i32>>(&mut self, at: usize, indices: &[T]) -> Result<()> {
+		self.array(at, indices, 4, 4, |writer, at, index| {
+			writer.i32(at, (*index).try_into().ok().context("Index is out of range")?);
+			Ok(())
+		})?;
+	}
+
+	#[try_fn]
+	fn reference(&mut self, at: usize, reference: &EntityReference) -> Result<()> {
+		self.u64(at, reference.entity_id);
+		self.i32(at + 0x8, reference.external_scene_index as _);
+		self.i32(at + 0xC, reference.entity_index as _);
+		self.string(at + 0x10, &reference.exposed_entity);
+	}
+
+	#[try_fn]
+	fn property(&mut self, at: usize, property: &hitman_commons::resourcelib::Property) -> Result<()> {
+		let id = match &property.n_property_id {
+			PropertyID::Int(id) => u32::try_from(*id).context("Property ID is out of range")?,
+			PropertyID::String(name) => property_id(name)
+		};
+
+		self.u32(at, id);
+
+		self.variant(at + 0x8, &property.value)
+			.with_context(|| format!("Couldn't write property {}", id))?;
+	}
+
+	#[try_fn]
+	fn properties(&mut self, at: usize, properties: &[hitman_commons::resourcelib::Property]) -> Result<()> {
+		self.array(at, properties, PROPERTY_SIZE, 8, |writer, at, property| {
+			writer.property(at, property)
+		})?;
+	}
+
+	/// Write a ZVariant, with its value at the end of the data.
+	#[try_fn]
+	fn variant(&mut self, at: usize, value: &PropertyValue) -> Result<()> {
+		let type_index = match self.types.iter().position(|x| *x == value.property_type) {
+			Some(index) => index,
+			None => {
+				self.types.push(value.property_type.to_owned());
+				self.types.len() - 1
+			}
+		};
+
+		self.u64(at, type_index as u64);
+		self.type_offsets.push(at as u32);
+
+		if value.property_type == "void" {
+			self.pointer(at + 8, None);
+		} else {
+			let (size, align) = layout(&value.property_type)?;
+			let target = self.reserve(size, align);
+			self.value(target, &value.property_type, &value.property_value)?;
+			self.pointer(at + 8, Some(target));
+		}
+	}
+
+	#[try_fn]
+	fn value(&mut self, at: usize, property_type: &str, value: &Value) -> Result<()> {
+		let integer = || {
+			value
+				.as_i64()
+				.or_else(|| value.as_u64().map(|x| x as i64))
+				.with_context(|| format!("{} value must be an integer", property_type))
+		};
+
+		let out_of_range = || format!("{} value is out of range", property_type);
+
+		let float = || {
+			value
+				.as_f64()
+				.with_context(|| format!("{} value must be a number", property_type))
+		};
+
+		match property_type {
+			"bool" => {
+				self.data[at] = value.as_bool().context("bool value must be a boolean")?.into();
+			}
+
+			"int8" => self.bytes(at, &i8::try_from(integer()?).with_context(out_of_range)?.to_le_bytes()),
+			"uint8" => self.bytes(at, &u8::try_from(integer()?).with_context(out_of_range)?.to_le_bytes()),
+			"int16" => self.bytes(at, &i16::try_from(integer()?).with_context(out_of_range)?.to_le_bytes()),
+			"uint16" => self.bytes(at, &u16::try_from(integer()?).with_context(out_of_range)?.to_le_bytes()),
+			"int32" => self.i32(at, i32::try_from(integer()?).with_context(out_of_range)?),
+			"uint32" => self.u32(at, u32::try_from(integer()?).with_context(out_of_range)?),
+			"int64" => self.bytes(at, &integer()?.to_le_bytes()),
+			"uint64" => self.u64(at, value.as_u64().with_context(out_of_range)?),
+			"float32" => self.bytes(at, &(float()? as f32).to_le_bytes()),
+			"float64" => self.bytes(at, &float()?.to_le_bytes()),
+			"ZString" => self.string(at, value.as_str().context("ZString value must be a string")?),
+
+			"SMatrix43" => {
+				for (index, axis) in MATRIX_AXES.iter().enumerate() {
+					self.floats(
+						at + index * 12,
+						value
+							.get(axis)
+							.with_context(|| format!("SMatrix43 value must have {}", axis))?,
+						&["x", "y", "z"]
+					)?;
+				}
+			}
+
+			"ZGuid" => {
+				let field = |key: &str| {
+					value
+						.get(key)
+						.and_then(Value::as_u64)
+						.with_context(|| format!("ZGuid value must have {}", key))
+				};
+
+				self.u32(at, u32::try_from(field("_a")?).with_context(out_of_range)?);
+				self.bytes(
+					at + 4,
+					&u16::try_from(field("_b")?).with_context(out_of_range)?.to_le_bytes()
+				);
+				self.bytes(
+					at + 6,
+					&u16::try_from(field("_c")?).with_context(out_of_range)?.to_le_bytes()
+				);
+
+				for (index, key) in GUID_BYTES.iter().enumerate() {
+					self.data[at + 8 + index] = u8::try_from(field(key)?).with_context(out_of_range)?;
+				}
+			}
+
+			"ZRepositoryID" => {
+				let guid = value
+					.as_str()
+					.context("ZRepositoryID value must be a string")?
+					.replace('-', "");
+
+				if guid.len() != 32 {
+					bail!("ZRepositoryID value must be a GUID");
+				}
+
+				let hex = |range: std::ops::Range<usize>| {
+					u32::from_str_radix(guid.get(range).context("ZRepositoryID value must be a GUID")?, 16)
+						.context("ZRepositoryID value must be a GUID")
+				};
+
+				self.u32(at, hex(0..8)?);
+				self.bytes(at + 4, &(hex(8..12)? as u16).to_le_bytes());
+				self.bytes(at + 6, &(hex(12..16)? as u16).to_le_bytes());
+
+				for index in 0..8 {
+					self.data[at + 8 + index] = hex(16 + index * 2..18 + index * 2)? as u8;
+				}
+			}
+
+			"SEntityTemplateReference" => {
+				self.reference(
+					at,
+					&from_value(value.to_owned()).context("SEntityTemplateReference value is invalid")?
+				)?;
+			}
+
+			"ZRuntimeResourceID" => {
+				let id = from_value::<ZRuntimeResourceIDPropertyValue>(value.to_owned())
+					.context("ZRuntimeResourceID value is invalid")?;
+
+				self.u32(at, id.m_IDHigh);
+				self.u32(at + 4, id.m_IDLow);
+
+				// Null IDs don't refer to a resource
+				if id.m_IDLow != u32::MAX {
+					self.runtime_resource_ids.push(at as u32);
+				}
+			}
+
+			x if is_enum(x) => {
+				if value.is_string() {
+					bail!(
+						"{} value {} can't be stored in binary resources, since they store enum values as integers \
+						 rather than by name",
+						x,
+						value
+					);
+				}
+
+				self.i32(at, i32::try_from(integer()?).with_context(out_of_range)?);
+			}
+
+			x => match (float_components(x), array_element_type(x)) {
+				(Some(components), _) => self.floats(at, value, components)?,
+
+				(None, Some(element_type)) => {
+					let (element_size, align) = layout(element_type)?;
+
+					self.array(
+						at,
+						value.as_array().context("TArray value must be an array")?,
+						element_size,
+						align,
+						|writer, at, element| writer.value(at, element_type, element)
+					)?;
+				}
+
+				(None, None) => bail!("Property type {} can't be stored in binary resources", x)
+			}
+		}
+	}
+
+	#[try_fn]
+	fn floats(&mut self, at: usize, value: &Value, components: &[&str]) -> Result<()> {
+		for (index, component) in components.iter().enumerate() {
+			let component = value
+				.get(component)
+				.and_then(Value::as_f64)
+				.with_context(|| format!("Value must have a number {}", component))?;
+
+			self.bytes(at + index * 4, &(component as f32).to_le_bytes());
+		}
+	}
+
+	#[try_fn]
+	fn pin_connections(&mut self, at: usize, pin_connections: &[PinConnection]) -> Result<()> {
+		self.array(at, pin_connections, 0x38, 8, |writer, at, pin_connection| {
+			writer.i32(at, pin_connection.from_id as _);
+			writer.i32(at + 0x4, pin_connection.to_id as _);
+			writer.string(at + 0x8, &pin_connection.from_pin_name);
+			writer.string(at + 0x18, &pin_connection.to_pin_name);
+			writer.variant(at + 0x28, &pin_connection.constant_pin_value)
+		})?;
+	}
+
+	#[try_fn]
+	fn external_pin_connections(&mut self, at: usize, pin_connections: &[ExternalPinConnection]) -> Result<()> {
+		self.array(at, pin_connections, 0x70, 8, |writer, at, pin_connection| {
+			writer.reference(at, &pin_connection.from_entity)?;
+			writer.reference(at + 0x20, &pin_connection.to_entity)?;
+			writer.string(at + 0x40, &pin_connection.from_pin_name);
+			writer.string(at + 0x50, &pin_connection.to_pin_name);
+			writer.variant(at + 0x60, &pin_connection.constant_pin_value)
+		})?;
+	}
+
+	/// Add the header and segments to the data.
+	fn finish(self) -> Vec<u8> {
+		let offsets = |offsets: &[u32]| {
+			let mut segment = (offsets.len() as u32).to_le_bytes().to_vec();
+			segment.extend(offsets.iter().flat_map(|x| x.to_le_bytes()));
+			segment
+		};
+
+		// The type table follows the type offsets: each type's index, -1, and its null-terminated name, aligned to 4 bytes
+		let mut type_ids = offsets(&self.type_offsets);
+		type_ids.extend((self.types.len() as u32).to_le_bytes());
+
+		for (index, name) in self.types.iter().enumerate() {
+			type_ids.extend((index as u32).to_le_bytes());
+			type_ids.extend((-1i32).to_le_bytes());
+			type_ids.extend((name.len() as u32 + 1).to_le_bytes());
+			type_ids.extend(name.as_bytes());
+			type_ids.push(0);
+			type_ids.resize(type_ids.len().next_multiple_of(4), 0);
+		}
+
+		let segments = [
+			(RELOCATION_SEGMENT, offsets(&self.relocations)),
+			(TYPE_ID_SEGMENT, type_ids),
+			(RUNTIME_RESOURCE_ID_SEGMENT, offsets(&self.runtime_resource_ids))
+		];
+
+		let mut resource = Vec::with_capacity(HEADER_SIZE + self.data.len());
+		resource.extend(b"BIN1");
+		resource.extend([0, ALIGNMENT, segments.len() as u8, 0]);
+		resource.extend((self.data.len() as u32).to_be_bytes());
+		resource.extend([0; 4]);
+		resource.extend(&self.data);
+
+		for (id, contents) in segments {
+			resource.extend(id.to_le_bytes());
+			resource.extend((contents.len() as u32).to_le_bytes());
+			resource.extend(contents);
+		}
+
+		resource
+	}
+}
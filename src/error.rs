@@ -0,0 +1,173 @@
+use std::fmt::Display;
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// An error returned by QuickEntity's public functions.
+///
+/// Errors which QuickEntity can identify are returned as a specific variant; anything else is returned as `Other`, with the usual context chain as its source.
+///
+/// Identified errors keep the context they occurred in (such as the function or patch operation that failed) as `Context` layers around them, so use `kind` to match on the error itself.
+#[derive(Error, Debug)]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum QuickEntityError {
+	/// A local entity ID was referenced that doesn't exist in the entity.
+	#[error("Entity ID not found: {entity_id}")]
+	EntityNotFound { entity_id: String },
+
+	/// A property was referenced that doesn't exist on the sub-entity.
+	#[error(
+		"Property {property_name} not found on entity {entity_id}{}",
+		platform.as_ref().map(|x| format!(" for platform {}", x)).unwrap_or_default()
+	)]
+	PropertyNotFound {
+		entity_id: String,
+		property_name: String,
+
+		/// The platform, if the property was platform-specific.
+		platform: Option<String>
+	},
+
+	/// A sub-entity has no platform-specific properties for a platform that was referenced.
+	#[error("Entity {entity_id} has no platform-specific properties for platform {platform}")]
+	PlatformNotFound { entity_id: String, platform: String },
+
+	/// A property value couldn't be converted, for instance an SMatrix43 without a rotation.
+	#[error("Invalid {property_type} value for property {property_name}")]
+	InvalidPropertyValue {
+		property_name: String,
+		property_type: String,
+		#[source]
+		source: anyhow::Error
+	},
+
+	/// A resource was used that isn't one of the entity's dependencies.
+	#[error("Nonexistent dependency: {hash}")]
+	NonexistentDependency { hash: String },
+
+	/// Patches can't be generated between entities of differing QuickEntity versions.
+	#[error("Can't create patches between differing QuickEntity versions ({original} and {modified})!")]
+	VersionMismatch { original: f64, modified: f64 },
+
+	/// A patch being merged or composed was made for a different entity than the base.
+	#[error("Patch {index} is for {found}, but the base entity is {expected}")]
+	PatchEntityMismatch {
+		/// The index of the patch among those being merged.
+		index: usize,
+
+		/// The factory or blueprint hash of the base entity.
+		expected: String,
+
+		/// The corresponding hash in the patch.
+		found: String
+	},
+
+	/// A patch was of a patch version that can't be applied, because it was made by a newer version of QuickEntity than this one.
+	#[error(
+		"Patch version {version} is not supported (the latest supported version is {}); update QuickEntity to apply \
+		 this patch",
+		crate::migration::CURRENT_PATCH_VERSION
+	)]
+	UnsupportedPatchVersion { version: u8 },
+
+	/// An entity was of a QuickEntity format version that can't be loaded.
+	#[error(
+		"QuickEntity version {version} is not supported (supported versions are {:.1} to {:.1})",
+		crate::migration::MINIMUM_QUICKENTITY_VERSION,
+		crate::migration::CURRENT_QUICKENTITY_VERSION
+	)]
+	UnsupportedEntityVersion { version: f64 },
+
+	/// An array patch operation referred to an item that isn't in the array.
+	#[error("Couldn't find value in array patch: {value}")]
+	ArrayItemNotFound { value: Value },
+
+	/// A patch operation failed to apply.
+	#[error("Patch operation {index} failed")]
+	PatchOperationFailed {
+		/// The index of the operation in the patch.
+		index: usize,
+		#[source]
+		source: Box<QuickEntityError>
+	},
+
+	/// JSON couldn't be deserialised; the path is to the value that was invalid.
+	#[error("Couldn't deserialise JSON at {path}")]
+	Deserialisation {
+		path: String,
+		#[source]
+		source: serde_json::Error
+	},
+
+	/// An error wrapped in the context it occurred in.
+	#[error("{context}")]
+	Context {
+		context: String,
+		#[source]
+		source: Box<QuickEntityError>
+	},
+
+	/// Any other error.
+	#[error(transparent)]
+	Other(anyhow::Error)
+}
+
+impl QuickEntityError {
+	/// Wrap this error with some context, in the same way as `anyhow::Error::context`.
+	pub fn context<C>(self, context: C) -> anyhow::Error
+	where
+		C: Display + Send + Sync + 'static
+	{
+		anyhow::Error::from(self).context(context)
+	}
+
+	/// Get the error itself, without any context it was wrapped in.
+	pub fn kind(&self) -> &QuickEntityError {
+		match self {
+			QuickEntityError::Context { source, .. } => source.kind(),
+			x => x
+		}
+	}
+
+	/// Mark an error that occurred while converting a property's value as belonging to that property, unless it's already more specific.
+	pub(crate) fn for_property(source: anyhow::Error, property_name: &str, property_type: &str) -> Self {
+		match Self::from(source) {
+			QuickEntityError::Other(source) => QuickEntityError::InvalidPropertyValue {
+				property_name: property_name.to_owned(),
+				property_type: property_type.to_owned(),
+				source
+			},
+
+			x => x
+		}
+	}
+}
+
+impl From<anyhow::Error> for QuickEntityError {
+	fn from(error: anyhow::Error) -> Self {
+		// The context messages around the first QuickEntityError in the chain, outermost first
+		let mut context = vec![];
+
+		for cause in error.chain() {
+			match cause.downcast_ref::<QuickEntityError>() {
+				None => context.push(cause.to_string()),
+				Some(QuickEntityError::Other(_)) => return QuickEntityError::Other(error),
+				Some(_) => break
+			}
+		}
+
+		match error.downcast::<QuickEntityError>() {
+			Ok(source) => context
+				.into_iter()
+				.rev()
+				.fold(source, |source, context| QuickEntityError::Context {
+					context,
+					source: Box::new(source)
+				}),
+
+			Err(error) => QuickEntityError::Other(error)
+		}
+	}
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use fn_error_context::context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use specta::Type;
+use tryvial::try_fn;
+
+use crate::qn_structs::{Dependency, DependencyWithFlag, Entity};
+
+#[cfg(feature = "rune")]
+pub fn rune_module() -> Result<rune::Module, rune::ContextError> {
+	let mut module = rune::Module::with_crate_item("quickentity_rs", ["hash"])?;
+
+	module.ty::<HashList>()?;
+	module.ty::<HashListMode>()?;
+	module.function_meta(HashList::from_hash_list__meta)?;
+	module.function_meta(HashList::insert__meta)?;
+	module.function_meta(HashList::path__meta)?;
+	module.function_meta(path_to_runtime_id__meta)?;
+	module.function_meta(normalise_hash__meta)?;
+	module.function_meta(hashes_match__meta)?;
+
+	Ok(module)
+}
+
+/// Whether a hash is an IOI path, such as `[modules:/zentity.class].pc_entitytype`, rather than a runtime ID.
+pub fn is_path(hash: &str) -> bool {
+	hash.contains(':')
+}
+
+/// Get the runtime ID of an IOI path: the first eight bytes of the MD5 of the lowercased path, with the top byte cleared, as 16 uppercase hex digits.
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn path_to_runtime_id(path: &str) -> String {
+	let digest = md5::compute(path.to_lowercase());
+
+	format!(
+		"{:016X}",
+		u64::from_be_bytes(digest[..8].try_into().expect("MD5 digests are 16 bytes")) & 0x00FF_FFFF_FFFF_FFFF
+	)
+}
+
+/// Remove the path a hash was annotated with by a hash list, if any.
+pub fn strip_annotation(hash: &str) -> &str {
+	match hash.split_once(' ') {
+		Some((id, _)) if id.len() == 16 && id.chars().all(|x| x.is_ascii_hexdigit()) => id,
+		_ => hash
+	}
+}
+
+/// Normalise a hash to its runtime ID, hashing it if it's a path.
+///
+/// Hashes which are neither paths nor hex are left as they are.
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn normalise_hash(hash: &str) -> String {
+	let hash = strip_annotation(hash);
+
+	if is_path(hash) {
+		path_to_runtime_id(hash)
+	} else if let Ok(id) = u64::from_str_radix(hash, 16) {
+		format!("{:016X}", id)
+	} else {
+		hash.to_owned()
+	}
+}
+
+/// Whether two hashes refer to the same resource, whether each is given as a path or a runtime ID.
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn hashes_match(a: &str, b: &str) -> bool {
+	a == b || normalise_hash(a) == normalise_hash(b)
+}
+
+/// Get the numeric form of a hash, hashing it first if it's a path.
+#[try_fn]
+#[context("Failure parsing hash {}", hash)]
+pub fn parse_hash(hash: &str) -> Result<u64> {
+	let hash = strip_annotation(hash);
+
+	if is_path(hash) {
+		u64::from_str_radix(&path_to_runtime_id(hash), 16)?
+	} else {
+		u64::from_str_radix(hash, 16)?
+	}
+}
+
+/// A list of known IOI paths, used to make the runtime IDs in converted entities readable.
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::hash))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct HashList {
+	/// Paths by runtime ID.
+	paths: HashMap<String, String>
+}
+
+impl HashList {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a hash list from the contents of a hash list file.
+	///
+	/// Each line is either a runtime ID and its path separated by a comma, as in `00123456789ABCDE.TEMP,[assembly:/...].pc_entitytype` (the resource type after the ID is optional), or just a path. Blank lines and lines starting with `#` are ignored.
+	#[try_fn]
+	#[context("Failure reading hash list")]
+	#[cfg_attr(feature = "rune", rune::function(keep, path = Self::from_hash_list))]
+	pub fn from_hash_list(hash_list: &str) -> Result<Self> {
+		let mut list = Self::new();
+
+		for line in hash_list.lines().map(str::trim) {
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			match line.split_once(',') {
+				Some((id, path)) => {
+					let id = id.split_once('.').map(|(id, _)| id).unwrap_or(id);
+
+					if !path.is_empty() {
+						list.paths.insert(
+							format!(
+								"{:016X}",
+								u64::from_str_radix(id, 16).with_context(|| format!("Invalid runtime ID: {}", id))?
+							),
+							path.to_owned()
+						);
+					}
+				}
+
+				None => list.insert(line)
+			}
+		}
+
+		list
+	}
+
+	/// Add a path to the list.
+	#[cfg_attr(feature = "rune", rune::function(keep, instance))]
+	pub fn insert(&mut self, path: &str) {
+		self.paths.insert(path_to_runtime_id(path), path.to_owned());
+	}
+
+	/// Get the path of a hash, if it's known.
+	#[cfg_attr(feature = "rune", rune::function(keep, instance))]
+	pub fn path(&self, hash: &str) -> Option<String> {
+		self.paths.get(&normalise_hash(hash)).cloned()
+	}
+
+	pub fn len(&self) -> usize {
+		self.paths.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.paths.is_empty()
+	}
+}
+
+/// How the paths in a hash list are shown in a converted entity.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::hash))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum HashListMode {
+	/// Keep runtime IDs, following them with their paths (as in `00123456789ABCDE [assembly:/...].pc_entitytype`).
+	#[default]
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Annotate,
+
+	/// Replace runtime IDs with their paths.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Replace
+}
+
+impl FromStr for HashListMode {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"annotate" => Ok(Self::Annotate),
+			"replace" => Ok(Self::Replace),
+			_ => Err(format!("Unknown hash list mode {} (expected annotate or replace)", s))
+		}
+	}
+}
+
+/// Annotate or replace the runtime IDs in an entity with their paths from a hash list.
+///
+/// This covers the entity's own hashes, sub-entity factories and blueprints, ZRuntimeResourceID values, external scenes and dependencies. Hashes which are already paths or aren't in the list are left as they are.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn apply_hash_list(entity: &mut Entity, hash_list: &HashList, mode: HashListMode) {
+	map_hashes(entity, |hash| {
+		if is_path(hash) {
+			return None;
+		}
+
+		let path = hash_list.path(hash)?;
+
+		Some(match mode {
+			HashListMode::Annotate => format!("{} {}", strip_annotation(hash), path),
+			HashListMode::Replace => path
+		})
+	});
+}
+
+/// Remove the paths hashes in an entity were annotated with by a hash list.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn strip_annotations(entity: &mut Entity) {
+	map_hashes(entity, |hash| {
+		let stripped = strip_annotation(hash);
+		(stripped != hash).then(|| stripped.to_owned())
+	});
+}
+
+/// Call a function on each hash in an entity, replacing the hash with its result if it returns one.
+fn map_hashes(entity: &mut Entity, f: impl Fn(&str) -> Option<String>) {
+	let map = |hash: &mut String| {
+		if let Some(new) = f(hash) {
+			*hash = new;
+		}
+	};
+
+	let map_value = |property_type: &str, value: &mut Value| match property_type {
+		"ZRuntimeResourceID" => map_resource_id(value, &map),
+
+		"TArray<ZRuntimeResourceID>" => {
+			for value in value.as_array_mut().into_iter().flatten() {
+				map_resource_id(value, &map);
+			}
+		}
+
+		_ => {}
+	};
+
+	map(&mut entity.factory_hash);
+	map(&mut entity.blueprint_hash);
+
+	for sub_entity in entity.entities.values_mut() {
+		map(&mut sub_entity.factory);
+		map(&mut sub_entity.blueprint);
+
+		for property in sub_entity.properties.iter_mut().flatten().map(|(_, x)| x).chain(
+			sub_entity
+				.platform_specific_properties
+				.iter_mut()
+				.flatten()
+				.flat_map(|(_, x)| x.values_mut())
+		) {
+			map_value(&property.property_type, &mut property.value);
+		}
+	}
+
+	for property_override in &mut entity.property_overrides {
+		for property in property_override.properties.values_mut() {
+			map_value(&property.property_type, &mut property.value);
+		}
+	}
+
+	for scene in &mut entity.external_scenes {
+		map(scene);
+	}
+
+	for dependency in entity
+		.extra_factory_dependencies
+		.iter_mut()
+		.chain(entity.extra_blueprint_dependencies.iter_mut())
+	{
+		match dependency {
+			Dependency::Short(hash) => map(hash),
+			Dependency::Full(DependencyWithFlag { resource, .. }) => map(resource)
+		}
+	}
+}
+
+/// ZRuntimeResourceIDs are either a hash or an object with the hash as its resource.
+fn map_resource_id(value: &mut Value, map: &impl Fn(&mut String)) {
+	let hash = match value {
+		Value::Object(obj) => obj.get_mut("resource"),
+		value => Some(value)
+	};
+
+	if let Some(Value::String(hash)) = hash {
+		map(hash);
+	}
+}
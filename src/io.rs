@@ -0,0 +1,341 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use anyhow::Context;
+use fn_error_context::context;
+use hitman_commons::{
+	resourcelib::{EntityBlueprint, EntityBlueprintLegacy, EntityFactory, EntityFactoryLegacy},
+	rpkg_tool::RpkgResourceMeta
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{
+	ser::{Formatter, PrettyFormatter},
+	to_value, Map, Number, Serializer, Value
+};
+use tryvial::try_fn;
+
+use crate::error::QuickEntityError;
+use crate::patch_structs::Patch;
+use crate::qn_structs::Entity;
+
+/// Read a QuickEntity entity from JSON.
+#[try_fn]
+#[context("Failure reading entity")]
+pub fn read_entity(reader: impl Read) -> Result<Entity, QuickEntityError> {
+	read_entity_from_slice(&read_all(reader)?)?
+}
+
+/// Read a QuickEntity entity from JSON.
+pub fn read_entity_from_slice(data: &[u8]) -> Result<Entity, QuickEntityError> {
+	from_slice(data)
+}
+
+/// Read a QuickEntity patch from JSON.
+#[try_fn]
+#[context("Failure reading patch")]
+pub fn read_patch(reader: impl Read) -> Result<Patch, QuickEntityError> {
+	read_patch_from_slice(&read_all(reader)?)?
+}
+
+/// Read a QuickEntity patch from JSON.
+pub fn read_patch_from_slice(data: &[u8]) -> Result<Patch, QuickEntityError> {
+	from_slice(data)
+}
+
+/// Read a factory (TEMP) from ResourceTool JSON, in either the modern format or the legacy format used by HITMAN (2016).
+#[try_fn]
+#[context("Failure reading factory")]
+pub fn read_factory(reader: impl Read) -> Result<EntityFactory, QuickEntityError> {
+	read_factory_from_slice(&read_all(reader)?)?
+}
+
+/// Read a factory (TEMP) from ResourceTool JSON, in either the modern format or the legacy format used by HITMAN (2016).
+#[try_fn]
+pub fn read_factory_from_slice(data: &[u8]) -> Result<EntityFactory, QuickEntityError> {
+	let value: Value = from_slice(data)?;
+
+	if is_legacy(&value) {
+		from_value::<EntityFactoryLegacy>(value)?.into_modern()
+	} else {
+		from_value(value)?
+	}
+}
+
+/// Read a blueprint (TBLU) from ResourceTool JSON, in either the modern format or the legacy format used by HITMAN (2016).
+#[try_fn]
+#[context("Failure reading blueprint")]
+pub fn read_blueprint(reader: impl Read) -> Result<EntityBlueprint, QuickEntityError> {
+	read_blueprint_from_slice(&read_all(reader)?)?
+}
+
+/// Read a blueprint (TBLU) from ResourceTool JSON, in either the modern format or the legacy format used by HITMAN (2016).
+#[try_fn]
+pub fn read_blueprint_from_slice(data: &[u8]) -> Result<EntityBlueprint, QuickEntityError> {
+	let value: Value = from_slice(data)?;
+
+	if is_legacy(&value) {
+		from_value::<EntityBlueprintLegacy>(value)?.into_modern()
+	} else {
+		from_value(value)?
+	}
+}
+
+/// Read a resource meta from RPKG tool JSON.
+#[try_fn]
+#[context("Failure reading meta")]
+pub fn read_meta(reader: impl Read) -> Result<RpkgResourceMeta, QuickEntityError> {
+	read_meta_from_slice(&read_all(reader)?)?
+}
+
+/// Read a resource meta from RPKG tool JSON.
+pub fn read_meta_from_slice(data: &[u8]) -> Result<RpkgResourceMeta, QuickEntityError> {
+	from_slice(data)
+}
+
+/// A format QuickEntity entities and patches can be stored in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+	#[default]
+	Json,
+	Yaml,
+	Ron
+}
+
+impl Format {
+	/// Get the format of a file from its extension: `.yaml` or `.yml` for YAML, `.ron` for RON and JSON otherwise.
+	pub fn from_path(path: impl AsRef<Path>) -> Self {
+		match path
+			.as_ref()
+			.extension()
+			.and_then(|x| x.to_str())
+			.map(|x| x.to_lowercase())
+			.as_deref()
+		{
+			Some("yaml" | "yml") => Self::Yaml,
+			Some("ron") => Self::Ron,
+			_ => Self::Json
+		}
+	}
+}
+
+/// Read a value, such as an entity or a patch, in the given format.
+pub fn from_slice_in_format<T: DeserializeOwned>(data: &[u8], format: Format) -> Result<T, QuickEntityError> {
+	match format {
+		Format::Json => from_slice(data),
+
+		Format::Yaml => serde_path_to_error::deserialize(serde_yaml::Deserializer::from_slice(data))
+			.map_err(|error| path_error(error, "YAML").into()),
+
+		Format::Ron => {
+			let mut deserializer = ron::Deserializer::from_bytes(data).context("Couldn't parse RON")?;
+
+			let value =
+				serde_path_to_error::deserialize(&mut deserializer).map_err(|error| path_error(error, "RON"))?;
+
+			deserializer.end().context("Unexpected data after RON value")?;
+
+			Ok(value)
+		}
+	}
+}
+
+/// Serialise a value, such as an entity or a patch, in the given format.
+///
+/// The value is canonicalised first (see `canonicalise`) in every format, so map keys are sorted and integral floats written as integers. JSON is then written in canonical form (see `write_canonical_json`). YAML and RON keep each struct's fields in order, and write enum variants as YAML tags and RON enums respectively, so only the value's maps and numbers are canonical in those formats.
+#[try_fn]
+pub fn to_vec_in_format<T: Serialize + DeserializeOwned>(
+	contents: &T,
+	format: Format
+) -> Result<Vec<u8>, QuickEntityError> {
+	match format {
+		Format::Json => to_vec_canonical(contents)?,
+
+		Format::Yaml => serde_yaml::to_string(&canonicalise_typed(contents)?)
+			.context("Couldn't serialise value as YAML")?
+			.into_bytes(),
+
+		Format::Ron => {
+			let mut data = ron::ser::to_string_pretty(
+				&canonicalise_typed(contents)?,
+				ron::ser::PrettyConfig::new().indentor("\t".to_owned())
+			)
+			.context("Couldn't serialise value as RON")?;
+
+			data.push('\n');
+			data.into_bytes()
+		}
+	}
+}
+
+/// Canonicalise a value (see `canonicalise`) while keeping its type, so that its enums and structs are still serialised as such.
+#[try_fn]
+fn canonicalise_typed<T: Serialize + DeserializeOwned>(contents: &T) -> Result<T, QuickEntityError> {
+	from_value(canonicalise(
+		to_value(contents).context("Couldn't convert value to JSON")?
+	))?
+}
+
+/// Whether ResourceTool JSON is in the legacy format used by HITMAN (2016), which stores sub-entities under `entityTemplates`.
+pub fn is_legacy(value: &Value) -> bool {
+	value.get("entityTemplates").is_some()
+}
+
+/// Write a value as JSON, formatting floats in their shortest form.
+#[try_fn]
+#[context("Failure writing JSON")]
+pub fn write_json<T: ?Sized + Serialize>(writer: impl Write, contents: &T) -> Result<(), QuickEntityError> {
+	contents
+		.serialize(&mut Serializer::with_formatter(writer, FloatFormatter))
+		.context("Couldn't serialise value")?;
+}
+
+/// Serialise a value to JSON, formatting floats in their shortest form.
+#[try_fn]
+pub fn to_vec_float_format<T: ?Sized + Serialize>(contents: &T) -> Result<Vec<u8>, QuickEntityError> {
+	let mut writer = Vec::with_capacity(128);
+	write_json(&mut writer, contents)?;
+	writer
+}
+
+/// Write a value as canonical JSON: keys sorted, floats in their shortest round-trip form and integral floats (including -0) written as integers, indented with tabs and followed by a newline.
+///
+/// The same value always produces the same bytes, and reading the output back gives a value that compares equal to the canonical form, so QN files written this way diff cleanly.
+#[try_fn]
+#[context("Failure writing canonical JSON")]
+pub fn write_canonical_json<T: ?Sized + Serialize>(
+	mut writer: impl Write,
+	contents: &T
+) -> Result<(), QuickEntityError> {
+	canonicalise(to_value(contents).context("Couldn't convert value to JSON")?)
+		.serialize(&mut Serializer::with_formatter(
+			&mut writer,
+			PrettyFormatter::with_indent(b"\t")
+		))
+		.context("Couldn't serialise value")?;
+
+	writer.write_all(b"\n").context("Couldn't write value")?;
+}
+
+/// Serialise a value to canonical JSON; see `write_canonical_json`.
+#[try_fn]
+pub fn to_vec_canonical<T: ?Sized + Serialize>(contents: &T) -> Result<Vec<u8>, QuickEntityError> {
+	let mut writer = Vec::with_capacity(128);
+	write_canonical_json(&mut writer, contents)?;
+	writer
+}
+
+/// Convert a JSON value to canonical form, sorting the keys of objects and writing integral floats as integers.
+pub fn canonicalise(value: Value) -> Value {
+	match value {
+		Value::Object(object) => {
+			let mut entries = object
+				.into_iter()
+				.map(|(key, value)| (key, canonicalise(value)))
+				.collect::<Vec<_>>();
+
+			entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+			Value::Object(entries.into_iter().collect::<Map<_, _>>())
+		}
+
+		Value::Array(array) => Value::Array(array.into_iter().map(canonicalise).collect()),
+
+		Value::Number(number) => Value::Number(canonicalise_number(number)),
+
+		value => value
+	}
+}
+
+/// The largest integer up to which every integer can be represented exactly as a float.
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+fn canonicalise_number(number: Number) -> Number {
+	match number.as_f64() {
+		Some(x) if number.is_f64() && x.fract() == 0.0 && x.abs() <= MAX_EXACT_INTEGER => Number::from(x as i64),
+		_ => number
+	}
+}
+
+#[try_fn]
+fn read_all(mut reader: impl Read) -> anyhow::Result<Vec<u8>> {
+	let mut data = vec![];
+	reader.read_to_end(&mut data).context("Couldn't read data")?;
+	data
+}
+
+pub(crate) fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, QuickEntityError> {
+	serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(data)).map_err(deserialisation_error)
+}
+
+pub(crate) fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, QuickEntityError> {
+	serde_path_to_error::deserialize(value).map_err(deserialisation_error)
+}
+
+fn path_error<E: std::error::Error + Send + Sync + 'static>(
+	error: serde_path_to_error::Error<E>,
+	format: &str
+) -> anyhow::Error {
+	let path = error.path().to_string();
+	anyhow::Error::new(error.into_inner()).context(format!("Couldn't deserialise {} at {}", format, path))
+}
+
+fn deserialisation_error(error: serde_path_to_error::Error<serde_json::Error>) -> QuickEntityError {
+	QuickEntityError::Deserialisation {
+		path: error.path().to_string(),
+		source: error.into_inner()
+	}
+}
+
+/// A JSON formatter which writes floats in their shortest form.
+#[derive(Clone, Debug)]
+pub struct FloatFormatter;
+
+impl Formatter for FloatFormatter {
+	#[inline]
+	fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+	where
+		W: ?Sized + io::Write
+	{
+		writer.write_all(value.to_string().as_bytes())
+	}
+
+	#[inline]
+	fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+	where
+		W: ?Sized + io::Write
+	{
+		writer.write_all(
+			if value == 0.0 {
+				"0".to_string()
+			} else {
+				value.to_string()
+			}
+			.as_bytes()
+		)
+	}
+
+	/// Writes a number that has already been rendered to a string.
+	#[inline]
+	fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+	where
+		W: ?Sized + io::Write
+	{
+		let x = value.parse::<f64>();
+		if let Ok(y) = x {
+			if value.parse::<u64>().is_err() || y.to_string() == value.parse::<u64>().unwrap().to_string() {
+				writer.write_all(
+					if y.to_string() == "-0" {
+						"0".to_string()
+					} else {
+						y.to_string()
+					}
+					.as_bytes()
+				)
+			} else {
+				writer.write_all(value.as_bytes())
+			}
+		} else {
+			writer.write_all(value.as_bytes())
+		}
+	}
+}
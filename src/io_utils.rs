@@ -1,138 +1,134 @@
-use hitman_commons::resourcelib::{EntityBlueprint, EntityBlueprintLegacy, EntityFactory, EntityFactoryLegacy};
+use anyhow::{bail, Context, Result};
+use hitman_commons::resourcelib::{EntityBlueprint, EntityFactory};
 use hitman_commons::rpkg_tool::RpkgResourceMeta;
+use quickentity_rs::binary::{
+	read_binary_blueprint, read_binary_factory, write_binary_blueprint, write_binary_factory
+};
+use quickentity_rs::hash::HashList;
+use quickentity_rs::io::{
+	from_slice_in_format, read_blueprint_from_slice, read_factory_from_slice, read_meta_from_slice,
+	to_vec_float_format, to_vec_in_format, Format
+};
+use quickentity_rs::meta::{read_binary_meta, write_binary_meta};
 use quickentity_rs::patch_structs::Patch;
+use quickentity_rs::property_names::PropertyNames;
 use quickentity_rs::qn_structs::Entity;
-
+use serde::de::DeserializeOwned;
 use serde::Serialize;
-use serde_json::ser::Formatter;
-use serde_json::{from_value, Serializer, Value};
-use std::io;
-use std::{fs, io::Read};
-
-pub fn read_as_entity(path: &str) -> Entity {
-	serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&{
-		let mut vec = Vec::new();
-		fs::File::open(path)
-			.expect("Failed to open file")
-			.read_to_end(&mut vec)
-			.expect("Failed to read file");
-		vec
-	}))
-	.expect("Failed to parse file")
+use std::fs;
+
+fn read(path: &str) -> Result<Vec<u8>> {
+	fs::read(path).with_context(|| format!("Failed to read {}", path))
 }
 
-pub fn read_as_rtfactory(path: &str) -> EntityFactory {
-	let x = fs::read(path).expect("Failed to read file");
-	let val: Value =
-		serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&x)).expect("Failed to parse file");
+/// Read an entity, patch or other QuickEntity value, in the format given by the path's extension.
+pub fn read_as_value<T: DeserializeOwned>(path: &str) -> Result<T> {
+	from_slice_in_format(&read(path)?, Format::from_path(path)).with_context(|| format!("Failed to parse {}", path))
+}
 
-	if val.get("entityTemplates").is_some() {
-		from_value::<EntityFactoryLegacy>(val)
-			.expect("Failed to read file as RT struct")
-			.into_modern()
-	} else {
-		serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&x))
-			.expect("Failed to read file as RT struct")
-	}
+pub fn read_as_entity(path: &str) -> Result<Entity> {
+	read_as_value(path)
 }
 
-pub fn read_as_rtblueprint(path: &str) -> EntityBlueprint {
-	let x = fs::read(path).expect("Failed to read file");
-	let val: Value =
-		serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&x)).expect("Failed to parse file");
+/// Whether a factory or blueprint path is to a binary TEMP/TBLU rather than ResourceTool JSON.
+fn is_binary_resource_path(path: &str) -> bool {
+	let path = path.to_lowercase();
+	path.ends_with(".temp") || path.ends_with(".tblu")
+}
 
-	if val.get("entityTemplates").is_some() {
-		from_value::<EntityBlueprintLegacy>(val)
-			.expect("Failed to read file as RT struct")
-			.into_modern()
+pub fn read_as_rtfactory(path: &str) -> Result<EntityFactory> {
+	if is_binary_resource_path(path) {
+		read_binary_factory(&read(path)?)
 	} else {
-		serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&x))
-			.expect("Failed to read file as RT struct")
+		read_factory_from_slice(&read(path)?)
 	}
+	.with_context(|| format!("Failed to parse {}", path))
 }
 
-pub fn read_as_meta(path: &str) -> RpkgResourceMeta {
-	serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&{
-		let mut vec = Vec::new();
-		fs::File::open(path)
-			.expect("Failed to open file")
-			.read_to_end(&mut vec)
-			.expect("Failed to read file");
-		vec
-	}))
-	.expect("Failed to parse file")
+pub fn read_as_rtblueprint(path: &str) -> Result<EntityBlueprint> {
+	if is_binary_resource_path(path) {
+		read_binary_blueprint(&read(path)?)
+	} else {
+		read_blueprint_from_slice(&read(path)?)
+	}
+	.with_context(|| format!("Failed to parse {}", path))
 }
 
-pub fn read_as_patch(path: &str) -> Patch {
-	serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&{
-		let mut vec = Vec::new();
-		fs::File::open(path)
-			.expect("Failed to open file")
-			.read_to_end(&mut vec)
-			.expect("Failed to read file");
-		vec
-	}))
-	.expect("Failed to parse file")
+/// Write a factory as binary if the path ends in .temp, or as ResourceTool JSON (for HITMAN (2016) if `h1` is set) otherwise.
+pub fn write_rtfactory(path: &str, factory: EntityFactory, h1: bool) -> Result<()> {
+	let data = if is_binary_resource_path(path) {
+		if h1 {
+			bail!("HITMAN (2016) factories can't be written as binary");
+		}
+
+		write_binary_factory(&factory)?
+	} else if h1 {
+		to_vec_float_format(&factory.into_legacy())?
+	} else {
+		to_vec_float_format(&factory)?
+	};
+
+	fs::write(path, data).with_context(|| format!("Failed to write {}", path))
 }
 
-pub fn to_vec_float_format<W>(contents: &W) -> Vec<u8>
-where
-	W: ?Sized + Serialize
-{
-	let mut writer = Vec::with_capacity(128);
+/// Write a blueprint as binary if the path ends in .tblu, or as ResourceTool JSON (for HITMAN (2016) if `h1` is set) otherwise.
+pub fn write_rtblueprint(path: &str, blueprint: EntityBlueprint, h1: bool) -> Result<()> {
+	let data = if is_binary_resource_path(path) {
+		if h1 {
+			bail!("HITMAN (2016) blueprints can't be written as binary");
+		}
 
-	let mut ser = Serializer::with_formatter(&mut writer, FloatFormatter);
-	contents.serialize(&mut ser).unwrap();
+		write_binary_blueprint(&blueprint)?
+	} else if h1 {
+		to_vec_float_format(&blueprint.into_legacy())?
+	} else {
+		to_vec_float_format(&blueprint)?
+	};
 
-	writer
+	fs::write(path, data).with_context(|| format!("Failed to write {}", path))
 }
 
-#[derive(Clone, Debug)]
-struct FloatFormatter;
+/// Whether a meta path is to a binary meta rather than RPKG tool meta JSON.
+fn is_binary_meta_path(path: &str) -> bool {
+	path.to_lowercase().ends_with(".meta")
+}
 
-impl Formatter for FloatFormatter {
-	#[inline]
-	fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
-	where
-		W: ?Sized + io::Write
-	{
-		writer.write_all(value.to_string().as_bytes())
+pub fn read_as_meta(path: &str) -> Result<RpkgResourceMeta> {
+	if is_binary_meta_path(path) {
+		read_binary_meta(&read(path)?)
+	} else {
+		read_meta_from_slice(&read(path)?)
 	}
+	.with_context(|| format!("Failed to parse {}", path))
+}
 
-	#[inline]
-	fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
-	where
-		W: ?Sized + io::Write
-	{
-		writer.write_all(value.to_string().as_bytes())
-	}
+pub fn read_as_patch(path: &str) -> Result<Patch> {
+	read_as_value(path)
+}
 
-	/// Writes a number that has already been rendered to a string.
-	#[inline]
-	fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
-	where
-		W: ?Sized + io::Write
-	{
-		let x = value.parse::<f64>();
-		if let Ok(y) = x {
-			if value.parse::<u64>().is_err() || y.to_string() == value.parse::<u64>().unwrap().to_string() {
-				writer
-					.write_all(
-						if y.to_string() == "-0" {
-							"0".to_string()
-						} else {
-							y.to_string()
-						}
-						.as_bytes()
-					)
-					.unwrap();
-			} else {
-				writer.write_all(value.as_bytes()).unwrap();
-			}
-		} else {
-			writer.write_all(value.as_bytes()).unwrap();
-		}
+/// Write an entity, patch or other QuickEntity value, in the format given by the path's extension.
+pub fn write_as_value<T: Serialize + DeserializeOwned>(path: &str, contents: &T) -> Result<()> {
+	fs::write(path, to_vec_in_format(contents, Format::from_path(path))?)
+		.with_context(|| format!("Failed to write {}", path))
+}
 
-		Ok(())
-	}
+pub fn read_as_property_names(path: &str) -> Result<PropertyNames> {
+	Ok(PropertyNames::from_dictionary(
+		&fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?
+	))
+}
+
+pub fn read_as_hash_list(path: &str) -> Result<HashList> {
+	HashList::from_hash_list(&fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?)
+		.with_context(|| format!("Failed to parse {}", path))
+}
+
+pub fn write_meta(path: &str, meta: &RpkgResourceMeta) -> Result<()> {
+	let data = if is_binary_meta_path(path) {
+		write_binary_meta(meta)?
+	} else {
+		to_vec_float_format(meta)?
+	};
+
+	fs::write(path, data).with_context(|| format!("Failed to write {}", path))
 }
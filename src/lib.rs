@@ -1,8 +1,19 @@
 #![feature(try_find)]
 
+pub mod binary;
+pub mod error;
+pub mod hash;
+pub mod io;
+pub mod meta;
+pub mod migration;
 pub mod patch_structs;
+pub mod project;
+pub mod property_names;
 pub mod qn_structs;
+pub mod schema;
+pub mod typescript;
 pub mod util_structs;
+pub mod validation;
 
 use anyhow::{anyhow, bail, Context, Error, Result};
 use auto_context::auto_context;
@@ -16,33 +27,53 @@ use indexmap::IndexMap;
 use itertools::Itertools;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_json::{from_value, json, to_string, to_value, Value};
+use serde_json::{from_value, json, to_string, to_value, Map, Value};
 use similar::{capture_diff_slices, Algorithm, DiffOp};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use tryvial::try_fn;
 
+use error::QuickEntityError;
+use hash::{hashes_match, normalise_hash, strip_annotation};
+use migration::{check_patch_version, upgrade_patch_operation, CURRENT_PATCH_VERSION, CURRENT_QUICKENTITY_VERSION};
 use patch_structs::{
-	ArrayPatchOperation, Patch, PatchOperation, PropertyOverrideConnection, SetPlatformSpecificPropertyValue,
-	SetPropertyValue, SubEntityOperation
+	ArrayPatchOperation, FloatTolerance, MergeConflict, MergeResult, Patch, PatchGenOptions, PatchOperation,
+	PatchOperationReport, PatchOperationStatus, PatchReport, PropertyOverrideConnection,
+	SetPlatformSpecificPropertyValue, SetPropertyValue, SubEntityOperation, ValuePatchOperation
 };
 use qn_structs::{
-	Dependency, DependencyWithFlag, Entity, ExposedEntity, FullRef, OverriddenProperty, PinConnectionOverride,
-	PinConnectionOverrideDelete, Property, PropertyAlias, PropertyOverride, Ref, RefMaybeConstantValue,
-	RefWithConstantValue, SimpleProperty, SubEntity, SubType
+	ColourRepresentation, CommentEntity, ConvertOptions, Dependency, DependencyWithFlag, Entity, ExposedEntity,
+	FullRef, GuidCase, OverriddenProperty, PinConnectionOverride, PinConnectionOverrideDelete, Property, PropertyAlias,
+	PropertyOverride, Ref, RefMaybeConstantValue, RefWithConstantValue, SimpleProperty, SubEntity, SubType,
+	TransformRepresentation
 };
 use util_structs::{SMatrix43PropertyValue, ZGuidPropertyValue, ZRuntimeResourceIDPropertyValue};
 
 pub const RAD2DEG: f64 = 180.0 / std::f64::consts::PI;
 pub const DEG2RAD: f64 = std::f64::consts::PI / 180.0;
 
+/// The factory of the editor-only sub-entities that QN comments are stored as in RT entities.
+pub const COMMENT_ENTITY_FACTORY: &str = "[modules:/zcommententity.class].pc_entitytype";
+
+/// The blueprint of the editor-only sub-entities that QN comments are stored as in RT entities.
+pub const COMMENT_ENTITY_BLUEPRINT: &str = "[modules:/zcommententity.class].pc_entityblueprint";
+
 #[cfg(feature = "rune")]
 pub fn rune_install(ctx: &mut rune::Context) -> Result<(), rune::ContextError> {
 	ctx.install(qn_structs::rune_module()?)?;
 	ctx.install(patch_structs::rune_module()?)?;
+	ctx.install(validation::rune_module()?)?;
+	ctx.install(migration::rune_module()?)?;
+	ctx.install(property_names::rune_module()?)?;
+	ctx.install(hash::rune_module()?)?;
 
 	let mut module = rune::Module::with_crate("quickentity_rs")?;
+	module.ty::<QuickEntityError>()?;
 	module.function_meta(apply_patch__meta)?;
+	module.function_meta(apply_patch_with_report__meta)?;
 	module.function_meta(generate_patch__meta)?;
+	module.function_meta(generate_patch_with__meta)?;
+	module.function_meta(invert_patch__meta)?;
 	module.function_meta(convert_to_qn__meta)?;
 	module.function_meta(convert_to_rt__meta)?;
 	ctx.install(module)?;
@@ -114,15 +145,18 @@ where
 
 trait PermissiveUnwrap {
 	/// Throw away the value of this Option. If it was None, return Err or Ok depending on whether permissive mode is enabled, for use with `?`. If it was Some, return Ok.
-	fn permit(&self, permissive: bool, message: &str) -> Result<()>;
+	///
+	/// Permitted failures are logged and recorded in `warnings`.
+	fn permit(&self, permissive: bool, message: &str, warnings: &mut Vec<String>) -> Result<()>;
 }
 
 impl<T> PermissiveUnwrap for Option<T> {
 	#[context("Permissive unwrap failure")]
-	fn permit(&self, permissive: bool, message: &str) -> Result<()> {
+	fn permit(&self, permissive: bool, message: &str, warnings: &mut Vec<String>) -> Result<()> {
 		if self.is_none() {
 			if permissive {
 				log::warn!("QuickEntity warning: {}", message);
+				warnings.push(message.to_owned());
 
 				Ok(())
 			} else {
@@ -225,6 +259,13 @@ fn normalise_ref(reference: &Ref) -> Result<Ref> {
 	}
 }
 
+#[try_fn]
+#[context("Failure normalising ref value")]
+#[auto_context]
+fn normalise_ref_value(value: Value) -> Result<Value> {
+	to_value(normalise_ref(&from_value::<Ref>(value)?)?)?
+}
+
 #[try_fn]
 #[context("Failure checking property is roughly identical")]
 #[auto_context]
@@ -249,9 +290,15 @@ fn property_is_roughly_identical(p1: &OverriddenProperty, p2: &OverriddenPropert
 				true
 			};
 
-			p1.get("rotation").ctx? == p2.get("rotation").ctx?
-				&& p1.get("position").ctx? == p2.get("position").ctx?
-				&& scales_roughly_identical
+			// the rest of the transform (in whichever representation) must be identical
+			let without_scale = |x: &Map<String, Value>| {
+				x.iter()
+					.filter(|(key, _)| *key != "scale")
+					.map(|(key, value)| (key.to_owned(), value.to_owned()))
+					.collect::<Map<_, _>>()
+			};
+
+			without_scale(p1) == without_scale(p2) && scales_roughly_identical
 		} else {
 			p1.value == p2.value
 		}
@@ -263,1074 +310,1229 @@ fn property_is_roughly_identical(p1: &OverriddenProperty, p2: &OverriddenPropert
 #[auto_context]
 #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
 #[cfg_attr(feature = "rune", rune::function(keep))]
-pub fn apply_patch(entity: &mut Entity, patch: Patch, permissive: bool) -> Result<()> {
-	let patch: Vec<PatchOperation> = patch.patch;
+pub fn apply_patch(entity: &mut Entity, patch: Patch, permissive: bool) -> Result<(), QuickEntityError> {
+	check_patch_version(patch.patch_version)?;
 
-	let pool = rayon::ThreadPoolBuilder::new().build()?;
+	let pool = rayon::ThreadPoolBuilder::new().build().ctx?;
 	pool.install(|| {
-		for operation in patch {
-			match operation {
-				PatchOperation::SetRootEntity(value) => {
-					entity.root_entity = value;
-				}
+		for (index, operation) in patch.patch.into_iter().enumerate() {
+			for operation in upgrade_patch_operation(patch.patch_version, operation) {
+				apply_patch_operation(entity, operation, permissive, &mut vec![]).map_err(|source| {
+					QuickEntityError::PatchOperationFailed {
+						index,
+						source: Box::new(source.into())
+					}
+				})?;
+			}
+		}
 
-				PatchOperation::SetSubType(value) => {
-					entity.sub_type = value;
-				}
+		Ok::<_, QuickEntityError>(())
+	})?;
+}
 
-				PatchOperation::RemoveEntityByID(value) => {
-					entity.entities.shift_remove(&normalise_entity_id(&value)?).permit(
-						permissive,
-						"Couldn't remove entity by ID because entity did not exist in target!"
-					)?;
-				}
+/// Apply a patch, reporting whether each operation was applied, skipped (in permissive mode) or failed.
+///
+/// Unlike `apply_patch`, a failing operation doesn't stop the rest of the patch from being applied, so the entity may be left partially patched; apply to a clone if that matters.
+#[try_fn]
+#[context("Failure applying patch to entity with report")]
+#[auto_context]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn apply_patch_with_report(
+	entity: &mut Entity,
+	patch: Patch,
+	permissive: bool
+) -> Result<PatchReport, QuickEntityError> {
+	check_patch_version(patch.patch_version)?;
+
+	let pool = rayon::ThreadPoolBuilder::new().build().ctx?;
+	pool.install(|| PatchReport {
+		operations: patch
+			.patch
+			.into_iter()
+			.enumerate()
+			.map(|(index, operation)| {
+				let entity_id = get_patch_operation_entity_id(&operation);
+				let mut warnings = vec![];
 
-				PatchOperation::AddEntity(id, data) => {
-					entity.entities.insert(id, data);
+				let (status, reason) = match upgrade_patch_operation(patch.patch_version, operation)
+					.into_iter()
+					.try_for_each(|operation| apply_patch_operation(entity, operation, permissive, &mut warnings))
+				{
+					Ok(()) if warnings.is_empty() => (PatchOperationStatus::Applied, None),
+					Ok(()) => (PatchOperationStatus::Skipped, Some(warnings.join("; "))),
+					Err(err) => (PatchOperationStatus::Failed, Some(format!("{:#}", err)))
+				};
+
+				PatchOperationReport {
+					index,
+					status,
+					reason,
+					entity_id
 				}
+			})
+			.collect()
+	})
+}
 
-				PatchOperation::SubEntityOperation(entity_id, op) => {
-					let entity = entity
-						.entities
-						.get_mut(&normalise_entity_id(&entity_id)?)
-						.with_context(|| format!("SubEntityOperation couldn't find entity ID: {}!", entity_id))?;
+/// Get the ID of the sub-entity a patch operation targets, if it targets one.
+fn get_patch_operation_entity_id(operation: &PatchOperation) -> Option<String> {
+	match operation {
+		PatchOperation::RemoveEntityByID(entity_id)
+		| PatchOperation::AddEntity(entity_id, _)
+		| PatchOperation::SubEntityOperation(entity_id, _) => Some(entity_id.to_owned()),
 
-					match op {
-						SubEntityOperation::SetParent(value) => {
-							entity.parent = value;
-						}
+		_ => None
+	}
+}
 
-						SubEntityOperation::SetName(value) => {
-							entity.name = value;
-						}
+#[try_fn]
+#[context("Failure applying patch operation to entity")]
+#[auto_context]
+fn apply_patch_operation(
+	entity: &mut Entity,
+	operation: PatchOperation,
+	permissive: bool,
+	warnings: &mut Vec<String>
+) -> Result<()> {
+	match operation {
+		PatchOperation::SetRootEntity(value) => {
+			entity.root_entity = value;
+		}
 
-						SubEntityOperation::SetFactory(value) => {
-							entity.factory = value;
-						}
+		PatchOperation::SetSubType(value) => {
+			entity.sub_type = value;
+		}
 
-						SubEntityOperation::SetFactoryFlag(value) => {
-							entity.factory_flag = value;
-						}
+		PatchOperation::RemoveEntityByID(value) => {
+			entity.entities.shift_remove(&normalise_entity_id(&value)?).permit(
+				permissive,
+				"Couldn't remove entity by ID because entity did not exist in target!",
+				warnings
+			)?;
+		}
 
-						SubEntityOperation::SetBlueprint(value) => {
-							entity.blueprint = value;
-						}
+		PatchOperation::AddEntity(id, data) => {
+			entity.entities.insert(id, data);
+		}
 
-						SubEntityOperation::SetEditorOnly(value) => {
-							entity.editor_only = value;
-						}
+		PatchOperation::SubEntityOperation(entity_id, op) => {
+			let entity = entity
+				.entities
+				.get_mut(&normalise_entity_id(&entity_id)?)
+				.ok_or_else(|| QuickEntityError::EntityNotFound {
+					entity_id: entity_id.to_owned()
+				})?;
+
+			match op {
+				SubEntityOperation::SetParent(value) => {
+					entity.parent = value;
+				}
 
-						SubEntityOperation::AddProperty(name, data) => {
-							entity.properties.get_or_insert(Default::default()).insert(name, data);
-						}
+				SubEntityOperation::SetName(value) => {
+					entity.name = value;
+				}
 
-						SubEntityOperation::RemovePropertyByName(name) => {
-							entity
-								.properties
-								.as_mut()
-								.context("RemovePropertyByName couldn't find entity properties!")?
-								.shift_remove(&name)
-								.permit(permissive, "RemovePropertyByName couldn't find expected property!")?;
+				SubEntityOperation::SetFactory(value) => {
+					entity.factory = value;
+				}
 
-							if entity.properties.as_ref().ctx?.is_empty() {
-								entity.properties = None;
-							}
-						}
+				SubEntityOperation::SetFactoryFlag(value) => {
+					entity.factory_flag = value;
+				}
 
-						SubEntityOperation::SetPropertyType(name, value) => {
-							entity
-								.properties
-								.get_or_insert(Default::default())
-								.get_mut(&name)
-								.context("SetPropertyType couldn't find expected property!")?
-								.property_type = value;
-						}
+				SubEntityOperation::SetBlueprint(value) => {
+					entity.blueprint = value;
+				}
 
-						SubEntityOperation::SetPropertyValue(SetPropertyValue { property_name, value }) => {
-							entity
-								.properties
-								.get_or_insert(Default::default())
-								.get_mut(&property_name)
-								.context("SetPropertyValue couldn't find expected property!")?
-								.value = value;
-						}
+				SubEntityOperation::SetEditorOnly(value) => {
+					entity.editor_only = value;
+				}
 
-						SubEntityOperation::PatchArrayPropertyValue(property_name, array_patch) => {
-							let item_to_patch = entity
-								.properties
-								.get_or_insert(Default::default())
-								.get_mut(&property_name)
-								.context("PatchArrayPropertyValue couldn't find expected property!")?;
-
-							apply_array_patch(
-								&mut item_to_patch.value,
-								array_patch,
-								permissive,
-								item_to_patch.property_type == "TArray<SEntityTemplateReference>"
-							)?;
-						}
+				SubEntityOperation::AddProperty(name, data) => {
+					entity.properties.get_or_insert(Default::default()).insert(name, data);
+				}
 
-						SubEntityOperation::SetPropertyPostInit(name, value) => {
-							entity
-								.properties
-								.get_or_insert(Default::default())
-								.get_mut(&name)
-								.context("SetPropertyPostInit couldn't find expected property!")?
-								.post_init = if value { Some(true) } else { None };
-						}
+				SubEntityOperation::RemovePropertyByName(name) => {
+					entity
+						.properties
+						.as_mut()
+						.context("RemovePropertyByName couldn't find entity properties!")?
+						.shift_remove(&name)
+						.permit(
+							permissive,
+							"RemovePropertyByName couldn't find expected property!",
+							warnings
+						)?;
+
+					if entity.properties.as_ref().ctx?.is_empty() {
+						entity.properties = None;
+					}
+				}
 
-						SubEntityOperation::AddPlatformSpecificProperty(platform, name, data) => {
-							entity
-								.platform_specific_properties
-								.get_or_insert(Default::default())
-								.entry(platform)
-								.or_default()
-								.insert(name, data);
-						}
+				SubEntityOperation::SetPropertyType(name, value) => {
+					entity
+						.properties
+						.get_or_insert(Default::default())
+						.get_mut(&name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: name.to_owned(),
+							platform: None
+						})?
+						.property_type = value;
+				}
 
-						SubEntityOperation::RemovePlatformSpecificPropertiesForPlatform(name) => {
-							entity
-								.platform_specific_properties
-								.as_mut()
-								.context("RemovePSPropertiesForPlatform couldn't find properties!")?
-								.shift_remove(&name)
-								.permit(
-									permissive,
-									"RemovePSPropertiesForPlatform couldn't find platform to remove!"
-								)?;
-
-							if entity.platform_specific_properties.as_ref().ctx?.is_empty() {
-								entity.platform_specific_properties = None;
-							}
-						}
+				SubEntityOperation::SetPropertyValue(SetPropertyValue { property_name, value }) => {
+					entity
+						.properties
+						.get_or_insert(Default::default())
+						.get_mut(&property_name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: property_name.to_owned(),
+							platform: None
+						})?
+						.value = value;
+				}
 
-						SubEntityOperation::RemovePlatformSpecificPropertyByName(platform, name) => {
-							entity
-								.platform_specific_properties
-								.as_mut()
-								.context("RemovePSPropertyByName couldn't find properties!")?
-								.get_mut(&platform)
-								.context("RemovePSPropertyByName couldn't find platform!")?
-								.shift_remove(&name)
-								.permit(permissive, "RemovePSPropertyByName couldn't find property to remove!")?;
-
-							if entity
-								.platform_specific_properties
-								.as_ref()
-								.ctx?
-								.get(&platform)
-								.ctx?
-								.is_empty()
-							{
-								entity
-									.platform_specific_properties
-									.as_mut()
-									.ctx?
-									.shift_remove(&platform);
-							}
+				SubEntityOperation::PatchArrayPropertyValue(property_name, array_patch) => {
+					let item_to_patch = entity
+						.properties
+						.get_or_insert(Default::default())
+						.get_mut(&property_name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: property_name.to_owned(),
+							platform: None
+						})?;
+
+					apply_array_patch_with_warnings(
+						&mut item_to_patch.value,
+						array_patch,
+						permissive,
+						item_to_patch.property_type == "TArray<SEntityTemplateReference>",
+						warnings
+					)?;
+				}
 
-							if entity.platform_specific_properties.as_ref().ctx?.is_empty() {
-								entity.platform_specific_properties = None;
-							}
-						}
+				SubEntityOperation::PatchPropertyValue(property_name, value_patch) => {
+					let item_to_patch = entity
+						.properties
+						.get_or_insert(Default::default())
+						.get_mut(&property_name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: property_name.to_owned(),
+							platform: None
+						})?;
+
+					apply_value_patch_with_warnings(&mut item_to_patch.value, value_patch, permissive, warnings)?;
+				}
 
-						SubEntityOperation::SetPlatformSpecificPropertyType(platform, name, value) => {
-							entity
-								.platform_specific_properties
-								.as_mut()
-								.context("SetPSPropertyType couldn't find properties!")?
-								.get_mut(&platform)
-								.context("SetPSPropertyType couldn't find expected platform!")?
-								.get_mut(&name)
-								.context("SetPSPropertyType couldn't find expected property!")?
-								.property_type = value;
-						}
+				SubEntityOperation::SetPropertyPostInit(name, value) => {
+					entity
+						.properties
+						.get_or_insert(Default::default())
+						.get_mut(&name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: name.to_owned(),
+							platform: None
+						})?
+						.post_init = if value { Some(true) } else { None };
+				}
 
-						SubEntityOperation::SetPlatformSpecificPropertyValue(SetPlatformSpecificPropertyValue {
-							platform,
-							property_name,
-							value
-						}) => {
-							entity
-								.platform_specific_properties
-								.as_mut()
-								.context("SetPSPropertyValue couldn't find properties!")?
-								.get_mut(&platform)
-								.context("SetPSPropertyValue couldn't find expected platform!")?
-								.get_mut(&property_name)
-								.context("SetPSPropertyValue couldn't find expected property!")?
-								.value = value;
-						}
+				SubEntityOperation::AddPlatformSpecificProperty(platform, name, data) => {
+					entity
+						.platform_specific_properties
+						.get_or_insert(Default::default())
+						.entry(platform)
+						.or_default()
+						.insert(name, data);
+				}
 
-						SubEntityOperation::PatchPlatformSpecificArrayPropertyValue(
-							platform,
-							property_name,
-							array_patch
-						) => {
-							let item_to_patch = entity
-								.platform_specific_properties
-								.as_mut()
-								.context("PatchPSArrayPropertyValue couldn't find properties!")?
-								.get_mut(&platform)
-								.context("PatchPSArrayPropertyValue couldn't find expected platform!")?
-								.get_mut(&property_name)
-								.context("PatchPSArrayPropertyValue couldn't find expected property!")?;
-
-							apply_array_patch(
-								&mut item_to_patch.value,
-								array_patch,
-								permissive,
-								item_to_patch.property_type == "TArray<SEntityTemplateReference>"
-							)?;
-						}
+				SubEntityOperation::RemovePlatformSpecificPropertiesForPlatform(name) => {
+					entity
+						.platform_specific_properties
+						.as_mut()
+						.context("RemovePSPropertiesForPlatform couldn't find properties!")?
+						.shift_remove(&name)
+						.permit(
+							permissive,
+							"RemovePSPropertiesForPlatform couldn't find platform to remove!",
+							warnings
+						)?;
+
+					if entity.platform_specific_properties.as_ref().ctx?.is_empty() {
+						entity.platform_specific_properties = None;
+					}
+				}
 
-						SubEntityOperation::SetPlatformSpecificPropertyPostInit(platform, name, value) => {
-							entity
-								.platform_specific_properties
-								.as_mut()
-								.context("SetPSPropertyPostInit couldn't find properties!")?
-								.get_mut(&platform)
-								.context("SetPSPropertyPostInit couldn't find expected platform!")?
-								.get_mut(&name)
-								.context("SetPSPropertyPostInit couldn't find expected property!")?
-								.post_init = if value { Some(true) } else { None };
-						}
+				SubEntityOperation::RemovePlatformSpecificPropertyByName(platform, name) => {
+					entity
+						.platform_specific_properties
+						.as_mut()
+						.context("RemovePSPropertyByName couldn't find properties!")?
+						.get_mut(&platform)
+						.context("RemovePSPropertyByName couldn't find platform!")?
+						.shift_remove(&name)
+						.permit(
+							permissive,
+							"RemovePSPropertyByName couldn't find property to remove!",
+							warnings
+						)?;
+
+					if entity
+						.platform_specific_properties
+						.as_ref()
+						.ctx?
+						.get(&platform)
+						.ctx?
+						.is_empty()
+					{
+						entity
+							.platform_specific_properties
+							.as_mut()
+							.ctx?
+							.shift_remove(&platform);
+					}
 
-						SubEntityOperation::RemoveAllEventConnectionsForEvent(event) => {
-							entity
-								.events
-								.as_mut()
-								.context("RemoveAllEventConnectionsForEvent couldn't find events!")?
-								.shift_remove(&event)
-								.context("RemoveAllEventConnectionsForEvent couldn't find event!")?;
+					if entity.platform_specific_properties.as_ref().ctx?.is_empty() {
+						entity.platform_specific_properties = None;
+					}
+				}
 
-							if entity.events.as_ref().ctx?.is_empty() {
-								entity.events = None;
-							}
-						}
+				SubEntityOperation::SetPlatformSpecificPropertyType(platform, name, value) => {
+					entity
+						.platform_specific_properties
+						.as_mut()
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&platform)
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: name.to_owned(),
+							platform: Some(platform.to_owned())
+						})?
+						.property_type = value;
+				}
 
-						SubEntityOperation::RemoveAllEventConnectionsForTrigger(event, trigger) => {
-							entity
-								.events
-								.as_mut()
-								.context("RemoveAllEventConnectionsForTrigger couldn't find events!")?
-								.get_mut(&event)
-								.context("RemoveAllEventConnectionsForTrigger couldn't find event!")?
-								.shift_remove(&trigger)
-								.context("RemoveAllEventConnectionsForTrigger couldn't find trigger!")?;
-
-							if entity.events.as_ref().ctx?.get(&event).ctx?.is_empty() {
-								entity.events.as_mut().ctx?.shift_remove(&event);
-							}
+				SubEntityOperation::SetPlatformSpecificPropertyValue(SetPlatformSpecificPropertyValue {
+					platform,
+					property_name,
+					value
+				}) => {
+					entity
+						.platform_specific_properties
+						.as_mut()
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&platform)
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&property_name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: property_name.to_owned(),
+							platform: Some(platform.to_owned())
+						})?
+						.value = value;
+				}
 
-							if entity.events.as_ref().ctx?.is_empty() {
-								entity.events = None;
-							}
-						}
+				SubEntityOperation::PatchPlatformSpecificArrayPropertyValue(platform, property_name, array_patch) => {
+					let item_to_patch = entity
+						.platform_specific_properties
+						.as_mut()
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&platform)
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&property_name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: property_name.to_owned(),
+							platform: Some(platform.to_owned())
+						})?;
+
+					apply_array_patch_with_warnings(
+						&mut item_to_patch.value,
+						array_patch,
+						permissive,
+						item_to_patch.property_type == "TArray<SEntityTemplateReference>",
+						warnings
+					)?;
+				}
 
-						SubEntityOperation::RemoveEventConnection(event, trigger, reference) => {
-							let reference = match reference {
-								RefMaybeConstantValue::Ref(x) => RefMaybeConstantValue::Ref(normalise_ref(&x)?),
-								RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
-									entity_ref,
-									value
-								}) => RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
-									entity_ref: normalise_ref(&entity_ref)?,
-									value
-								})
-							};
+				SubEntityOperation::PatchPlatformSpecificPropertyValue(platform, property_name, value_patch) => {
+					let item_to_patch = entity
+						.platform_specific_properties
+						.as_mut()
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&platform)
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&property_name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: property_name.to_owned(),
+							platform: Some(platform.to_owned())
+						})?;
+
+					apply_value_patch_with_warnings(&mut item_to_patch.value, value_patch, permissive, warnings)?;
+				}
 
-							let ind = entity
-								.events
-								.as_ref()
-								.context("RemoveEventConnection couldn't find events!")?
-								.get(&event)
-								.context("RemoveEventConnection couldn't find event!")?
-								.get(&trigger)
-								.context("RemoveEventConnection couldn't find trigger!")?
-								.iter()
-								.position(|x| *x == reference)
-								.context("RemoveEventConnection couldn't find reference!")?;
+				SubEntityOperation::SetPlatformSpecificPropertyPostInit(platform, name, value) => {
+					entity
+						.platform_specific_properties
+						.as_mut()
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&platform)
+						.ok_or_else(|| QuickEntityError::PlatformNotFound {
+							entity_id: entity_id.to_owned(),
+							platform: platform.to_owned()
+						})?
+						.get_mut(&name)
+						.ok_or_else(|| QuickEntityError::PropertyNotFound {
+							entity_id: entity_id.to_owned(),
+							property_name: name.to_owned(),
+							platform: Some(platform.to_owned())
+						})?
+						.post_init = if value { Some(true) } else { None };
+				}
 
-							entity
-								.events
-								.as_mut()
-								.ctx?
-								.get_mut(&event)
-								.ctx?
-								.get_mut(&trigger)
-								.ctx?
-								.remove(ind);
+				SubEntityOperation::RemoveAllEventConnectionsForEvent(event) => {
+					entity
+						.events
+						.as_mut()
+						.context("RemoveAllEventConnectionsForEvent couldn't find events!")?
+						.shift_remove(&event)
+						.context("RemoveAllEventConnectionsForEvent couldn't find event!")?;
 
-							if entity
-								.events
-								.as_ref()
-								.ctx?
-								.get(&event)
-								.ctx?
-								.get(&trigger)
-								.ctx?
-								.is_empty()
-							{
-								entity.events.as_mut().ctx?.get_mut(&event).ctx?.shift_remove(&trigger);
-							}
+					if entity.events.as_ref().ctx?.is_empty() {
+						entity.events = None;
+					}
+				}
 
-							if entity.events.as_ref().ctx?.get(&event).ctx?.is_empty() {
-								entity.events.as_mut().ctx?.shift_remove(&event);
-							}
+				SubEntityOperation::RemoveAllEventConnectionsForTrigger(event, trigger) => {
+					entity
+						.events
+						.as_mut()
+						.context("RemoveAllEventConnectionsForTrigger couldn't find events!")?
+						.get_mut(&event)
+						.context("RemoveAllEventConnectionsForTrigger couldn't find event!")?
+						.shift_remove(&trigger)
+						.context("RemoveAllEventConnectionsForTrigger couldn't find trigger!")?;
+
+					if entity.events.as_ref().ctx?.get(&event).ctx?.is_empty() {
+						entity.events.as_mut().ctx?.shift_remove(&event);
+					}
 
-							if entity.events.as_ref().ctx?.is_empty() {
-								entity.events = None;
-							}
+					if entity.events.as_ref().ctx?.is_empty() {
+						entity.events = None;
+					}
+				}
+
+				SubEntityOperation::RemoveEventConnection(event, trigger, reference) => {
+					let reference = match reference {
+						RefMaybeConstantValue::Ref(x) => RefMaybeConstantValue::Ref(normalise_ref(&x)?),
+						RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue { entity_ref, value }) => {
+							RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
+								entity_ref: normalise_ref(&entity_ref)?,
+								value
+							})
 						}
+					};
 
-						SubEntityOperation::AddEventConnection(event, trigger, reference) => {
-							if entity.events.is_none() {
-								entity.events = Some(Default::default());
-							}
+					let ind = entity
+						.events
+						.as_ref()
+						.context("RemoveEventConnection couldn't find events!")?
+						.get(&event)
+						.context("RemoveEventConnection couldn't find event!")?
+						.get(&trigger)
+						.context("RemoveEventConnection couldn't find trigger!")?
+						.iter()
+						.position(|x| *x == reference)
+						.context("RemoveEventConnection couldn't find reference!")?;
 
-							if entity.events.as_ref().ctx?.get(&event).is_none() {
-								entity.events.as_mut().ctx?.insert(event.to_owned(), Default::default());
-							}
+					entity
+						.events
+						.as_mut()
+						.ctx?
+						.get_mut(&event)
+						.ctx?
+						.get_mut(&trigger)
+						.ctx?
+						.remove(ind);
 
-							if entity.events.as_ref().ctx?.get(&event).ctx?.get(&trigger).is_none() {
-								entity
-									.events
-									.as_mut()
-									.ctx?
-									.get_mut(&event)
-									.ctx?
-									.insert(trigger.to_owned(), Default::default());
-							}
+					if entity
+						.events
+						.as_ref()
+						.ctx?
+						.get(&event)
+						.ctx?
+						.get(&trigger)
+						.ctx?
+						.is_empty()
+					{
+						entity.events.as_mut().ctx?.get_mut(&event).ctx?.shift_remove(&trigger);
+					}
 
-							entity
-								.events
-								.as_mut()
-								.ctx?
-								.get_mut(&event)
-								.ctx?
-								.get_mut(&trigger)
-								.ctx?
-								.push(reference);
-						}
+					if entity.events.as_ref().ctx?.get(&event).ctx?.is_empty() {
+						entity.events.as_mut().ctx?.shift_remove(&event);
+					}
 
-						SubEntityOperation::RemoveAllInputCopyConnectionsForInput(event) => {
-							entity
-								.input_copying
-								.as_mut()
-								.context("RemoveAllInputCopyConnectionsForInput couldn't find input copying!")?
-								.shift_remove(&event)
-								.context("RemoveAllInputCopyConnectionsForInput couldn't find input!")?;
+					if entity.events.as_ref().ctx?.is_empty() {
+						entity.events = None;
+					}
+				}
 
-							if entity.input_copying.as_ref().ctx?.is_empty() {
-								entity.input_copying = None;
-							}
-						}
+				SubEntityOperation::AddEventConnection(event, trigger, reference) => {
+					if entity.events.is_none() {
+						entity.events = Some(Default::default());
+					}
 
-						SubEntityOperation::RemoveAllInputCopyConnectionsForTrigger(event, trigger) => {
-							entity
-								.input_copying
-								.as_mut()
-								.context("RemoveAllInputCopyConnectionsForTrigger couldn't find input copying!")?
-								.get_mut(&event)
-								.context("RemoveAllInputCopyConnectionsForTrigger couldn't find input!")?
-								.shift_remove(&trigger)
-								.context("RemoveAllInputCopyConnectionsForTrigger couldn't find trigger!")?;
-
-							if entity.input_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
-								entity.input_copying.as_mut().ctx?.shift_remove(&event);
-							}
+					if entity.events.as_ref().ctx?.get(&event).is_none() {
+						entity.events.as_mut().ctx?.insert(event.to_owned(), Default::default());
+					}
 
-							if entity.input_copying.as_ref().ctx?.is_empty() {
-								entity.input_copying = None;
-							}
-						}
+					if entity.events.as_ref().ctx?.get(&event).ctx?.get(&trigger).is_none() {
+						entity
+							.events
+							.as_mut()
+							.ctx?
+							.get_mut(&event)
+							.ctx?
+							.insert(trigger.to_owned(), Default::default());
+					}
 
-						SubEntityOperation::RemoveInputCopyConnection(event, trigger, reference) => {
-							let reference = match reference {
-								RefMaybeConstantValue::Ref(x) => RefMaybeConstantValue::Ref(normalise_ref(&x)?),
-								RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
-									entity_ref,
-									value
-								}) => RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
-									entity_ref: normalise_ref(&entity_ref)?,
-									value
-								})
-							};
+					entity
+						.events
+						.as_mut()
+						.ctx?
+						.get_mut(&event)
+						.ctx?
+						.get_mut(&trigger)
+						.ctx?
+						.push(reference);
+				}
 
-							let ind = entity
-								.input_copying
-								.as_ref()
-								.context("RemoveInputCopyConnection couldn't find input copying!")?
-								.get(&event)
-								.context("RemoveInputCopyConnection couldn't find input!")?
-								.get(&trigger)
-								.context("RemoveInputCopyConnection couldn't find trigger!")?
-								.iter()
-								.position(|x| *x == reference)
-								.context("RemoveInputCopyConnection couldn't find reference!")?;
+				SubEntityOperation::RemoveAllInputCopyConnectionsForInput(event) => {
+					entity
+						.input_copying
+						.as_mut()
+						.context("RemoveAllInputCopyConnectionsForInput couldn't find input copying!")?
+						.shift_remove(&event)
+						.context("RemoveAllInputCopyConnectionsForInput couldn't find input!")?;
 
-							entity
-								.input_copying
-								.as_mut()
-								.ctx?
-								.get_mut(&event)
-								.ctx?
-								.get_mut(&trigger)
-								.ctx?
-								.remove(ind);
+					if entity.input_copying.as_ref().ctx?.is_empty() {
+						entity.input_copying = None;
+					}
+				}
 
-							if entity
-								.input_copying
-								.as_ref()
-								.ctx?
-								.get(&event)
-								.ctx?
-								.get(&trigger)
-								.ctx?
-								.is_empty()
-							{
-								entity
-									.input_copying
-									.as_mut()
-									.ctx?
-									.get_mut(&event)
-									.ctx?
-									.shift_remove(&trigger);
-							}
+				SubEntityOperation::RemoveAllInputCopyConnectionsForTrigger(event, trigger) => {
+					entity
+						.input_copying
+						.as_mut()
+						.context("RemoveAllInputCopyConnectionsForTrigger couldn't find input copying!")?
+						.get_mut(&event)
+						.context("RemoveAllInputCopyConnectionsForTrigger couldn't find input!")?
+						.shift_remove(&trigger)
+						.context("RemoveAllInputCopyConnectionsForTrigger couldn't find trigger!")?;
+
+					if entity.input_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
+						entity.input_copying.as_mut().ctx?.shift_remove(&event);
+					}
 
-							if entity.input_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
-								entity.input_copying.as_mut().ctx?.shift_remove(&event);
-							}
+					if entity.input_copying.as_ref().ctx?.is_empty() {
+						entity.input_copying = None;
+					}
+				}
 
-							if entity.input_copying.as_ref().ctx?.is_empty() {
-								entity.input_copying = None;
-							}
+				SubEntityOperation::RemoveInputCopyConnection(event, trigger, reference) => {
+					let reference = match reference {
+						RefMaybeConstantValue::Ref(x) => RefMaybeConstantValue::Ref(normalise_ref(&x)?),
+						RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue { entity_ref, value }) => {
+							RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
+								entity_ref: normalise_ref(&entity_ref)?,
+								value
+							})
 						}
+					};
 
-						SubEntityOperation::AddInputCopyConnection(event, trigger, reference) => {
-							if entity.input_copying.is_none() {
-								entity.input_copying = Some(Default::default());
-							}
+					let ind = entity
+						.input_copying
+						.as_ref()
+						.context("RemoveInputCopyConnection couldn't find input copying!")?
+						.get(&event)
+						.context("RemoveInputCopyConnection couldn't find input!")?
+						.get(&trigger)
+						.context("RemoveInputCopyConnection couldn't find trigger!")?
+						.iter()
+						.position(|x| *x == reference)
+						.context("RemoveInputCopyConnection couldn't find reference!")?;
 
-							if entity.input_copying.as_ref().ctx?.get(&event).is_none() {
-								entity
-									.input_copying
-									.as_mut()
-									.ctx?
-									.insert(event.to_owned(), Default::default());
-							}
+					entity
+						.input_copying
+						.as_mut()
+						.ctx?
+						.get_mut(&event)
+						.ctx?
+						.get_mut(&trigger)
+						.ctx?
+						.remove(ind);
 
-							if entity
-								.input_copying
-								.as_ref()
-								.ctx?
-								.get(&event)
-								.ctx?
-								.get(&trigger)
-								.is_none()
-							{
-								entity
-									.input_copying
-									.as_mut()
-									.ctx?
-									.get_mut(&event)
-									.ctx?
-									.insert(trigger.to_owned(), Default::default());
-							}
+					if entity
+						.input_copying
+						.as_ref()
+						.ctx?
+						.get(&event)
+						.ctx?
+						.get(&trigger)
+						.ctx?
+						.is_empty()
+					{
+						entity
+							.input_copying
+							.as_mut()
+							.ctx?
+							.get_mut(&event)
+							.ctx?
+							.shift_remove(&trigger);
+					}
 
-							entity
-								.input_copying
-								.as_mut()
-								.ctx?
-								.get_mut(&event)
-								.ctx?
-								.get_mut(&trigger)
-								.ctx?
-								.push(reference);
-						}
+					if entity.input_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
+						entity.input_copying.as_mut().ctx?.shift_remove(&event);
+					}
 
-						SubEntityOperation::RemoveAllOutputCopyConnectionsForOutput(event) => {
-							entity
-								.output_copying
-								.as_mut()
-								.context("RemoveAllOutputCopyConnectionsForOutput couldn't find output copying!")?
-								.shift_remove(&event)
-								.context("RemoveAllOutputCopyConnectionsForOutput couldn't find event!")?;
+					if entity.input_copying.as_ref().ctx?.is_empty() {
+						entity.input_copying = None;
+					}
+				}
 
-							if entity.output_copying.as_ref().ctx?.is_empty() {
-								entity.output_copying = None;
-							}
-						}
+				SubEntityOperation::AddInputCopyConnection(event, trigger, reference) => {
+					if entity.input_copying.is_none() {
+						entity.input_copying = Some(Default::default());
+					}
 
-						SubEntityOperation::RemoveAllOutputCopyConnectionsForPropagate(event, trigger) => {
-							entity
-								.output_copying
-								.as_mut()
-								.context("RemoveAllOutputCopyConnectionsForPropagate couldn't find output copying!")?
-								.get_mut(&event)
-								.context("RemoveAllOutputCopyConnectionsForPropagate couldn't find event!")?
-								.shift_remove(&trigger)
-								.context("RemoveAllOutputCopyConnectionsForPropagate couldn't find propagate!")?;
-
-							if entity.output_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
-								entity.output_copying.as_mut().ctx?.shift_remove(&event);
-							}
+					if entity.input_copying.as_ref().ctx?.get(&event).is_none() {
+						entity
+							.input_copying
+							.as_mut()
+							.ctx?
+							.insert(event.to_owned(), Default::default());
+					}
 
-							if entity.output_copying.as_ref().ctx?.is_empty() {
-								entity.output_copying = None;
-							}
-						}
+					if entity
+						.input_copying
+						.as_ref()
+						.ctx?
+						.get(&event)
+						.ctx?
+						.get(&trigger)
+						.is_none()
+					{
+						entity
+							.input_copying
+							.as_mut()
+							.ctx?
+							.get_mut(&event)
+							.ctx?
+							.insert(trigger.to_owned(), Default::default());
+					}
 
-						SubEntityOperation::RemoveOutputCopyConnection(event, trigger, reference) => {
-							let reference = match reference {
-								RefMaybeConstantValue::Ref(x) => RefMaybeConstantValue::Ref(normalise_ref(&x)?),
-								RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
-									entity_ref,
-									value
-								}) => RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
-									entity_ref: normalise_ref(&entity_ref)?,
-									value
-								})
-							};
+					entity
+						.input_copying
+						.as_mut()
+						.ctx?
+						.get_mut(&event)
+						.ctx?
+						.get_mut(&trigger)
+						.ctx?
+						.push(reference);
+				}
 
-							let ind = entity
-								.output_copying
-								.as_ref()
-								.context("RemoveOutputCopyConnection couldn't find output copying!")?
-								.get(&event)
-								.context("RemoveOutputCopyConnection couldn't find event!")?
-								.get(&trigger)
-								.context("RemoveOutputCopyConnection couldn't find propagate!")?
-								.iter()
-								.position(|x| *x == reference)
-								.context("RemoveOutputCopyConnection couldn't find reference!")?;
+				SubEntityOperation::RemoveAllOutputCopyConnectionsForOutput(event) => {
+					entity
+						.output_copying
+						.as_mut()
+						.context("RemoveAllOutputCopyConnectionsForOutput couldn't find output copying!")?
+						.shift_remove(&event)
+						.context("RemoveAllOutputCopyConnectionsForOutput couldn't find event!")?;
 
-							entity
-								.output_copying
-								.as_mut()
-								.ctx?
-								.get_mut(&event)
-								.ctx?
-								.get_mut(&trigger)
-								.ctx?
-								.remove(ind);
+					if entity.output_copying.as_ref().ctx?.is_empty() {
+						entity.output_copying = None;
+					}
+				}
 
-							if entity
-								.output_copying
-								.as_ref()
-								.ctx?
-								.get(&event)
-								.ctx?
-								.get(&trigger)
-								.ctx?
-								.is_empty()
-							{
-								entity
-									.output_copying
-									.as_mut()
-									.ctx?
-									.get_mut(&event)
-									.ctx?
-									.shift_remove(&trigger);
-							}
+				SubEntityOperation::RemoveAllOutputCopyConnectionsForPropagate(event, trigger) => {
+					entity
+						.output_copying
+						.as_mut()
+						.context("RemoveAllOutputCopyConnectionsForPropagate couldn't find output copying!")?
+						.get_mut(&event)
+						.context("RemoveAllOutputCopyConnectionsForPropagate couldn't find event!")?
+						.shift_remove(&trigger)
+						.context("RemoveAllOutputCopyConnectionsForPropagate couldn't find propagate!")?;
+
+					if entity.output_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
+						entity.output_copying.as_mut().ctx?.shift_remove(&event);
+					}
 
-							if entity.output_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
-								entity.output_copying.as_mut().ctx?.shift_remove(&event);
-							}
+					if entity.output_copying.as_ref().ctx?.is_empty() {
+						entity.output_copying = None;
+					}
+				}
 
-							if entity.output_copying.as_ref().ctx?.is_empty() {
-								entity.output_copying = None;
-							}
+				SubEntityOperation::RemoveOutputCopyConnection(event, trigger, reference) => {
+					let reference = match reference {
+						RefMaybeConstantValue::Ref(x) => RefMaybeConstantValue::Ref(normalise_ref(&x)?),
+						RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue { entity_ref, value }) => {
+							RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
+								entity_ref: normalise_ref(&entity_ref)?,
+								value
+							})
 						}
+					};
 
-						SubEntityOperation::AddOutputCopyConnection(event, trigger, reference) => {
-							if entity.output_copying.is_none() {
-								entity.output_copying = Some(Default::default());
-							}
+					let ind = entity
+						.output_copying
+						.as_ref()
+						.context("RemoveOutputCopyConnection couldn't find output copying!")?
+						.get(&event)
+						.context("RemoveOutputCopyConnection couldn't find event!")?
+						.get(&trigger)
+						.context("RemoveOutputCopyConnection couldn't find propagate!")?
+						.iter()
+						.position(|x| *x == reference)
+						.context("RemoveOutputCopyConnection couldn't find reference!")?;
 
-							if entity.output_copying.as_ref().ctx?.get(&event).is_none() {
-								entity
-									.output_copying
-									.as_mut()
-									.ctx?
-									.insert(event.to_owned(), Default::default());
-							}
+					entity
+						.output_copying
+						.as_mut()
+						.ctx?
+						.get_mut(&event)
+						.ctx?
+						.get_mut(&trigger)
+						.ctx?
+						.remove(ind);
 
-							if entity
-								.output_copying
-								.as_ref()
-								.ctx?
-								.get(&event)
-								.ctx?
-								.get(&trigger)
-								.is_none()
-							{
-								entity
-									.output_copying
-									.as_mut()
-									.ctx?
-									.get_mut(&event)
-									.ctx?
-									.insert(trigger.to_owned(), Default::default());
-							}
+					if entity
+						.output_copying
+						.as_ref()
+						.ctx?
+						.get(&event)
+						.ctx?
+						.get(&trigger)
+						.ctx?
+						.is_empty()
+					{
+						entity
+							.output_copying
+							.as_mut()
+							.ctx?
+							.get_mut(&event)
+							.ctx?
+							.shift_remove(&trigger);
+					}
 
-							entity
-								.output_copying
-								.as_mut()
-								.ctx?
-								.get_mut(&event)
-								.ctx?
-								.get_mut(&trigger)
-								.ctx?
-								.push(reference);
-						}
+					if entity.output_copying.as_ref().ctx?.get(&event).ctx?.is_empty() {
+						entity.output_copying.as_mut().ctx?.shift_remove(&event);
+					}
 
-						SubEntityOperation::AddPropertyAliasConnection(alias, data) => {
-							entity
-								.property_aliases
-								.get_or_insert(Default::default())
-								.entry(alias)
-								.or_default()
-								.push(data);
-						}
+					if entity.output_copying.as_ref().ctx?.is_empty() {
+						entity.output_copying = None;
+					}
+				}
 
-						SubEntityOperation::RemovePropertyAlias(alias) => {
-							entity
-								.property_aliases
-								.get_or_insert(Default::default())
-								.shift_remove(&alias)
-								.context("RemovePropertyAlias couldn't find alias!")?;
+				SubEntityOperation::AddOutputCopyConnection(event, trigger, reference) => {
+					if entity.output_copying.is_none() {
+						entity.output_copying = Some(Default::default());
+					}
 
-							if entity.property_aliases.as_ref().ctx?.is_empty() {
-								entity.property_aliases = None;
-							}
-						}
+					if entity.output_copying.as_ref().ctx?.get(&event).is_none() {
+						entity
+							.output_copying
+							.as_mut()
+							.ctx?
+							.insert(event.to_owned(), Default::default());
+					}
 
-						SubEntityOperation::RemoveConnectionForPropertyAlias(alias, data) => {
-							let data = PropertyAlias {
-								original_property: data.original_property,
-								original_entity: normalise_ref(&data.original_entity)?
-							};
+					if entity
+						.output_copying
+						.as_ref()
+						.ctx?
+						.get(&event)
+						.ctx?
+						.get(&trigger)
+						.is_none()
+					{
+						entity
+							.output_copying
+							.as_mut()
+							.ctx?
+							.get_mut(&event)
+							.ctx?
+							.insert(trigger.to_owned(), Default::default());
+					}
 
-							let connection = entity
-								.property_aliases
-								.as_ref()
-								.context("RemoveConnectionForPropertyAlias had no aliases to remove!")?
-								.get(&alias)
-								.context("RemoveConnectionForPropertyAlias couldn't find alias!")?
-								.iter()
-								.position(|x| *x == data)
-								.context("RemoveConnectionForPropertyAlias couldn't find connection!")?;
+					entity
+						.output_copying
+						.as_mut()
+						.ctx?
+						.get_mut(&event)
+						.ctx?
+						.get_mut(&trigger)
+						.ctx?
+						.push(reference);
+				}
 
-							entity
-								.property_aliases
-								.as_mut()
-								.ctx?
-								.get_mut(&alias)
-								.ctx?
-								.remove(connection);
+				SubEntityOperation::AddPropertyAliasConnection(alias, data) => {
+					entity
+						.property_aliases
+						.get_or_insert(Default::default())
+						.entry(alias)
+						.or_default()
+						.push(data);
+				}
 
-							if entity.property_aliases.as_ref().ctx?.get(&alias).ctx?.is_empty() {
-								entity.property_aliases.as_mut().ctx?.shift_remove(&alias);
-							}
+				SubEntityOperation::RemovePropertyAlias(alias) => {
+					entity
+						.property_aliases
+						.get_or_insert(Default::default())
+						.shift_remove(&alias)
+						.context("RemovePropertyAlias couldn't find alias!")?;
 
-							if entity.property_aliases.as_ref().ctx?.is_empty() {
-								entity.property_aliases = None;
-							}
-						}
+					if entity.property_aliases.as_ref().ctx?.is_empty() {
+						entity.property_aliases = None;
+					}
+				}
 
-						SubEntityOperation::SetExposedEntity(name, data) => {
-							entity
-								.exposed_entities
-								.get_or_insert(Default::default())
-								.insert(name, data);
-						}
+				SubEntityOperation::RemoveConnectionForPropertyAlias(alias, data) => {
+					let data = PropertyAlias {
+						original_property: data.original_property,
+						original_entity: normalise_ref(&data.original_entity)?
+					};
 
-						SubEntityOperation::RemoveExposedEntity(name) => {
-							entity
-								.exposed_entities
-								.as_mut()
-								.context("RemoveExposedEntity had no exposed entities to remove!")?
-								.shift_remove(&name)
-								.context("RemoveExposedEntity couldn't find exposed entity to remove!")?;
-						}
+					let connection = entity
+						.property_aliases
+						.as_ref()
+						.context("RemoveConnectionForPropertyAlias had no aliases to remove!")?
+						.get(&alias)
+						.context("RemoveConnectionForPropertyAlias couldn't find alias!")?
+						.iter()
+						.position(|x| *x == data)
+						.context("RemoveConnectionForPropertyAlias couldn't find connection!")?;
 
-						SubEntityOperation::SetExposedInterface(name, implementor) => {
-							entity
-								.exposed_interfaces
-								.get_or_insert(Default::default())
-								.insert(name, implementor);
-						}
+					entity
+						.property_aliases
+						.as_mut()
+						.ctx?
+						.get_mut(&alias)
+						.ctx?
+						.remove(connection);
 
-						SubEntityOperation::RemoveExposedInterface(name) => {
-							entity
-								.exposed_interfaces
-								.as_mut()
-								.context("RemoveExposedInterface had no exposed entities to remove!")?
-								.shift_remove(&name)
-								.context("RemoveExposedInterface couldn't find exposed entity to remove!")?;
-						}
+					if entity.property_aliases.as_ref().ctx?.get(&alias).ctx?.is_empty() {
+						entity.property_aliases.as_mut().ctx?.shift_remove(&alias);
+					}
 
-						SubEntityOperation::AddSubset(name, ent) => {
-							entity
-								.subsets
-								.get_or_insert(Default::default())
-								.entry(name)
-								.or_default()
-								.push(ent);
-						}
+					if entity.property_aliases.as_ref().ctx?.is_empty() {
+						entity.property_aliases = None;
+					}
+				}
 
-						SubEntityOperation::RemoveSubset(name, ent) => {
-							let ent = normalise_entity_id(&ent)?;
+				SubEntityOperation::SetExposedEntity(name, data) => {
+					entity
+						.exposed_entities
+						.get_or_insert(Default::default())
+						.insert(name, data);
+				}
 
-							let ind = entity
-								.subsets
-								.as_ref()
-								.context("RemoveSubset had no subsets to remove!")?
-								.get(&name)
-								.context("RemoveSubset couldn't find subset to remove from!")?
-								.iter()
-								.position(|x| *x == ent)
-								.context("RemoveSubset couldn't find the entity to remove from the subset!")?;
+				SubEntityOperation::RemoveExposedEntity(name) => {
+					entity
+						.exposed_entities
+						.as_mut()
+						.context("RemoveExposedEntity had no exposed entities to remove!")?
+						.shift_remove(&name)
+						.context("RemoveExposedEntity couldn't find exposed entity to remove!")?;
+				}
 
-							entity.subsets.as_mut().ctx?.get_mut(&name).ctx?.remove(ind);
-						}
+				SubEntityOperation::SetExposedInterface(name, implementor) => {
+					entity
+						.exposed_interfaces
+						.get_or_insert(Default::default())
+						.insert(name, implementor);
+				}
 
-						SubEntityOperation::RemoveAllSubsetsFor(name) => {
-							entity
-								.subsets
-								.as_mut()
-								.context("RemoveAllSubsetsFor had no subsets to remove!")?
-								.shift_remove(&name)
-								.context("RemoveAllSubsetsFor couldn't find subset to remove!")?;
-						}
-					}
+				SubEntityOperation::RemoveExposedInterface(name) => {
+					entity
+						.exposed_interfaces
+						.as_mut()
+						.context("RemoveExposedInterface had no exposed entities to remove!")?
+						.shift_remove(&name)
+						.context("RemoveExposedInterface couldn't find exposed entity to remove!")?;
 				}
 
-				#[allow(deprecated)]
-				PatchOperation::AddPropertyOverride(value) => {
-					entity.property_overrides.push(value);
+				SubEntityOperation::AddSubset(name, ent) => {
+					entity
+						.subsets
+						.get_or_insert(Default::default())
+						.entry(name)
+						.or_default()
+						.push(ent);
 				}
 
-				#[allow(deprecated)]
-				PatchOperation::RemovePropertyOverride(value) => {
-					entity.property_overrides.remove(
-						entity
-							.property_overrides
-							.par_iter()
-							.position_any(|x| *x == value)
-							.context("RemovePropertyOverride couldn't find expected value!")?
-					);
-				}
-
-				PatchOperation::AddPropertyOverrideConnection(mut value) => {
-					let mut unravelled_overrides: Vec<PropertyOverride> = vec![];
-
-					value.entity = normalise_ref(&value.entity)?;
-
-					for property_override in &entity.property_overrides {
-						for ent in &property_override.entities {
-							for (prop_name, prop_override) in &property_override.properties {
-								unravelled_overrides.push(PropertyOverride {
-									entities: vec![ent.to_owned()],
-									properties: {
-										let mut x = IndexMap::new();
-										x.insert(prop_name.to_owned(), prop_override.to_owned());
-										x
-									}
-								});
-							}
-						}
-					}
+				SubEntityOperation::RemoveSubset(name, ent) => {
+					let ent = normalise_entity_id(&ent)?;
 
-					unravelled_overrides.push(PropertyOverride {
-						entities: vec![value.entity],
-						properties: {
-							let mut x = IndexMap::new();
-							x.insert(value.property_name.to_owned(), value.property_override.to_owned());
-							x
-						}
-					});
+					let ind = entity
+						.subsets
+						.as_ref()
+						.context("RemoveSubset had no subsets to remove!")?
+						.get(&name)
+						.context("RemoveSubset couldn't find subset to remove from!")?
+						.iter()
+						.position(|x| *x == ent)
+						.context("RemoveSubset couldn't find the entity to remove from the subset!")?;
 
-					let mut merged_overrides: Vec<PropertyOverride> = vec![];
+					entity.subsets.as_mut().ctx?.get_mut(&name).ctx?.remove(ind);
+				}
 
-					let mut pass1: Vec<PropertyOverride> = Vec::default();
+				SubEntityOperation::RemoveAllSubsetsFor(name) => {
+					entity
+						.subsets
+						.as_mut()
+						.context("RemoveAllSubsetsFor had no subsets to remove!")?
+						.shift_remove(&name)
+						.context("RemoveAllSubsetsFor couldn't find subset to remove!")?;
+				}
+			}
+		}
 
-					for property_override in unravelled_overrides {
-						// if same entity being overridden, merge props
-						if let Some(found) = pass1.iter_mut().find(|x| x.entities == property_override.entities) {
-							found.properties.extend(property_override.properties);
-						} else {
-							pass1.push(PropertyOverride {
-								entities: property_override.entities,
-								properties: property_override.properties
-							});
-						}
-					}
+		PatchOperation::AddPropertyOverride(value) => {
+			entity.property_overrides.push(value);
+		}
 
-					// merge entities when same props being overridden
-					for property_override in pass1 {
-						if let Some(found) = merged_overrides.iter_mut().try_find(|x| -> Result<bool> {
-							let contain_same_keys = x
-								.properties
-								.iter()
-								.all(|(y, _)| property_override.properties.contains_key(y))
-								&& property_override
-									.properties
-									.iter()
-									.all(|(y, _)| x.properties.contains_key(y));
+		PatchOperation::RemovePropertyOverride(value) => {
+			entity.property_overrides.remove(
+				entity
+					.property_overrides
+					.par_iter()
+					.position_any(|x| *x == value)
+					.context("RemovePropertyOverride couldn't find expected value!")?
+			);
+		}
 
-							// short-circuit
-							if !contain_same_keys {
-								return Ok(false);
-							}
+		PatchOperation::AddPropertyOverrideConnection(mut value) => {
+			let mut unravelled_overrides: Vec<PropertyOverride> = vec![];
 
-							let values_identical =
-								x.properties.iter().try_all(|(prop_name, prop_val)| -> Result<bool> {
-									property_is_roughly_identical(prop_val, &property_override.properties[prop_name])
-								})?;
+			value.entity = normalise_ref(&value.entity)?;
 
-							// Properties are identical when they contain the same properties and each property's value is roughly identical
-							Ok(values_identical)
-						})? {
-							found.entities.extend(property_override.entities);
-						} else {
-							merged_overrides.push(property_override);
-						}
+			for property_override in &entity.property_overrides {
+				for ent in &property_override.entities {
+					for (prop_name, prop_override) in &property_override.properties {
+						unravelled_overrides.push(PropertyOverride {
+							entities: vec![ent.to_owned()],
+							properties: {
+								let mut x = IndexMap::new();
+								x.insert(prop_name.to_owned(), prop_override.to_owned());
+								x
+							}
+						});
 					}
+				}
+			}
 
-					entity.property_overrides = merged_overrides;
+			unravelled_overrides.push(PropertyOverride {
+				entities: vec![value.entity],
+				properties: {
+					let mut x = IndexMap::new();
+					x.insert(value.property_name.to_owned(), value.property_override.to_owned());
+					x
 				}
+			});
 
-				PatchOperation::RemovePropertyOverrideConnection(mut value) => {
-					let mut unravelled_overrides: Vec<PropertyOverride> = vec![];
+			let mut merged_overrides: Vec<PropertyOverride> = vec![];
 
-					value.entity = normalise_ref(&value.entity)?;
+			let mut pass1: Vec<PropertyOverride> = Vec::default();
 
-					for property_override in &entity.property_overrides {
-						for ent in &property_override.entities {
-							for (prop_name, prop_override) in &property_override.properties {
-								unravelled_overrides.push(PropertyOverride {
-									entities: vec![ent.to_owned()],
-									properties: {
-										let mut x = IndexMap::new();
-										x.insert(prop_name.to_owned(), prop_override.to_owned());
-										x
-									}
-								});
-							}
-						}
-					}
+			for property_override in unravelled_overrides {
+				// if same entity being overridden, merge props
+				if let Some(found) = pass1.iter_mut().find(|x| x.entities == property_override.entities) {
+					found.properties.extend(property_override.properties);
+				} else {
+					pass1.push(PropertyOverride {
+						entities: property_override.entities,
+						properties: property_override.properties
+					});
+				}
+			}
 
-					let search = PropertyOverride {
-						entities: vec![value.entity.to_owned()],
-						properties: {
-							let mut x = IndexMap::new();
-							x.insert(value.property_name.to_owned(), value.property_override.to_owned());
-							x
-						}
-					};
+			// merge entities when same props being overridden
+			for property_override in pass1 {
+				if let Some(found) = merged_overrides.iter_mut().try_find(|x| -> Result<bool> {
+					let contain_same_keys = x
+						.properties
+						.iter()
+						.all(|(y, _)| property_override.properties.contains_key(y))
+						&& property_override
+							.properties
+							.iter()
+							.all(|(y, _)| x.properties.contains_key(y));
 
-					unravelled_overrides.retain(|x| *x != search);
+					// short-circuit
+					if !contain_same_keys {
+						return Ok(false);
+					}
 
-					let mut merged_overrides: Vec<PropertyOverride> = vec![];
+					let values_identical = x.properties.iter().try_all(|(prop_name, prop_val)| -> Result<bool> {
+						property_is_roughly_identical(prop_val, &property_override.properties[prop_name])
+					})?;
 
-					let mut pass1: Vec<PropertyOverride> = Vec::default();
+					// Properties are identical when they contain the same properties and each property's value is roughly identical
+					Ok(values_identical)
+				})? {
+					found.entities.extend(property_override.entities);
+				} else {
+					merged_overrides.push(property_override);
+				}
+			}
 
-					for property_override in unravelled_overrides {
-						// if same entity being overridden, merge props
-						if let Some(found) = pass1.iter_mut().find(|x| x.entities == property_override.entities) {
-							found.properties.extend(property_override.properties);
-						} else {
-							pass1.push(PropertyOverride {
-								entities: property_override.entities,
-								properties: property_override.properties
-							});
-						}
-					}
+			entity.property_overrides = merged_overrides;
+		}
 
-					// merge entities when same props being overridden
-					for property_override in pass1 {
-						if let Some(found) = merged_overrides.iter_mut().try_find(|x| -> Result<bool> {
-							let contain_same_keys = x
-								.properties
-								.iter()
-								.all(|(y, _)| property_override.properties.contains_key(y))
-								&& property_override
-									.properties
-									.iter()
-									.all(|(y, _)| x.properties.contains_key(y));
+		PatchOperation::RemovePropertyOverrideConnection(mut value) => {
+			let mut unravelled_overrides: Vec<PropertyOverride> = vec![];
 
-							// short-circuit
-							if !contain_same_keys {
-								return Ok(false);
-							}
+			value.entity = normalise_ref(&value.entity)?;
 
-							let values_identical = x
-								.properties
-								.iter()
-								.try_find(|(prop_name, prop_val)| -> Result<bool> {
-									Ok(!(property_is_roughly_identical(
-										prop_val,
-										&property_override.properties[*prop_name]
-									))?)
-								})?
-								.is_none();
-
-							// Properties are identical when they contain the same properties and each property's value is roughly identical
-							Ok(values_identical)
-						})? {
-							found.entities.extend(property_override.entities);
-						} else {
-							merged_overrides.push(property_override);
-						}
+			for property_override in &entity.property_overrides {
+				for ent in &property_override.entities {
+					for (prop_name, prop_override) in &property_override.properties {
+						unravelled_overrides.push(PropertyOverride {
+							entities: vec![ent.to_owned()],
+							properties: {
+								let mut x = IndexMap::new();
+								x.insert(prop_name.to_owned(), prop_override.to_owned());
+								x
+							}
+						});
 					}
-
-					entity.property_overrides = merged_overrides;
 				}
+			}
 
-				PatchOperation::AddOverrideDelete(value) => {
-					let value = normalise_ref(&value)?;
-
-					entity.override_deletes.push(value);
+			let search = PropertyOverride {
+				entities: vec![value.entity.to_owned()],
+				properties: {
+					let mut x = IndexMap::new();
+					x.insert(value.property_name.to_owned(), value.property_override.to_owned());
+					x
 				}
+			};
 
-				PatchOperation::RemoveOverrideDelete(value) => {
-					let value = normalise_ref(&value)?;
+			unravelled_overrides.retain(|x| *x != search);
 
-					entity.override_deletes.remove(
-						entity
-							.override_deletes
-							.par_iter()
-							.position_any(|x| *x == value)
-							.context("RemoveOverrideDelete couldn't find expected value!")?
-					);
-				}
+			let mut merged_overrides: Vec<PropertyOverride> = vec![];
 
-				PatchOperation::AddPinConnectionOverride(mut value) => {
-					value.to_entity = normalise_ref(&value.to_entity)?;
-					value.from_entity = normalise_ref(&value.from_entity)?;
+			let mut pass1: Vec<PropertyOverride> = Vec::default();
 
-					entity.pin_connection_overrides.push(value);
+			for property_override in unravelled_overrides {
+				// if same entity being overridden, merge props
+				if let Some(found) = pass1.iter_mut().find(|x| x.entities == property_override.entities) {
+					found.properties.extend(property_override.properties);
+				} else {
+					pass1.push(PropertyOverride {
+						entities: property_override.entities,
+						properties: property_override.properties
+					});
 				}
+			}
 
-				PatchOperation::RemovePinConnectionOverride(mut value) => {
-					value.to_entity = normalise_ref(&value.to_entity)?;
-					value.from_entity = normalise_ref(&value.from_entity)?;
+			// merge entities when same props being overridden
+			for property_override in pass1 {
+				if let Some(found) = merged_overrides.iter_mut().try_find(|x| -> Result<bool> {
+					let contain_same_keys = x
+						.properties
+						.iter()
+						.all(|(y, _)| property_override.properties.contains_key(y))
+						&& property_override
+							.properties
+							.iter()
+							.all(|(y, _)| x.properties.contains_key(y));
 
-					entity.pin_connection_overrides.remove(
-						entity
-							.pin_connection_overrides
-							.par_iter()
-							.position_any(|x| *x == value)
-							.context("RemovePinConnectionOverride couldn't find expected value!")?
-					);
-				}
+					// short-circuit
+					if !contain_same_keys {
+						return Ok(false);
+					}
 
-				PatchOperation::AddPinConnectionOverrideDelete(mut value) => {
-					value.to_entity = normalise_ref(&value.to_entity)?;
-					value.from_entity = normalise_ref(&value.from_entity)?;
+					let values_identical = x
+						.properties
+						.iter()
+						.try_find(|(prop_name, prop_val)| -> Result<bool> {
+							Ok(!(property_is_roughly_identical(prop_val, &property_override.properties[*prop_name]))?)
+						})?
+						.is_none();
 
-					entity.pin_connection_override_deletes.push(value);
+					// Properties are identical when they contain the same properties and each property's value is roughly identical
+					Ok(values_identical)
+				})? {
+					found.entities.extend(property_override.entities);
+				} else {
+					merged_overrides.push(property_override);
 				}
+			}
 
-				PatchOperation::RemovePinConnectionOverrideDelete(mut value) => {
-					value.to_entity = normalise_ref(&value.to_entity)?;
-					value.from_entity = normalise_ref(&value.from_entity)?;
+			entity.property_overrides = merged_overrides;
+		}
 
-					entity.pin_connection_override_deletes.remove(
-						entity
-							.pin_connection_override_deletes
-							.par_iter()
-							.position_any(|x| *x == value)
-							.context("RemovePinConnectionOverrideDelete couldn't find expected value!")?
-					);
-				}
-
-				PatchOperation::AddExternalScene(value) => {
-					entity.external_scenes.push(value);
-				}
-
-				PatchOperation::RemoveExternalScene(value) => {
-					if let Some(x) = entity.external_scenes.par_iter().position_any(|x| {
-						*x == value
-							|| value
-								== format!(
-									"00{}",
-									format!("{:X}", md5::compute(x))
-										.chars()
-										.skip(2)
-										.take(14)
-										.collect::<String>()
-								) || *x
-							== format!(
-								"00{}",
-								format!("{:X}", md5::compute(&value))
-									.chars()
-									.skip(2)
-									.take(14)
-									.collect::<String>()
-							)
-					}) {
-						entity.external_scenes.remove(x);
-					} else if permissive {
-						log::warn!("QuickEntity warning: RemoveExternalScene couldn't find expected value!");
-					} else {
-						bail!("RemoveExternalScene couldn't find expected value!");
-					}
-				}
+		PatchOperation::AddOverrideDelete(value) => {
+			let value = normalise_ref(&value)?;
 
-				PatchOperation::AddExtraFactoryDependency(value) => {
-					entity.extra_factory_dependencies.push(value);
-				}
+			entity.override_deletes.push(value);
+		}
 
-				PatchOperation::RemoveExtraFactoryDependency(value) => {
-					entity.extra_factory_dependencies.remove(
-						entity
-							.extra_factory_dependencies
-							.par_iter()
-							.position_any(|x| *x == value)
-							.context("RemoveExtraFactoryDependency couldn't find expected value!")?
-					);
-				}
+		PatchOperation::RemoveOverrideDelete(value) => {
+			let value = normalise_ref(&value)?;
 
-				PatchOperation::AddExtraBlueprintDependency(value) => {
-					entity.extra_blueprint_dependencies.push(value);
-				}
+			entity.override_deletes.remove(
+				entity
+					.override_deletes
+					.par_iter()
+					.position_any(|x| *x == value)
+					.context("RemoveOverrideDelete couldn't find expected value!")?
+			);
+		}
 
-				PatchOperation::RemoveExtraBlueprintDependency(value) => {
-					entity.extra_blueprint_dependencies.remove(
-						entity
-							.extra_blueprint_dependencies
-							.par_iter()
-							.position_any(|x| *x == value)
-							.context("RemoveExtraBlueprintDependency couldn't find expected value!")?
-					);
-				}
+		PatchOperation::AddPinConnectionOverride(mut value) => {
+			value.to_entity = normalise_ref(&value.to_entity)?;
+			value.from_entity = normalise_ref(&value.from_entity)?;
+
+			entity.pin_connection_overrides.push(value);
+		}
 
-				PatchOperation::AddComment(mut value) => {
-					value.parent = normalise_ref(&value.parent)?;
+		PatchOperation::RemovePinConnectionOverride(mut value) => {
+			value.to_entity = normalise_ref(&value.to_entity)?;
+			value.from_entity = normalise_ref(&value.from_entity)?;
 
-					entity.comments.push(value);
-				}
+			entity.pin_connection_overrides.remove(
+				entity
+					.pin_connection_overrides
+					.par_iter()
+					.position_any(|x| *x == value)
+					.context("RemovePinConnectionOverride couldn't find expected value!")?
+			);
+		}
 
-				PatchOperation::RemoveComment(mut value) => {
-					value.parent = normalise_ref(&value.parent)?;
+		PatchOperation::AddPinConnectionOverrideDelete(mut value) => {
+			value.to_entity = normalise_ref(&value.to_entity)?;
+			value.from_entity = normalise_ref(&value.from_entity)?;
 
-					entity.comments.remove(
-						entity
-							.comments
-							.par_iter()
-							.position_any(|x| *x == value)
-							.context("RemoveComment couldn't find expected value!")?
-					);
-				}
+			entity.pin_connection_override_deletes.push(value);
+		}
+
+		PatchOperation::RemovePinConnectionOverrideDelete(mut value) => {
+			value.to_entity = normalise_ref(&value.to_entity)?;
+			value.from_entity = normalise_ref(&value.from_entity)?;
+
+			entity.pin_connection_override_deletes.remove(
+				entity
+					.pin_connection_override_deletes
+					.par_iter()
+					.position_any(|x| *x == value)
+					.context("RemovePinConnectionOverrideDelete couldn't find expected value!")?
+			);
+		}
+
+		PatchOperation::AddExternalScene(value) => {
+			entity.external_scenes.push(value);
+		}
+
+		PatchOperation::RemoveExternalScene(value) => {
+			if let Some(x) = entity
+				.external_scenes
+				.par_iter()
+				.position_any(|x| hashes_match(x, &value))
+			{
+				entity.external_scenes.remove(x);
+			} else if permissive {
+				log::warn!("QuickEntity warning: RemoveExternalScene couldn't find expected value!");
+				warnings.push("RemoveExternalScene couldn't find expected value!".to_owned());
+			} else {
+				bail!("RemoveExternalScene couldn't find expected value!");
 			}
 		}
 
-		Ok(())
-	})?;
+		PatchOperation::AddExtraFactoryDependency(value) => {
+			entity.extra_factory_dependencies.push(value);
+		}
+
+		PatchOperation::RemoveExtraFactoryDependency(value) => {
+			entity.extra_factory_dependencies.remove(
+				entity
+					.extra_factory_dependencies
+					.par_iter()
+					.position_any(|x| *x == value)
+					.context("RemoveExtraFactoryDependency couldn't find expected value!")?
+			);
+		}
+
+		PatchOperation::AddExtraBlueprintDependency(value) => {
+			entity.extra_blueprint_dependencies.push(value);
+		}
+
+		PatchOperation::RemoveExtraBlueprintDependency(value) => {
+			entity.extra_blueprint_dependencies.remove(
+				entity
+					.extra_blueprint_dependencies
+					.par_iter()
+					.position_any(|x| *x == value)
+					.context("RemoveExtraBlueprintDependency couldn't find expected value!")?
+			);
+		}
+
+		PatchOperation::AddComment(mut value) => {
+			value.parent = normalise_ref(&value.parent)?;
+
+			entity.comments.push(value);
+		}
+
+		PatchOperation::RemoveComment(mut value) => {
+			value.parent = normalise_ref(&value.parent)?;
+
+			entity.comments.remove(
+				entity
+					.comments
+					.par_iter()
+					.position_any(|x| *x == value)
+					.context("RemoveComment couldn't find expected value!")?
+			);
+		}
+	}
 }
 
 #[try_fn]
@@ -1341,7 +1543,18 @@ pub fn apply_array_patch(
 	patch: Vec<ArrayPatchOperation>,
 	permissive: bool,
 	is_ref_array: bool
-) -> Result<()> {
+) -> Result<(), QuickEntityError> {
+	apply_array_patch_with_warnings(arr, patch, permissive, is_ref_array, &mut vec![])?;
+}
+
+#[try_fn]
+fn apply_array_patch_with_warnings(
+	arr: &mut Value,
+	patch: Vec<ArrayPatchOperation>,
+	permissive: bool,
+	is_ref_array: bool,
+	warnings: &mut Vec<String>
+) -> Result<(), QuickEntityError> {
 	let arr = arr
 		.as_array_mut()
 		.context("Array patch was given a non-array value to patch!")?;
@@ -1350,7 +1563,7 @@ pub fn apply_array_patch(
 		// It's not unnecessary because what Clippy suggests causes an error due to the borrow from .iter().cloned()
 		#[allow(clippy::unnecessary_to_owned)]
 		for (index, elem) in arr.to_owned().into_iter().enumerate() {
-			arr[index] = to_value(normalise_ref(&from_value::<Ref>(elem)?)?)?;
+			arr[index] = normalise_ref_value(elem)?;
 		}
 	}
 
@@ -1358,7 +1571,7 @@ pub fn apply_array_patch(
 		match op {
 			ArrayPatchOperation::RemoveItemByValue(mut val) => {
 				if is_ref_array {
-					val = to_value(normalise_ref(&from_value::<Ref>(val)?)?)?;
+					val = normalise_ref_value(val)?;
 				}
 
 				arr.retain(|x| *x != val);
@@ -1366,8 +1579,8 @@ pub fn apply_array_patch(
 
 			ArrayPatchOperation::AddItemAfter(mut val, mut new) => {
 				if is_ref_array {
-					val = to_value(normalise_ref(&from_value::<Ref>(val)?)?)?;
-					new = to_value(normalise_ref(&from_value::<Ref>(new)?)?)?;
+					val = normalise_ref_value(val)?;
+					new = normalise_ref_value(new)?;
 				}
 
 				let new = new.to_owned();
@@ -1376,16 +1589,17 @@ pub fn apply_array_patch(
 					arr.insert(pos + 1, new);
 				} else if permissive {
 					log::warn!("QuickEntity warning: couldn't find value to add after in array patch");
+					warnings.push(format!("Couldn't find value to add after in array patch: {}", val));
 					arr.push(new);
 				} else {
-					bail!("Couldn't find value to add after in array patch!");
+					return Err(QuickEntityError::ArrayItemNotFound { value: val });
 				}
 			}
 
 			ArrayPatchOperation::AddItemBefore(mut val, mut new) => {
 				if is_ref_array {
-					val = to_value(normalise_ref(&from_value::<Ref>(val)?)?)?;
-					new = to_value(normalise_ref(&from_value::<Ref>(new)?)?)?;
+					val = normalise_ref_value(val)?;
+					new = normalise_ref_value(new)?;
 				}
 
 				let new = new.to_owned();
@@ -1394,465 +1608,1270 @@ pub fn apply_array_patch(
 					arr.insert(pos, new);
 				} else if permissive {
 					log::warn!("QuickEntity warning: couldn't find value to add before in array patch");
+					warnings.push(format!("Couldn't find value to add before in array patch: {}", val));
 					arr.push(new);
 				} else {
-					bail!("Couldn't find value to add before in array patch!");
+					return Err(QuickEntityError::ArrayItemNotFound { value: val });
 				}
 			}
 
 			ArrayPatchOperation::AddItem(mut val) => {
 				if is_ref_array {
-					val = to_value(normalise_ref(&from_value::<Ref>(val)?)?)?;
+					val = normalise_ref_value(val)?;
 				}
 
 				arr.push(val);
 			}
+
+			ArrayPatchOperation::RemoveItemOccurrence(mut val, occurrence) => {
+				if is_ref_array {
+					val = normalise_ref_value(val)?;
+				}
+
+				if let Some(pos) = find_occurrence(arr, &val, occurrence) {
+					arr.remove(pos);
+				} else if permissive {
+					log::warn!("QuickEntity warning: couldn't find value to remove in array patch");
+					warnings.push(format!(
+						"Couldn't find value to remove in array patch: {} ({})",
+						val, occurrence
+					));
+				} else {
+					return Err(QuickEntityError::ArrayItemNotFound { value: val });
+				}
+			}
+
+			ArrayPatchOperation::InsertItemAfterOccurrence(after, mut new) => {
+				if is_ref_array {
+					new = normalise_ref_value(new)?;
+				}
+
+				match after {
+					None => arr.insert(0, new),
+
+					Some((mut val, occurrence)) => {
+						if is_ref_array {
+							val = normalise_ref_value(val)?;
+						}
+
+						if let Some(pos) = find_occurrence(arr, &val, occurrence) {
+							arr.insert(pos + 1, new);
+						} else if permissive {
+							log::warn!("QuickEntity warning: couldn't find value to insert after in array patch");
+							warnings.push(format!(
+								"Couldn't find value to insert after in array patch: {} ({})",
+								val, occurrence
+							));
+							arr.push(new);
+						} else {
+							return Err(QuickEntityError::ArrayItemNotFound { value: val });
+						}
+					}
+				}
+			}
+
+			ArrayPatchOperation::SetItemOccurrence(mut val, occurrence, mut new) => {
+				if is_ref_array {
+					val = normalise_ref_value(val)?;
+					new = normalise_ref_value(new)?;
+				}
+
+				if let Some(pos) = find_occurrence(arr, &val, occurrence) {
+					arr[pos] = new;
+				} else if permissive {
+					log::warn!("QuickEntity warning: couldn't find value to set in array patch");
+					warnings.push(format!(
+						"Couldn't find value to set in array patch: {} ({})",
+						val, occurrence
+					));
+				} else {
+					return Err(QuickEntityError::ArrayItemNotFound { value: val });
+				}
+			}
 		}
 	}
 }
 
+/// Find the position of an occurrence of a value in an array, given the number of equal items before it.
+fn find_occurrence(arr: &[Value], val: &Value, occurrence: usize) -> Option<usize> {
+	arr.iter()
+		.enumerate()
+		.filter(|(_, x)| *x == val)
+		.nth(occurrence)
+		.map(|(pos, _)| pos)
+}
+
+/// Get the occurrence of the item at a position in an array, as the item and the number of equal items before it.
+fn occurrence_at(arr: &[Value], pos: usize) -> (Value, usize) {
+	(
+		arr[pos].to_owned(),
+		arr[..pos].iter().filter(|x| **x == arr[pos]).count()
+	)
+}
+
+#[try_fn]
+#[context("Failure applying value patch")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(value, patch)))]
+pub fn apply_value_patch(
+	value: &mut Value,
+	patch: Vec<ValuePatchOperation>,
+	permissive: bool
+) -> Result<(), QuickEntityError> {
+	apply_value_patch_with_warnings(value, patch, permissive, &mut vec![])?;
+}
+
 #[try_fn]
-#[context("Failure generating patch from two entities")]
 #[auto_context]
-#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
-#[cfg_attr(feature = "rune", rune::function(keep))]
-pub fn generate_patch(original: &Entity, modified: &Entity) -> Result<Patch> {
-	if original.quick_entity_version != modified.quick_entity_version {
-		bail!("Can't create patches between differing QuickEntity versions!")
-	}
+fn apply_value_patch_with_warnings(
+	value: &mut Value,
+	patch: Vec<ValuePatchOperation>,
+	permissive: bool,
+	warnings: &mut Vec<String>
+) -> Result<()> {
+	for op in patch {
+		match op {
+			ValuePatchOperation::SetValue(pointer, new) => {
+				if let Some(existing) = value.pointer_mut(&pointer) {
+					*existing = new;
+				} else {
+					let (parent, key) = split_pointer(&pointer).context("SetValue was given an invalid pointer!")?;
 
-	let mut patch: Vec<PatchOperation> = vec![];
+					value
+						.pointer_mut(parent)
+						.and_then(|x| x.as_object_mut())
+						.with_context(|| format!("SetValue couldn't find object to set {} in!", pointer))?
+						.insert(key, new);
+				}
+			}
 
-	let mut original = original.clone();
-	let mut modified = modified.clone();
+			ValuePatchOperation::RemoveValue(pointer) => {
+				let (parent, key) = split_pointer(&pointer).context("RemoveValue was given an invalid pointer!")?;
 
-	if original.root_entity != modified.root_entity {
-		patch.push(PatchOperation::SetRootEntity(modified.root_entity.to_owned()));
+				value
+					.pointer_mut(parent)
+					.and_then(|x| x.as_object_mut())
+					.and_then(|x| x.shift_remove(&key))
+					.permit(permissive, "RemoveValue couldn't find expected value!", warnings)?;
+			}
+		}
 	}
+}
 
-	if original.sub_type != modified.sub_type {
-		patch.push(PatchOperation::SetSubType(modified.sub_type.to_owned()));
-	}
+/// Split a JSON pointer into the pointer to its parent and its final (unescaped) key.
+fn split_pointer(pointer: &str) -> Option<(&str, String)> {
+	let (parent, key) = pointer.rsplit_once('/')?;
 
-	for entity_id in original.entities.keys() {
-		if !modified.entities.contains_key(entity_id) {
-			patch.push(PatchOperation::RemoveEntityByID(entity_id.to_owned()));
-		}
-	}
+	Some((parent, key.replace("~1", "/").replace("~0", "~")))
+}
 
-	for (entity_id, new_entity_data) in &mut modified.entities {
-		if let Some(old_entity_data) = original.entities.get_mut(entity_id) {
-			if old_entity_data.parent != new_entity_data.parent {
-				patch.push(PatchOperation::SubEntityOperation(
-					entity_id.to_owned(),
-					SubEntityOperation::SetParent(new_entity_data.parent.to_owned())
-				));
+/// Generate value patch operations that turn one value into another, descending into objects so that only the parts which changed are included.
+fn generate_value_patch(old: &Value, new: &Value, pointer: &str, ops: &mut Vec<ValuePatchOperation>) {
+	match (old, new) {
+		(Value::Object(old), Value::Object(new)) => {
+			for (key, old_value) in old {
+				match new.get(key) {
+					Some(new_value) => {
+						generate_value_patch(old_value, new_value, &validation::pointer(pointer, key), ops)
+					}
+					None => ops.push(ValuePatchOperation::RemoveValue(validation::pointer(pointer, key)))
+				}
 			}
 
-			if old_entity_data.name != new_entity_data.name {
-				patch.push(PatchOperation::SubEntityOperation(
-					entity_id.to_owned(),
-					SubEntityOperation::SetName(new_entity_data.name.to_owned())
-				));
+			for (key, new_value) in new {
+				if !old.contains_key(key) {
+					ops.push(ValuePatchOperation::SetValue(
+						validation::pointer(pointer, key),
+						new_value.to_owned()
+					));
+				}
 			}
+		}
 
-			if old_entity_data.factory != new_entity_data.factory {
-				patch.push(PatchOperation::SubEntityOperation(
-					entity_id.to_owned(),
-					SubEntityOperation::SetFactory(new_entity_data.factory.to_owned())
-				));
+		_ => {
+			if old != new {
+				ops.push(ValuePatchOperation::SetValue(pointer.to_owned(), new.to_owned()));
 			}
+		}
+	}
+}
 
-			if old_entity_data.factory_flag != new_entity_data.factory_flag {
-				patch.push(PatchOperation::SubEntityOperation(
-					entity_id.to_owned(),
-					SubEntityOperation::SetFactoryFlag(new_entity_data.factory_flag.to_owned())
-				));
-			}
+/// Get a value patch for a property value if only part of it changed, so that patches changing different parts of the same value don't conflict.
+fn generate_partial_value_patch(old: &Value, new: &Value) -> Option<Vec<ValuePatchOperation>> {
+	let (old_object, new_object) = (old.as_object()?, new.as_object()?);
 
-			if old_entity_data.blueprint != new_entity_data.blueprint {
-				patch.push(PatchOperation::SubEntityOperation(
-					entity_id.to_owned(),
-					SubEntityOperation::SetBlueprint(new_entity_data.blueprint.to_owned())
-				));
-			}
+	if old_object.iter().any(|(key, value)| new_object.get(key) == Some(value)) {
+		let mut ops = vec![];
+		generate_value_patch(old, new, "", &mut ops);
+		Some(ops)
+	} else {
+		None
+	}
+}
 
-			if old_entity_data.editor_only != new_entity_data.editor_only {
-				patch.push(PatchOperation::SubEntityOperation(
-					entity_id.to_owned(),
-					SubEntityOperation::SetEditorOnly(new_entity_data.editor_only.to_owned())
-				));
-			}
+fn values_are_unique(values: &[DiffableValue]) -> bool {
+	values.iter().collect::<HashSet<_>>().len() == values.len()
+}
 
-			if old_entity_data.properties.is_none() {
-				old_entity_data.properties = Some(IndexMap::new());
+/// Generate array patch operations that turn one array into another by position rather than by value.
+///
+/// Everything before each diff operation already matches the new array by the time it's applied, so positions in the new array can be used directly; each operation is then anchored on the occurrence of the value at that position, so that it fails rather than hitting the wrong item if it's applied to a different array.
+fn generate_positional_array_patch(
+	old_value: &[DiffableValue],
+	new_value: &[DiffableValue],
+	algorithm: Algorithm
+) -> Vec<ArrayPatchOperation> {
+	let mut ops = vec![];
+
+	// The array as it is when each operation is applied
+	let mut current = old_value.iter().map(|x| x.0.to_owned()).collect::<Vec<_>>();
+
+	let remove = |current: &mut Vec<Value>, ops: &mut Vec<_>, pos: usize| {
+		let (val, occurrence) = occurrence_at(current, pos);
+		ops.push(ArrayPatchOperation::RemoveItemOccurrence(val, occurrence));
+		current.remove(pos);
+	};
+
+	let insert = |current: &mut Vec<Value>, ops: &mut Vec<_>, pos: usize| {
+		let new = new_value[pos].0.to_owned();
+		ops.push(ArrayPatchOperation::InsertItemAfterOccurrence(
+			pos.checked_sub(1).map(|x| occurrence_at(current, x)),
+			new.to_owned()
+		));
+		current.insert(pos, new);
+	};
+
+	for diff_result in capture_diff_slices(algorithm, old_value, new_value) {
+		match diff_result {
+			DiffOp::Replace {
+				old_len,
+				new_index,
+				new_len,
+				..
+			} => {
+				for pos in new_index..new_index + old_len.min(new_len) {
+					let (val, occurrence) = occurrence_at(&current, pos);
+					let new = new_value[pos].0.to_owned();
+					ops.push(ArrayPatchOperation::SetItemOccurrence(val, occurrence, new.to_owned()));
+					current[pos] = new;
+				}
+
+				for _ in new_len..old_len {
+					remove(&mut current, &mut ops, new_index + new_len);
+				}
+
+				for i in old_len..new_len {
+					insert(&mut current, &mut ops, new_index + i);
+				}
 			}
 
-			if new_entity_data.properties.is_none() {
-				new_entity_data.properties = Some(IndexMap::new());
+			DiffOp::Delete { old_len, new_index, .. } => {
+				for _ in 0..old_len {
+					remove(&mut current, &mut ops, new_index);
+				}
 			}
 
-			for property_name in old_entity_data.properties.as_ref().ctx?.keys() {
-				if !new_entity_data.properties.as_ref().ctx?.contains_key(property_name) {
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemovePropertyByName(property_name.to_owned())
-					));
+			DiffOp::Insert { new_index, new_len, .. } => {
+				for i in 0..new_len {
+					insert(&mut current, &mut ops, new_index + i);
 				}
 			}
 
-			for (property_name, new_property_data) in new_entity_data.properties.as_ref().ctx? {
-				if let Some(old_property_data) = old_entity_data.properties.as_ref().ctx?.get(property_name) {
-					if old_property_data.property_type != new_property_data.property_type {
-						patch.push(PatchOperation::SubEntityOperation(
-							entity_id.to_owned(),
-							SubEntityOperation::SetPropertyType(
-								property_name.to_owned(),
-								new_property_data.property_type.to_owned()
-							)
-						));
-					}
+			DiffOp::Equal { .. } => {}
+		}
+	}
 
-					if old_property_data.value != new_property_data.value {
-						if old_property_data.value.is_array()
-							&& new_property_data.value.is_array()
-							&& old_property_data.property_type != "ZCurve"
-							&& new_property_data.property_type != "ZCurve"
-						{
-							let old_value = old_property_data
-								.value
-								.as_array()
-								.ctx?
-								.iter()
-								.map(|x| DiffableValue(x.to_owned()))
-								.collect::<Vec<_>>();
+	ops
+}
 
-							let new_value = new_property_data
-								.value
-								.as_array()
-								.ctx?
-								.iter()
-								.map(|x| DiffableValue(x.to_owned()))
-								.collect::<Vec<_>>();
+/// The element type of an array property type, if it is one.
+fn array_element_type(property_type: &str) -> Option<&str> {
+	property_type.strip_prefix("TArray<").and_then(|x| x.strip_suffix('>'))
+}
 
-							let mut ops = vec![];
-
-							for diff_result in capture_diff_slices(Algorithm::Patience, &old_value, &new_value) {
-								match diff_result {
-									DiffOp::Replace {
-										old_index,
-										new_index,
-										old_len,
-										new_len
-									} => {
-										for i in 0..old_len {
-											ops.push(ArrayPatchOperation::RemoveItemByValue(
-												old_value[old_index + i].0.to_owned()
-											));
-										}
+/// Whether two numbers, or two values with the same structure made up of numbers, are within the tolerance of each other.
+///
+/// Integers other than angles are compared exactly, since large ones can't be represented as floats.
+fn numbers_roughly_equal(old: &Value, new: &Value, tolerance: f64, angles: bool) -> bool {
+	match (old, new) {
+		(Value::Number(old_number), Value::Number(new_number))
+			if angles || old_number.is_f64() || new_number.is_f64() =>
+		{
+			let (Some(old), Some(new)) = (old_number.as_f64(), new_number.as_f64()) else {
+				return false;
+			};
 
-										for i in (0..new_len).rev() {
-											if let Some(prev) = old_value.get(old_index - 1) {
-												ops.push(ArrayPatchOperation::AddItemAfter(
-													prev.0.to_owned(),
-													new_value[new_index + i].0.to_owned()
-												));
-											} else if let Some(next) = old_value.get(old_index + 1) {
-												ops.push(ArrayPatchOperation::AddItemBefore(
-													next.0.to_owned(),
-													new_value[new_index + i].0.to_owned()
-												));
-											} else {
-												ops.push(ArrayPatchOperation::AddItem(
-													new_value[new_index + i].0.to_owned()
-												));
-											}
-										}
-									}
+			let difference = if angles {
+				(old - new + 180.0).rem_euclid(360.0) - 180.0
+			} else {
+				old - new
+			};
 
-									DiffOp::Delete { old_index, old_len, .. } => {
-										for i in 0..old_len {
-											ops.push(ArrayPatchOperation::RemoveItemByValue(
-												old_value[old_index + i].0.to_owned()
-											));
-										}
-									}
+			difference.abs() <= tolerance
+		}
 
-									DiffOp::Insert {
-										old_index,
-										new_index,
-										new_len
-									} => {
-										for i in (0..new_len).rev() {
-											if let Some(prev) = old_value.get(old_index - 1) {
-												ops.push(ArrayPatchOperation::AddItemAfter(
-													prev.0.to_owned(),
-													new_value[new_index + i].0.to_owned()
-												));
-											} else if let Some(next) = old_value.first() {
-												ops.push(ArrayPatchOperation::AddItemBefore(
-													next.0.to_owned(),
-													new_value[new_index + i].0.to_owned()
-												));
-											} else {
-												ops.push(ArrayPatchOperation::AddItem(
-													new_value[new_index + i].0.to_owned()
-												));
-											}
-										}
-									}
+		(Value::Array(old), Value::Array(new)) => {
+			old.len() == new.len()
+				&& old
+					.iter()
+					.zip(new)
+					.all(|(old, new)| numbers_roughly_equal(old, new, tolerance, angles))
+		}
 
-									DiffOp::Equal { .. } => {}
-								}
-							}
+		(Value::Object(old), Value::Object(new)) => {
+			old.len() == new.len()
+				&& old.iter().all(|(key, old)| {
+					new.get(key)
+						.is_some_and(|new| numbers_roughly_equal(old, new, tolerance, angles))
+				})
+		}
 
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::PatchArrayPropertyValue(property_name.to_owned(), ops)
-							));
-						} else {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::SetPropertyValue(SetPropertyValue {
-									property_name: property_name.to_owned(),
-									value: new_property_data.value.to_owned()
-								})
-							));
-						}
-					}
-
-					if old_property_data.post_init != new_property_data.post_init {
-						patch.push(PatchOperation::SubEntityOperation(
-							entity_id.to_owned(),
-							SubEntityOperation::SetPropertyPostInit(
-								property_name.to_owned(),
-								new_property_data.post_init.unwrap_or(false)
-							)
-						));
-					}
-				} else {
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::AddProperty(property_name.to_owned(), new_property_data.to_owned())
-					));
-				}
-			}
+		_ => old == new
+	}
+}
 
-			// Duplicated from above except with an extra layer for platform
-			if old_entity_data.platform_specific_properties.is_none() {
-				old_entity_data.platform_specific_properties = Some(IndexMap::new());
-			}
+/// The tolerance for a component of an SMatrix43 value, and whether it's an angle.
+fn matrix_component_tolerance(component: &str, tolerance: &FloatTolerance) -> (f64, bool) {
+	match component {
+		"position" | "Trans" => (tolerance.position, false),
+		"rotation" => (tolerance.rotation, true),
+		"scale" => (tolerance.scale, false),
+		_ => (tolerance.value, false)
+	}
+}
 
-			if new_entity_data.platform_specific_properties.is_none() {
-				new_entity_data.platform_specific_properties = Some(IndexMap::new());
-			}
+/// Get a component of an SMatrix43 value, treating a missing scale as a scale of 1.
+fn matrix_component(matrix: &Map<String, Value>, component: &str) -> Option<Value> {
+	matrix
+		.get(component)
+		.cloned()
+		.or_else(|| (component == "scale").then(|| json!({ "x": 1.0, "y": 1.0, "z": 1.0 })))
+}
 
-			for platform_name in old_entity_data.platform_specific_properties.as_ref().ctx?.keys() {
-				if !new_entity_data
-					.platform_specific_properties
-					.as_ref()
-					.ctx?
-					.contains_key(platform_name)
-				{
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemovePlatformSpecificPropertiesForPlatform(platform_name.to_owned())
-					));
+/// Whether two values of a property are equal within the tolerance.
+fn values_roughly_equal(old: &Value, new: &Value, property_type: &str, tolerance: &FloatTolerance) -> bool {
+	if let Some(element_type) = array_element_type(property_type) {
+		if let (Some(old), Some(new)) = (old.as_array(), new.as_array()) {
+			return old.len() == new.len()
+				&& old
+					.iter()
+					.zip(new)
+					.all(|(old, new)| values_roughly_equal(old, new, element_type, tolerance));
+		}
+	} else if property_type == "SMatrix43" {
+		if let (Some(old), Some(new)) = (old.as_object(), new.as_object()) {
+			return old.keys().chain(new.keys()).all(|component| {
+				let (tolerance, angles) = matrix_component_tolerance(component, tolerance);
+
+				match (matrix_component(old, component), matrix_component(new, component)) {
+					(Some(old), Some(new)) => numbers_roughly_equal(&old, &new, tolerance, angles),
+					_ => false
 				}
-			}
+			});
+		}
+	}
 
-			for (platform_name, new_properties_data) in new_entity_data.platform_specific_properties.as_ref().ctx? {
-				if let Some(old_properties_data) = old_entity_data
-					.platform_specific_properties
-					.as_ref()
-					.ctx?
-					.get(platform_name)
-				{
-					for property_name in old_properties_data.keys() {
-						if !new_entity_data.properties.as_ref().ctx?.contains_key(property_name) {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::RemovePlatformSpecificPropertyByName(
-									platform_name.to_owned(),
-									property_name.to_owned()
-								)
-							));
-						}
-					}
+	numbers_roughly_equal(old, new, tolerance.value, false)
+}
 
-					for (property_name, new_property_data) in new_properties_data {
-						if let Some(old_property_data) = old_properties_data.get(property_name) {
-							if old_property_data.property_type != new_property_data.property_type {
-								patch.push(PatchOperation::SubEntityOperation(
-									entity_id.to_owned(),
-									SubEntityOperation::SetPlatformSpecificPropertyType(
-										platform_name.to_owned(),
-										property_name.to_owned(),
-										new_property_data.property_type.to_owned()
-									)
-								));
-							}
+/// Replace numbers in a modified value with the original's where they're within the tolerance, descending into objects.
+fn snap_numbers(old: &Value, new: &mut Value, tolerance: f64, angles: bool) {
+	if numbers_roughly_equal(old, new, tolerance, angles) {
+		*new = old.to_owned();
+	} else if let (Value::Object(old), Value::Object(new)) = (old, new) {
+		for (key, new_value) in new.iter_mut() {
+			if let Some(old_value) = old.get(key) {
+				snap_numbers(old_value, new_value, tolerance, angles);
+			}
+		}
+	}
+}
 
-							if old_property_data.value != new_property_data.value {
-								patch.push(PatchOperation::SubEntityOperation(
-									entity_id.to_owned(),
-									SubEntityOperation::SetPlatformSpecificPropertyValue(
-										SetPlatformSpecificPropertyValue {
-											platform: platform_name.to_owned(),
-											property_name: property_name.to_owned(),
-											value: new_property_data.value.to_owned()
-										}
-									)
-								));
-							}
+/// Replace a modified property value with the original if they're equal within the tolerance.
+///
+/// If they aren't, the parts of the value that are (array items and object values) are replaced instead, so that only real changes end up in the patch.
+fn snap_value(old: &Value, new: &mut Value, property_type: &str, tolerance: &FloatTolerance) {
+	if values_roughly_equal(old, new, property_type, tolerance) {
+		*new = old.to_owned();
+		return;
+	}
 
-							if old_property_data.post_init != new_property_data.post_init {
-								patch.push(PatchOperation::SubEntityOperation(
-									entity_id.to_owned(),
-									SubEntityOperation::SetPlatformSpecificPropertyPostInit(
-										platform_name.to_owned(),
-										property_name.to_owned(),
-										new_property_data.post_init.unwrap_or(false)
-									)
-								));
-							}
-						} else {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::AddPlatformSpecificProperty(
-									platform_name.to_owned(),
-									property_name.to_owned(),
-									new_property_data.to_owned()
-								)
-							));
-						}
-					}
-				} else {
-					for (property_name, new_property_data) in new_properties_data {
-						patch.push(PatchOperation::SubEntityOperation(
-							entity_id.to_owned(),
-							SubEntityOperation::AddPlatformSpecificProperty(
-								platform_name.to_owned(),
-								property_name.to_owned(),
-								new_property_data.to_owned()
-							)
-						))
+	match (old, new) {
+		(Value::Array(old), Value::Array(new)) => {
+			let element_type = array_element_type(property_type).unwrap_or_default();
+
+			for item in new.iter_mut() {
+				if !old.contains(item) {
+					if let Some(old_item) = old
+						.iter()
+						.find(|x| values_roughly_equal(x, item, element_type, tolerance))
+					{
+						*item = old_item.to_owned();
 					}
 				}
 			}
+		}
 
-			// An egregious amount of code duplication
-			if old_entity_data.events.is_none() {
-				old_entity_data.events = Some(IndexMap::new());
+		(Value::Object(old), Value::Object(new)) => {
+			for (key, new_value) in new.iter_mut() {
+				if let Some(old_value) = old.get(key) {
+					if property_type == "SMatrix43" {
+						let (tolerance, angles) = matrix_component_tolerance(key, tolerance);
+						snap_numbers(old_value, new_value, tolerance, angles);
+					} else {
+						snap_value(old_value, new_value, "", tolerance);
+					}
+				}
 			}
+		}
 
-			if new_entity_data.events.is_none() {
-				new_entity_data.events = Some(IndexMap::new());
-			}
+		_ => {}
+	}
+}
 
-			for event_name in old_entity_data.events.as_ref().ctx?.keys() {
-				if !new_entity_data.events.as_ref().ctx?.contains_key(event_name) {
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemoveAllEventConnectionsForEvent(event_name.to_owned())
-					));
-				}
-			}
+fn snap_properties(
+	old: Option<&IndexMap<String, Property>>,
+	new: Option<&mut IndexMap<String, Property>>,
+	tolerance: &FloatTolerance
+) {
+	for (property_name, new_property) in new.into_iter().flatten() {
+		if let Some(old_property) = old
+			.and_then(|x| x.get(property_name))
+			.filter(|x| x.property_type == new_property.property_type)
+		{
+			snap_value(
+				&old_property.value,
+				&mut new_property.value,
+				&new_property.property_type,
+				tolerance
+			);
+		}
+	}
+}
 
-			for (event_name, new_events_data) in new_entity_data.events.as_ref().ctx? {
-				if let Some(old_events_data) = old_entity_data.events.as_ref().ctx?.get(event_name) {
-					for trigger_name in old_events_data.keys() {
-						if !new_events_data.contains_key(trigger_name) {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::RemoveAllEventConnectionsForTrigger(
-									event_name.to_owned(),
-									trigger_name.to_owned()
-								)
-							));
-						}
-					}
+/// Replace a modified simple property's value with one of the original candidates if it's equal to one within the tolerance and not exactly equal to any.
+fn snap_simple_property(candidates: &[&SimpleProperty], new: &mut SimpleProperty, tolerance: &FloatTolerance) {
+	if candidates.iter().any(|x| **x == *new) {
+		return;
+	}
 
-					for (trigger_name, new_refs_data) in new_events_data {
-						if let Some(old_refs_data) = old_events_data.get(trigger_name) {
-							for i in old_refs_data {
-								if !new_refs_data.contains(i) {
-									patch.push(PatchOperation::SubEntityOperation(
-										entity_id.to_owned(),
-										SubEntityOperation::RemoveEventConnection(
-											event_name.to_owned(),
-											trigger_name.to_owned(),
-											i.to_owned()
-										)
-									))
-								}
-							}
+	if let Some(old) = candidates.iter().find(|x| {
+		x.property_type == new.property_type && values_roughly_equal(&x.value, &new.value, &x.property_type, tolerance)
+	}) {
+		new.value = old.value.to_owned();
+	}
+}
 
-							for i in new_refs_data {
-								if !old_refs_data.contains(i) {
-									patch.push(PatchOperation::SubEntityOperation(
-										entity_id.to_owned(),
-										SubEntityOperation::AddEventConnection(
-											event_name.to_owned(),
-											trigger_name.to_owned(),
-											i.to_owned()
-										)
-									))
-								}
-							}
-						} else {
-							for i in new_refs_data {
-								patch.push(PatchOperation::SubEntityOperation(
-									entity_id.to_owned(),
-									SubEntityOperation::AddEventConnection(
-										event_name.to_owned(),
-										trigger_name.to_owned(),
-										i.to_owned()
-									)
-								))
+fn snap_pins(
+	old: Option<&IndexMap<String, IndexMap<String, Vec<RefMaybeConstantValue>>>>,
+	new: Option<&mut IndexMap<String, IndexMap<String, Vec<RefMaybeConstantValue>>>>,
+	tolerance: &FloatTolerance
+) {
+	for (pin_name, new_targets) in new.into_iter().flatten() {
+		for (target_pin_name, new_refs) in new_targets {
+			let old_refs = old.and_then(|x| x.get(pin_name)).and_then(|x| x.get(target_pin_name));
+
+			for new_ref in new_refs {
+				if let RefMaybeConstantValue::RefWithConstantValue(new_ref) = new_ref {
+					let candidates = old_refs
+						.into_iter()
+						.flatten()
+						.filter_map(|x| match x {
+							RefMaybeConstantValue::RefWithConstantValue(x) if x.entity_ref == new_ref.entity_ref => {
+								Some(&x.value)
 							}
-						}
-					}
-				} else {
-					for (trigger_name, new_refs_data) in new_events_data {
-						for i in new_refs_data {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::AddEventConnection(
-									event_name.to_owned(),
-									trigger_name.to_owned(),
-									i.to_owned()
-								)
-							))
-						}
-					}
+
+							_ => None
+						})
+						.collect::<Vec<_>>();
+
+					snap_simple_property(&candidates, &mut new_ref.value, tolerance);
 				}
 			}
+		}
+	}
+}
 
-			if old_entity_data.input_copying.is_none() {
-				old_entity_data.input_copying = Some(IndexMap::new());
-			}
+/// Discard float noise in a modified entity by replacing values that are within the tolerance of the original's with the original's.
+///
+/// This covers properties, array items, constant pin values and property overrides; everything can then be compared exactly.
+fn discard_float_noise(original: &Entity, modified: &mut Entity, tolerance: &FloatTolerance) {
+	for (entity_id, new_entity_data) in &mut modified.entities {
+		let Some(old_entity_data) = original.entities.get(entity_id) else {
+			continue;
+		};
 
-			if new_entity_data.input_copying.is_none() {
-				new_entity_data.input_copying = Some(IndexMap::new());
-			}
+		snap_properties(
+			old_entity_data.properties.as_ref(),
+			new_entity_data.properties.as_mut(),
+			tolerance
+		);
 
-			for event_name in old_entity_data.input_copying.as_ref().ctx?.keys() {
-				if !new_entity_data.input_copying.as_ref().ctx?.contains_key(event_name) {
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemoveAllInputCopyConnectionsForInput(event_name.to_owned())
-					));
+		for (platform_name, new_properties) in new_entity_data.platform_specific_properties.iter_mut().flatten() {
+			snap_properties(
+				old_entity_data
+					.platform_specific_properties
+					.as_ref()
+					.and_then(|x| x.get(platform_name)),
+				Some(new_properties),
+				tolerance
+			);
+		}
+
+		snap_pins(
+			old_entity_data.events.as_ref(),
+			new_entity_data.events.as_mut(),
+			tolerance
+		);
+		snap_pins(
+			old_entity_data.input_copying.as_ref(),
+			new_entity_data.input_copying.as_mut(),
+			tolerance
+		);
+		snap_pins(
+			old_entity_data.output_copying.as_ref(),
+			new_entity_data.output_copying.as_mut(),
+			tolerance
+		);
+	}
+
+	for property_override in &mut modified.property_overrides {
+		for (property_name, new_property) in &mut property_override.properties {
+			let candidates = original
+				.property_overrides
+				.iter()
+				.filter_map(|x| x.properties.get(property_name))
+				.filter(|x| x.property_type == new_property.property_type)
+				.collect::<Vec<_>>();
+
+			if candidates.iter().all(|x| x.value != new_property.value) {
+				if let Some(old_property) = candidates
+					.iter()
+					.find(|x| values_roughly_equal(&x.value, &new_property.value, &x.property_type, tolerance))
+				{
+					new_property.value = old_property.value.to_owned();
 				}
 			}
+		}
+	}
 
-			for (event_name, new_input_copying_data) in new_entity_data.input_copying.as_ref().ctx? {
+	for pin_connection_override in &mut modified.pin_connection_overrides {
+		if let Some(new_value) = pin_connection_override.value.as_mut() {
+			let candidates = original
+				.pin_connection_overrides
+				.iter()
+				.filter(|x| {
+					x.from_entity == pin_connection_override.from_entity
+						&& x.from_pin == pin_connection_override.from_pin
+						&& x.to_entity == pin_connection_override.to_entity
+						&& x.to_pin == pin_connection_override.to_pin
+				})
+				.filter_map(|x| x.value.as_ref())
+				.collect::<Vec<_>>();
+
+			snap_simple_property(&candidates, new_value, tolerance);
+		}
+	}
+
+	for pin_connection_override_delete in &mut modified.pin_connection_override_deletes {
+		if let Some(new_value) = pin_connection_override_delete.value.as_mut() {
+			let candidates = original
+				.pin_connection_override_deletes
+				.iter()
+				.filter(|x| {
+					x.from_entity == pin_connection_override_delete.from_entity
+						&& x.from_pin == pin_connection_override_delete.from_pin
+						&& x.to_entity == pin_connection_override_delete.to_entity
+						&& x.to_pin == pin_connection_override_delete.to_pin
+				})
+				.filter_map(|x| x.value.as_ref())
+				.collect::<Vec<_>>();
+
+			snap_simple_property(&candidates, new_value, tolerance);
+		}
+	}
+}
+
+/// Normalise every entity ID in an entity, including those in references, so that IDs which only differ in length are the same.
+#[try_fn]
+#[context("Failure normalising entity IDs")]
+#[auto_context]
+fn normalise_entity_ids(entity: &mut Entity) -> Result<()> {
+	entity.root_entity = normalise_entity_id(&entity.root_entity)?;
+
+	entity.entities = std::mem::take(&mut entity.entities)
+		.into_iter()
+		.map(|(entity_id, sub_entity)| Ok((normalise_entity_id(&entity_id)?, sub_entity)))
+		.collect::<Result<_>>()?;
+
+	for sub_entity in entity.entities.values_mut() {
+		sub_entity.parent = normalise_ref(&sub_entity.parent)?;
+
+		for property in sub_entity.properties.iter_mut().flatten().map(|(_, x)| x).chain(
+			sub_entity
+				.platform_specific_properties
+				.iter_mut()
+				.flatten()
+				.flat_map(|(_, x)| x.values_mut())
+		) {
+			normalise_property_refs(&property.property_type, &mut property.value)?;
+		}
+
+		for pins in [
+			&mut sub_entity.events,
+			&mut sub_entity.input_copying,
+			&mut sub_entity.output_copying
+		] {
+			for reference in pins.iter_mut().flatten().flat_map(|(_, x)| x.values_mut()).flatten() {
+				*reference = normalise_ref_maybe_constant_value(reference)?;
+			}
+		}
+
+		for alias in sub_entity.property_aliases.iter_mut().flatten().flat_map(|(_, x)| x) {
+			alias.original_entity = normalise_ref(&alias.original_entity)?;
+		}
+
+		for reference in sub_entity
+			.exposed_entities
+			.iter_mut()
+			.flatten()
+			.flat_map(|(_, x)| &mut x.refers_to)
+		{
+			*reference = normalise_ref(reference)?;
+		}
+
+		for implementor in sub_entity.exposed_interfaces.iter_mut().flatten().map(|(_, x)| x) {
+			*implementor = normalise_entity_id(implementor)?;
+		}
+
+		for entity_id in sub_entity.subsets.iter_mut().flatten().flat_map(|(_, x)| x) {
+			*entity_id = normalise_entity_id(entity_id)?;
+		}
+	}
+
+	for property_override in &mut entity.property_overrides {
+		for reference in &mut property_override.entities {
+			*reference = normalise_ref(reference)?;
+		}
+
+		for property in property_override.properties.values_mut() {
+			normalise_property_refs(&property.property_type, &mut property.value)?;
+		}
+	}
+
+	for reference in &mut entity.override_deletes {
+		*reference = normalise_ref(reference)?;
+	}
+
+	for pin_connection_override in &mut entity.pin_connection_overrides {
+		pin_connection_override.from_entity = normalise_ref(&pin_connection_override.from_entity)?;
+		pin_connection_override.to_entity = normalise_ref(&pin_connection_override.to_entity)?;
+	}
+
+	for pin_connection_override_delete in &mut entity.pin_connection_override_deletes {
+		pin_connection_override_delete.from_entity = normalise_ref(&pin_connection_override_delete.from_entity)?;
+		pin_connection_override_delete.to_entity = normalise_ref(&pin_connection_override_delete.to_entity)?;
+	}
+
+	for comment in &mut entity.comments {
+		comment.parent = normalise_ref(&comment.parent)?;
+	}
+}
+
+#[try_fn]
+#[context("Failure normalising property references")]
+fn normalise_property_refs(property_type: &str, value: &mut Value) -> Result<()> {
+	if property_type == "SEntityTemplateReference" {
+		*value = normalise_ref_value(value.take())?;
+	} else if property_type == "TArray<SEntityTemplateReference>" {
+		for item in value.as_array_mut().into_iter().flatten() {
+			*item = normalise_ref_value(item.take())?;
+		}
+	}
+}
+
+#[try_fn]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn generate_patch(original: &Entity, modified: &Entity) -> Result<Patch, QuickEntityError> {
+	generate_patch_with(original, modified, &PatchGenOptions::default())?
+}
+
+#[try_fn]
+#[context("Failure generating patch from two entities")]
+#[auto_context]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn generate_patch_with(
+	original: &Entity,
+	modified: &Entity,
+	options: &PatchGenOptions
+) -> Result<Patch, QuickEntityError> {
+	if original.quick_entity_version != modified.quick_entity_version {
+		return Err(QuickEntityError::VersionMismatch {
+			original: original.quick_entity_version,
+			modified: modified.quick_entity_version
+		});
+	}
+
+	let mut patch: Vec<PatchOperation> = vec![];
+
+	let mut original = original.clone();
+	let mut modified = modified.clone();
+
+	if options.normalise_entity_ids {
+		normalise_entity_ids(&mut original)?;
+		normalise_entity_ids(&mut modified)?;
+	}
+
+	discard_float_noise(&original, &mut modified, &options.tolerance);
+
+	if original.root_entity != modified.root_entity {
+		patch.push(PatchOperation::SetRootEntity(modified.root_entity.to_owned()));
+	}
+
+	if original.sub_type != modified.sub_type {
+		patch.push(PatchOperation::SetSubType(modified.sub_type.to_owned()));
+	}
+
+	for entity_id in original.entities.keys() {
+		if !modified.entities.contains_key(entity_id) {
+			patch.push(PatchOperation::RemoveEntityByID(entity_id.to_owned()));
+		}
+	}
+
+	for (entity_id, new_entity_data) in &mut modified.entities {
+		if let Some(old_entity_data) = original.entities.get_mut(entity_id) {
+			if old_entity_data.parent != new_entity_data.parent {
+				patch.push(PatchOperation::SubEntityOperation(
+					entity_id.to_owned(),
+					SubEntityOperation::SetParent(new_entity_data.parent.to_owned())
+				));
+			}
+
+			if old_entity_data.name != new_entity_data.name {
+				patch.push(PatchOperation::SubEntityOperation(
+					entity_id.to_owned(),
+					SubEntityOperation::SetName(new_entity_data.name.to_owned())
+				));
+			}
+
+			if old_entity_data.factory != new_entity_data.factory {
+				patch.push(PatchOperation::SubEntityOperation(
+					entity_id.to_owned(),
+					SubEntityOperation::SetFactory(new_entity_data.factory.to_owned())
+				));
+			}
+
+			if old_entity_data.factory_flag != new_entity_data.factory_flag {
+				patch.push(PatchOperation::SubEntityOperation(
+					entity_id.to_owned(),
+					SubEntityOperation::SetFactoryFlag(new_entity_data.factory_flag.to_owned())
+				));
+			}
+
+			if old_entity_data.blueprint != new_entity_data.blueprint {
+				patch.push(PatchOperation::SubEntityOperation(
+					entity_id.to_owned(),
+					SubEntityOperation::SetBlueprint(new_entity_data.blueprint.to_owned())
+				));
+			}
+
+			if old_entity_data.editor_only != new_entity_data.editor_only {
+				patch.push(PatchOperation::SubEntityOperation(
+					entity_id.to_owned(),
+					SubEntityOperation::SetEditorOnly(new_entity_data.editor_only.to_owned())
+				));
+			}
+
+			if old_entity_data.properties.is_none() {
+				old_entity_data.properties = Some(IndexMap::new());
+			}
+
+			if new_entity_data.properties.is_none() {
+				new_entity_data.properties = Some(IndexMap::new());
+			}
+
+			for property_name in old_entity_data.properties.as_ref().ctx?.keys() {
+				if !new_entity_data.properties.as_ref().ctx?.contains_key(property_name) {
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemovePropertyByName(property_name.to_owned())
+					));
+				}
+			}
+
+			for (property_name, new_property_data) in new_entity_data.properties.as_ref().ctx? {
+				if let Some(old_property_data) = old_entity_data.properties.as_ref().ctx?.get(property_name) {
+					if old_property_data.property_type != new_property_data.property_type {
+						patch.push(PatchOperation::SubEntityOperation(
+							entity_id.to_owned(),
+							SubEntityOperation::SetPropertyType(
+								property_name.to_owned(),
+								new_property_data.property_type.to_owned()
+							)
+						));
+					}
+
+					if old_property_data.value != new_property_data.value {
+						if options.diff_arrays
+							&& old_property_data.value.is_array()
+							&& new_property_data.value.is_array()
+							&& old_property_data.property_type != "ZCurve"
+							&& new_property_data.property_type != "ZCurve"
+						{
+							let old_value = old_property_data
+								.value
+								.as_array()
+								.ctx?
+								.iter()
+								.map(|x| DiffableValue(x.to_owned()))
+								.collect::<Vec<_>>();
+
+							let new_value = new_property_data
+								.value
+								.as_array()
+								.ctx?
+								.iter()
+								.map(|x| DiffableValue(x.to_owned()))
+								.collect::<Vec<_>>();
+
+							let ops = if values_are_unique(&old_value) && values_are_unique(&new_value) {
+								let mut ops = vec![];
+
+								for diff_result in capture_diff_slices(options.algorithm.into(), &old_value, &new_value)
+								{
+									match diff_result {
+										DiffOp::Replace {
+											old_index,
+											new_index,
+											old_len,
+											new_len
+										} => {
+											for i in 0..old_len {
+												ops.push(ArrayPatchOperation::RemoveItemByValue(
+													old_value[old_index + i].0.to_owned()
+												));
+											}
+
+											for i in (0..new_len).rev() {
+												if let Some(prev) = old_value.get(old_index - 1) {
+													ops.push(ArrayPatchOperation::AddItemAfter(
+														prev.0.to_owned(),
+														new_value[new_index + i].0.to_owned()
+													));
+												} else if let Some(next) = old_value.get(old_index + 1) {
+													ops.push(ArrayPatchOperation::AddItemBefore(
+														next.0.to_owned(),
+														new_value[new_index + i].0.to_owned()
+													));
+												} else {
+													ops.push(ArrayPatchOperation::AddItem(
+														new_value[new_index + i].0.to_owned()
+													));
+												}
+											}
+										}
+
+										DiffOp::Delete { old_index, old_len, .. } => {
+											for i in 0..old_len {
+												ops.push(ArrayPatchOperation::RemoveItemByValue(
+													old_value[old_index + i].0.to_owned()
+												));
+											}
+										}
+
+										DiffOp::Insert {
+											old_index,
+											new_index,
+											new_len
+										} => {
+											for i in (0..new_len).rev() {
+												if let Some(prev) = old_value.get(old_index - 1) {
+													ops.push(ArrayPatchOperation::AddItemAfter(
+														prev.0.to_owned(),
+														new_value[new_index + i].0.to_owned()
+													));
+												} else if let Some(next) = old_value.first() {
+													ops.push(ArrayPatchOperation::AddItemBefore(
+														next.0.to_owned(),
+														new_value[new_index + i].0.to_owned()
+													));
+												} else {
+													ops.push(ArrayPatchOperation::AddItem(
+														new_value[new_index + i].0.to_owned()
+													));
+												}
+											}
+										}
+
+										DiffOp::Equal { .. } => {}
+									}
+								}
+
+								ops
+							} else {
+								// Value-based operations would be ambiguous, so use positions instead
+								generate_positional_array_patch(&old_value, &new_value, options.algorithm.into())
+							};
+
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::PatchArrayPropertyValue(property_name.to_owned(), ops)
+							));
+						} else if let Some(ops) = options
+							.partial_values
+							.then(|| generate_partial_value_patch(&old_property_data.value, &new_property_data.value))
+							.flatten()
+						{
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::PatchPropertyValue(property_name.to_owned(), ops)
+							));
+						} else {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::SetPropertyValue(SetPropertyValue {
+									property_name: property_name.to_owned(),
+									value: new_property_data.value.to_owned()
+								})
+							));
+						}
+					}
+
+					if old_property_data.post_init != new_property_data.post_init {
+						patch.push(PatchOperation::SubEntityOperation(
+							entity_id.to_owned(),
+							SubEntityOperation::SetPropertyPostInit(
+								property_name.to_owned(),
+								new_property_data.post_init.unwrap_or(false)
+							)
+						));
+					}
+				} else {
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::AddProperty(property_name.to_owned(), new_property_data.to_owned())
+					));
+				}
+			}
+
+			// Duplicated from above except with an extra layer for platform
+			if old_entity_data.platform_specific_properties.is_none() {
+				old_entity_data.platform_specific_properties = Some(IndexMap::new());
+			}
+
+			if new_entity_data.platform_specific_properties.is_none() {
+				new_entity_data.platform_specific_properties = Some(IndexMap::new());
+			}
+
+			for platform_name in old_entity_data.platform_specific_properties.as_ref().ctx?.keys() {
+				if !new_entity_data
+					.platform_specific_properties
+					.as_ref()
+					.ctx?
+					.contains_key(platform_name)
+				{
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemovePlatformSpecificPropertiesForPlatform(platform_name.to_owned())
+					));
+				}
+			}
+
+			for (platform_name, new_properties_data) in new_entity_data.platform_specific_properties.as_ref().ctx? {
+				if let Some(old_properties_data) = old_entity_data
+					.platform_specific_properties
+					.as_ref()
+					.ctx?
+					.get(platform_name)
+				{
+					for property_name in old_properties_data.keys() {
+						if !new_entity_data.properties.as_ref().ctx?.contains_key(property_name) {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::RemovePlatformSpecificPropertyByName(
+									platform_name.to_owned(),
+									property_name.to_owned()
+								)
+							));
+						}
+					}
+
+					for (property_name, new_property_data) in new_properties_data {
+						if let Some(old_property_data) = old_properties_data.get(property_name) {
+							if old_property_data.property_type != new_property_data.property_type {
+								patch.push(PatchOperation::SubEntityOperation(
+									entity_id.to_owned(),
+									SubEntityOperation::SetPlatformSpecificPropertyType(
+										platform_name.to_owned(),
+										property_name.to_owned(),
+										new_property_data.property_type.to_owned()
+									)
+								));
+							}
+
+							if old_property_data.value != new_property_data.value {
+								let partial_patch = options
+									.partial_values
+									.then(|| {
+										generate_partial_value_patch(&old_property_data.value, &new_property_data.value)
+									})
+									.flatten();
+
+								patch.push(PatchOperation::SubEntityOperation(
+									entity_id.to_owned(),
+									match partial_patch {
+										Some(ops) => SubEntityOperation::PatchPlatformSpecificPropertyValue(
+											platform_name.to_owned(),
+											property_name.to_owned(),
+											ops
+										),
+
+										None => SubEntityOperation::SetPlatformSpecificPropertyValue(
+											SetPlatformSpecificPropertyValue {
+												platform: platform_name.to_owned(),
+												property_name: property_name.to_owned(),
+												value: new_property_data.value.to_owned()
+											}
+										)
+									}
+								));
+							}
+
+							if old_property_data.post_init != new_property_data.post_init {
+								patch.push(PatchOperation::SubEntityOperation(
+									entity_id.to_owned(),
+									SubEntityOperation::SetPlatformSpecificPropertyPostInit(
+										platform_name.to_owned(),
+										property_name.to_owned(),
+										new_property_data.post_init.unwrap_or(false)
+									)
+								));
+							}
+						} else {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::AddPlatformSpecificProperty(
+									platform_name.to_owned(),
+									property_name.to_owned(),
+									new_property_data.to_owned()
+								)
+							));
+						}
+					}
+				} else {
+					for (property_name, new_property_data) in new_properties_data {
+						patch.push(PatchOperation::SubEntityOperation(
+							entity_id.to_owned(),
+							SubEntityOperation::AddPlatformSpecificProperty(
+								platform_name.to_owned(),
+								property_name.to_owned(),
+								new_property_data.to_owned()
+							)
+						))
+					}
+				}
+			}
+
+			// An egregious amount of code duplication
+			if old_entity_data.events.is_none() {
+				old_entity_data.events = Some(IndexMap::new());
+			}
+
+			if new_entity_data.events.is_none() {
+				new_entity_data.events = Some(IndexMap::new());
+			}
+
+			for event_name in old_entity_data.events.as_ref().ctx?.keys() {
+				if !new_entity_data.events.as_ref().ctx?.contains_key(event_name) {
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemoveAllEventConnectionsForEvent(event_name.to_owned())
+					));
+				}
+			}
+
+			for (event_name, new_events_data) in new_entity_data.events.as_ref().ctx? {
+				if let Some(old_events_data) = old_entity_data.events.as_ref().ctx?.get(event_name) {
+					for trigger_name in old_events_data.keys() {
+						if !new_events_data.contains_key(trigger_name) {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::RemoveAllEventConnectionsForTrigger(
+									event_name.to_owned(),
+									trigger_name.to_owned()
+								)
+							));
+						}
+					}
+
+					for (trigger_name, new_refs_data) in new_events_data {
+						if let Some(old_refs_data) = old_events_data.get(trigger_name) {
+							for i in old_refs_data {
+								if !new_refs_data.contains(i) {
+									patch.push(PatchOperation::SubEntityOperation(
+										entity_id.to_owned(),
+										SubEntityOperation::RemoveEventConnection(
+											event_name.to_owned(),
+											trigger_name.to_owned(),
+											i.to_owned()
+										)
+									))
+								}
+							}
+
+							for i in new_refs_data {
+								if !old_refs_data.contains(i) {
+									patch.push(PatchOperation::SubEntityOperation(
+										entity_id.to_owned(),
+										SubEntityOperation::AddEventConnection(
+											event_name.to_owned(),
+											trigger_name.to_owned(),
+											i.to_owned()
+										)
+									))
+								}
+							}
+						} else {
+							for i in new_refs_data {
+								patch.push(PatchOperation::SubEntityOperation(
+									entity_id.to_owned(),
+									SubEntityOperation::AddEventConnection(
+										event_name.to_owned(),
+										trigger_name.to_owned(),
+										i.to_owned()
+									)
+								))
+							}
+						}
+					}
+				} else {
+					for (trigger_name, new_refs_data) in new_events_data {
+						for i in new_refs_data {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::AddEventConnection(
+									event_name.to_owned(),
+									trigger_name.to_owned(),
+									i.to_owned()
+								)
+							))
+						}
+					}
+				}
+			}
+
+			if old_entity_data.input_copying.is_none() {
+				old_entity_data.input_copying = Some(IndexMap::new());
+			}
+
+			if new_entity_data.input_copying.is_none() {
+				new_entity_data.input_copying = Some(IndexMap::new());
+			}
+
+			for event_name in old_entity_data.input_copying.as_ref().ctx?.keys() {
+				if !new_entity_data.input_copying.as_ref().ctx?.contains_key(event_name) {
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemoveAllInputCopyConnectionsForInput(event_name.to_owned())
+					));
+				}
+			}
+
+			for (event_name, new_input_copying_data) in new_entity_data.input_copying.as_ref().ctx? {
 				if let Some(old_input_copying_data) = old_entity_data.input_copying.as_ref().ctx?.get(event_name) {
 					for trigger_name in old_input_copying_data.keys() {
 						if !new_input_copying_data.contains_key(trigger_name) {
 							patch.push(PatchOperation::SubEntityOperation(
 								entity_id.to_owned(),
-								SubEntityOperation::RemoveAllInputCopyConnectionsForTrigger(
+								SubEntityOperation::RemoveAllInputCopyConnectionsForTrigger(
+									event_name.to_owned(),
+									trigger_name.to_owned()
+								)
+							));
+						}
+					}
+
+					for (trigger_name, new_refs_data) in new_input_copying_data {
+						if let Some(old_refs_data) = old_input_copying_data.get(trigger_name) {
+							for i in old_refs_data {
+								if !new_refs_data.contains(i) {
+									patch.push(PatchOperation::SubEntityOperation(
+										entity_id.to_owned(),
+										SubEntityOperation::RemoveInputCopyConnection(
+											event_name.to_owned(),
+											trigger_name.to_owned(),
+											i.to_owned()
+										)
+									))
+								}
+							}
+
+							for i in new_refs_data {
+								if !old_refs_data.contains(i) {
+									patch.push(PatchOperation::SubEntityOperation(
+										entity_id.to_owned(),
+										SubEntityOperation::AddInputCopyConnection(
+											event_name.to_owned(),
+											trigger_name.to_owned(),
+											i.to_owned()
+										)
+									))
+								}
+							}
+						} else {
+							for i in new_refs_data {
+								patch.push(PatchOperation::SubEntityOperation(
+									entity_id.to_owned(),
+									SubEntityOperation::AddInputCopyConnection(
+										event_name.to_owned(),
+										trigger_name.to_owned(),
+										i.to_owned()
+									)
+								))
+							}
+						}
+					}
+				} else {
+					for (trigger_name, new_refs_data) in new_input_copying_data {
+						for i in new_refs_data {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::AddInputCopyConnection(
+									event_name.to_owned(),
+									trigger_name.to_owned(),
+									i.to_owned()
+								)
+							))
+						}
+					}
+				}
+			}
+
+			if old_entity_data.output_copying.is_none() {
+				old_entity_data.output_copying = Some(IndexMap::new());
+			}
+
+			if new_entity_data.output_copying.is_none() {
+				new_entity_data.output_copying = Some(IndexMap::new());
+			}
+
+			for event_name in old_entity_data.output_copying.as_ref().ctx?.keys() {
+				if !new_entity_data.output_copying.as_ref().ctx?.contains_key(event_name) {
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemoveAllOutputCopyConnectionsForOutput(event_name.to_owned())
+					));
+				}
+			}
+
+			for (event_name, new_output_copying_data) in new_entity_data.output_copying.as_ref().ctx? {
+				if let Some(old_output_copying_data) = old_entity_data.output_copying.as_ref().ctx?.get(event_name) {
+					for trigger_name in old_output_copying_data.keys() {
+						if !new_output_copying_data.contains_key(trigger_name) {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::RemoveAllOutputCopyConnectionsForPropagate(
 									event_name.to_owned(),
 									trigger_name.to_owned()
 								)
@@ -1860,18 +2879,18 @@ pub fn generate_patch(original: &Entity, modified: &Entity) -> Result<Patch> {
 						}
 					}
 
-					for (trigger_name, new_refs_data) in new_input_copying_data {
-						if let Some(old_refs_data) = old_input_copying_data.get(trigger_name) {
+					for (trigger_name, new_refs_data) in new_output_copying_data {
+						if let Some(old_refs_data) = old_output_copying_data.get(trigger_name) {
 							for i in old_refs_data {
 								if !new_refs_data.contains(i) {
 									patch.push(PatchOperation::SubEntityOperation(
 										entity_id.to_owned(),
-										SubEntityOperation::RemoveInputCopyConnection(
+										SubEntityOperation::RemoveOutputCopyConnection(
 											event_name.to_owned(),
 											trigger_name.to_owned(),
 											i.to_owned()
 										)
-									))
+									));
 								}
 							}
 
@@ -1879,478 +2898,1604 @@ pub fn generate_patch(original: &Entity, modified: &Entity) -> Result<Patch> {
 								if !old_refs_data.contains(i) {
 									patch.push(PatchOperation::SubEntityOperation(
 										entity_id.to_owned(),
-										SubEntityOperation::AddInputCopyConnection(
+										SubEntityOperation::AddOutputCopyConnection(
 											event_name.to_owned(),
 											trigger_name.to_owned(),
 											i.to_owned()
 										)
-									))
+									));
 								}
 							}
 						} else {
 							for i in new_refs_data {
 								patch.push(PatchOperation::SubEntityOperation(
 									entity_id.to_owned(),
-									SubEntityOperation::AddInputCopyConnection(
+									SubEntityOperation::AddOutputCopyConnection(
 										event_name.to_owned(),
 										trigger_name.to_owned(),
 										i.to_owned()
 									)
-								))
+								));
 							}
 						}
 					}
-				} else {
-					for (trigger_name, new_refs_data) in new_input_copying_data {
-						for i in new_refs_data {
+				} else {
+					for (trigger_name, new_refs_data) in new_output_copying_data {
+						for i in new_refs_data {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::AddOutputCopyConnection(
+									event_name.to_owned(),
+									trigger_name.to_owned(),
+									i.to_owned()
+								)
+							));
+						}
+					}
+				}
+			}
+
+			if old_entity_data.property_aliases.is_none() {
+				old_entity_data.property_aliases = Some(IndexMap::new());
+			}
+
+			if new_entity_data.property_aliases.is_none() {
+				new_entity_data.property_aliases = Some(IndexMap::new());
+			}
+
+			for alias_name in old_entity_data.property_aliases.as_ref().ctx?.keys() {
+				if !new_entity_data.property_aliases.as_ref().ctx?.contains_key(alias_name) {
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemovePropertyAlias(alias_name.to_owned())
+					));
+				}
+			}
+
+			for (alias_name, new_alias_connections) in new_entity_data.property_aliases.as_ref().ctx? {
+				if let Some(old_alias_connections) = old_entity_data.property_aliases.as_ref().ctx?.get(alias_name) {
+					for connection in new_alias_connections {
+						if !old_alias_connections.contains(connection) {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::AddPropertyAliasConnection(
+									alias_name.to_owned(),
+									connection.to_owned()
+								)
+							));
+						}
+					}
+
+					for connection in old_alias_connections {
+						if !new_alias_connections.contains(connection) {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::RemoveConnectionForPropertyAlias(
+									alias_name.to_owned(),
+									connection.to_owned()
+								)
+							));
+						}
+					}
+				} else {
+					for connection in new_alias_connections {
+						patch.push(PatchOperation::SubEntityOperation(
+							entity_id.to_owned(),
+							SubEntityOperation::AddPropertyAliasConnection(
+								alias_name.to_owned(),
+								connection.to_owned()
+							)
+						));
+					}
+				}
+			}
+
+			if old_entity_data.exposed_entities.is_none() {
+				old_entity_data.exposed_entities = Some(IndexMap::new());
+			}
+
+			if new_entity_data.exposed_entities.is_none() {
+				new_entity_data.exposed_entities = Some(IndexMap::new());
+			}
+
+			for exposed_entity in old_entity_data.exposed_entities.as_ref().ctx?.keys() {
+				if !new_entity_data
+					.exposed_entities
+					.as_ref()
+					.ctx?
+					.contains_key(exposed_entity)
+				{
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemoveExposedEntity(exposed_entity.to_owned())
+					));
+				}
+			}
+
+			for (exposed_entity, data) in new_entity_data.exposed_entities.as_ref().ctx? {
+				if !old_entity_data
+					.exposed_entities
+					.as_ref()
+					.ctx?
+					.contains_key(exposed_entity)
+					|| old_entity_data.exposed_entities.as_ref().ctx?.get(exposed_entity).ctx? != data
+				{
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::SetExposedEntity(exposed_entity.to_owned(), data.to_owned())
+					));
+				}
+			}
+
+			if old_entity_data.exposed_interfaces.is_none() {
+				old_entity_data.exposed_interfaces = Some(IndexMap::new());
+			}
+
+			if new_entity_data.exposed_interfaces.is_none() {
+				new_entity_data.exposed_interfaces = Some(IndexMap::new());
+			}
+
+			for exposed_interface in old_entity_data.exposed_interfaces.as_ref().ctx?.keys() {
+				if !new_entity_data
+					.exposed_interfaces
+					.as_ref()
+					.ctx?
+					.contains_key(exposed_interface)
+				{
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemoveExposedInterface(exposed_interface.to_owned())
+					));
+				}
+			}
+
+			for (exposed_interface, data) in new_entity_data.exposed_interfaces.as_ref().ctx? {
+				if !old_entity_data
+					.exposed_interfaces
+					.as_ref()
+					.ctx?
+					.contains_key(exposed_interface)
+					|| old_entity_data
+						.exposed_interfaces
+						.as_ref()
+						.ctx?
+						.get(exposed_interface)
+						.ctx? != data
+				{
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::SetExposedInterface(exposed_interface.to_owned(), data.to_owned())
+					));
+				}
+			}
+
+			if old_entity_data.subsets.is_none() {
+				old_entity_data.subsets = Some(IndexMap::new());
+			}
+
+			if new_entity_data.subsets.is_none() {
+				new_entity_data.subsets = Some(IndexMap::new());
+			}
+
+			for subset_name in old_entity_data.subsets.as_ref().ctx?.keys() {
+				if !new_entity_data.subsets.as_ref().ctx?.contains_key(subset_name) {
+					patch.push(PatchOperation::SubEntityOperation(
+						entity_id.to_owned(),
+						SubEntityOperation::RemoveAllSubsetsFor(subset_name.to_owned())
+					));
+				}
+			}
+
+			for (subset_name, new_refs_data) in new_entity_data.subsets.as_ref().ctx? {
+				if let Some(old_refs_data) = old_entity_data.subsets.as_ref().ctx?.get(subset_name) {
+					for i in old_refs_data {
+						if !new_refs_data.contains(i) {
+							patch.push(PatchOperation::SubEntityOperation(
+								entity_id.to_owned(),
+								SubEntityOperation::RemoveSubset(subset_name.to_owned(), i.to_owned())
+							));
+						}
+					}
+
+					for i in new_refs_data {
+						if !old_refs_data.contains(i) {
 							patch.push(PatchOperation::SubEntityOperation(
 								entity_id.to_owned(),
-								SubEntityOperation::AddInputCopyConnection(
-									event_name.to_owned(),
-									trigger_name.to_owned(),
-									i.to_owned()
-								)
-							))
+								SubEntityOperation::AddSubset(subset_name.to_owned(), i.to_owned())
+							));
 						}
 					}
+				} else {
+					for i in new_refs_data {
+						patch.push(PatchOperation::SubEntityOperation(
+							entity_id.to_owned(),
+							SubEntityOperation::AddSubset(subset_name.to_owned(), i.to_owned())
+						));
+					}
+				}
+			}
+		} else {
+			patch.push(PatchOperation::AddEntity(
+				entity_id.to_owned(),
+				new_entity_data.to_owned()
+			));
+		}
+	}
+
+	let original_unravelled_overrides: Vec<PropertyOverrideConnection> = original
+		.property_overrides
+		.iter()
+		.flat_map(|property_override| {
+			property_override
+				.entities
+				.iter()
+				.flat_map(|ent| {
+					property_override
+						.properties
+						.iter()
+						.map(|(prop_name, prop_val)| PropertyOverrideConnection {
+							entity: ent.to_owned(),
+							property_name: prop_name.to_owned(),
+							property_override: prop_val.to_owned()
+						})
+						.collect_vec()
+				})
+				.collect_vec()
+		})
+		.collect();
+
+	let modified_unravelled_overrides: Vec<PropertyOverrideConnection> = modified
+		.property_overrides
+		.iter()
+		.flat_map(|property_override| {
+			property_override
+				.entities
+				.iter()
+				.flat_map(|ent| {
+					property_override
+						.properties
+						.iter()
+						.map(|(prop_name, prop_val)| PropertyOverrideConnection {
+							entity: ent.to_owned(),
+							property_name: prop_name.to_owned(),
+							property_override: prop_val.to_owned()
+						})
+						.collect_vec()
+				})
+				.collect_vec()
+		})
+		.collect();
+
+	for x in &original_unravelled_overrides {
+		if modified_unravelled_overrides
+			.iter()
+			.try_find(|val| -> Result<bool> {
+				Ok(val.entity == x.entity
+					&& val.property_name == x.property_name
+					&& property_is_roughly_identical(&val.property_override, &x.property_override)?)
+			})?
+			.is_none()
+		{
+			patch.push(PatchOperation::RemovePropertyOverrideConnection(x.to_owned()))
+		}
+	}
+
+	for x in &modified_unravelled_overrides {
+		if original_unravelled_overrides
+			.iter()
+			.try_find(|val| -> Result<bool> {
+				Ok(val.entity == x.entity
+					&& val.property_name == x.property_name
+					&& property_is_roughly_identical(&val.property_override, &x.property_override)?)
+			})?
+			.is_none()
+		{
+			patch.push(PatchOperation::AddPropertyOverrideConnection(x.to_owned()))
+		}
+	}
+
+	for x in &original.override_deletes {
+		if !modified.override_deletes.contains(x) {
+			patch.push(PatchOperation::RemoveOverrideDelete(x.to_owned()))
+		}
+	}
+
+	for x in &modified.override_deletes {
+		if !original.override_deletes.contains(x) {
+			patch.push(PatchOperation::AddOverrideDelete(x.to_owned()))
+		}
+	}
+
+	for x in &original.pin_connection_overrides {
+		if !modified.pin_connection_overrides.contains(x) {
+			patch.push(PatchOperation::RemovePinConnectionOverride(x.to_owned()))
+		}
+	}
+
+	for x in &modified.pin_connection_overrides {
+		if !original.pin_connection_overrides.contains(x) {
+			patch.push(PatchOperation::AddPinConnectionOverride(x.to_owned()))
+		}
+	}
+
+	for x in &original.pin_connection_override_deletes {
+		if !modified.pin_connection_override_deletes.contains(x) {
+			patch.push(PatchOperation::RemovePinConnectionOverrideDelete(x.to_owned()))
+		}
+	}
+
+	for x in &modified.pin_connection_override_deletes {
+		if !original.pin_connection_override_deletes.contains(x) {
+			patch.push(PatchOperation::AddPinConnectionOverrideDelete(x.to_owned()))
+		}
+	}
+
+	if options.diff_external_scenes {
+		for x in &original.external_scenes {
+			if !modified.external_scenes.contains(x) {
+				patch.push(PatchOperation::RemoveExternalScene(x.to_owned()))
+			}
+		}
+
+		for x in &modified.external_scenes {
+			if !original.external_scenes.contains(x) {
+				patch.push(PatchOperation::AddExternalScene(x.to_owned()))
+			}
+		}
+	}
+
+	if options.diff_dependencies {
+		for x in &original.extra_factory_dependencies {
+			if !modified.extra_factory_dependencies.contains(x) {
+				patch.push(PatchOperation::RemoveExtraFactoryDependency(x.to_owned()))
+			}
+		}
+
+		for x in &modified.extra_factory_dependencies {
+			if !original.extra_factory_dependencies.contains(x) {
+				patch.push(PatchOperation::AddExtraFactoryDependency(x.to_owned()))
+			}
+		}
+
+		for x in &original.extra_blueprint_dependencies {
+			if !modified.extra_blueprint_dependencies.contains(x) {
+				patch.push(PatchOperation::RemoveExtraBlueprintDependency(x.to_owned()))
+			}
+		}
+
+		for x in &modified.extra_blueprint_dependencies {
+			if !original.extra_blueprint_dependencies.contains(x) {
+				patch.push(PatchOperation::AddExtraBlueprintDependency(x.to_owned()))
+			}
+		}
+	}
+
+	if options.diff_comments {
+		for x in &original.comments {
+			if !modified.comments.contains(x) {
+				patch.push(PatchOperation::RemoveComment(x.to_owned()))
+			}
+		}
+
+		for x in &modified.comments {
+			if !original.comments.contains(x) {
+				patch.push(PatchOperation::AddComment(x.to_owned()))
+			}
+		}
+	}
+
+	Patch {
+		factory_hash: modified.factory_hash,
+		blueprint_hash: modified.blueprint_hash,
+		patch,
+		patch_version: CURRENT_PATCH_VERSION
+	}
+}
+
+#[try_fn]
+#[context("Failure merging patches")]
+#[auto_context]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn merge_patches(base: &Entity, patches: &[Patch]) -> Result<MergeResult, QuickEntityError> {
+	check_patches_are_for(base, patches)?;
+
+	// Each patch is applied to the base and regenerated so that all patches are expressed in the same terms
+	let operations = patches
+		.iter()
+		.enumerate()
+		.map(|(index, patch)| -> Result<Vec<PatchOperation>> {
+			let mut entity = base.to_owned();
+
+			apply_patch(&mut entity, patch.to_owned(), false)
+				.map_err(|x| x.context(format!("Patch {} couldn't be applied to the base entity", index)))?;
+
+			Ok(generate_patch(base, &entity)
+				.map_err(|x| x.context(format!("Couldn't regenerate patch {}", index)))?
+				.patch)
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	// Operations are identified by their patch index and their index within the (regenerated) patch
+	let mut operations_by_scope: HashMap<Vec<String>, Vec<(_, &PatchOperation)>> = HashMap::new();
+
+	for (patch_index, patch_operations) in operations.iter().enumerate() {
+		for (operation_index, operation) in patch_operations.iter().enumerate() {
+			operations_by_scope
+				.entry(get_patch_operation_scope(operation)?)
+				.or_default()
+				.push(((patch_index, operation_index), operation));
+		}
+	}
+
+	let mut conflicts = vec![];
+
+	for (scope, scope_operations) in &operations_by_scope {
+		for (position, operation) in scope_operations {
+			// An operation conflicts with operations from other patches on the same part of the entity or any part containing it
+			for prefix_len in 1..=scope.len() {
+				for (other_position, other_operation) in
+					operations_by_scope.get(&scope[0..prefix_len]).into_iter().flatten()
+				{
+					let is_same_scope = prefix_len == scope.len();
+
+					if other_position.0 != position.0
+						&& (!is_same_scope || other_position.0 < position.0)
+						&& !patch_operations_are_compatible(other_operation, operation)
+					{
+						let ((first_position, first_operation), (second_position, second_operation)) =
+							if other_position.0 < position.0 {
+								((other_position, other_operation), (position, operation))
+							} else {
+								((position, operation), (other_position, other_operation))
+							};
+
+						conflicts.push((
+							(*first_position, *second_position),
+							MergeConflict {
+								first_patch: first_position.0,
+								first_operation: (*first_operation).to_owned(),
+								second_patch: second_position.0,
+								second_operation: (*second_operation).to_owned()
+							}
+						));
+					}
+				}
+			}
+		}
+	}
+
+	if conflicts.is_empty() {
+		let mut seen = HashSet::new();
+		let mut patch = vec![];
+
+		for operation in operations.into_iter().flatten() {
+			if seen.insert(to_string(&operation).ctx?) {
+				patch.push(operation);
+			}
+		}
+
+		MergeResult::Merged(Patch {
+			factory_hash: base.factory_hash.to_owned(),
+			blueprint_hash: base.blueprint_hash.to_owned(),
+			patch,
+			patch_version: CURRENT_PATCH_VERSION
+		})
+	} else {
+		// The scopes are visited in an arbitrary order, so sort the conflicts to make the result deterministic
+		conflicts.sort_by_key(|(positions, _)| *positions);
+
+		MergeResult::Conflicts(conflicts.into_iter().map(|(_, conflict)| conflict).collect())
+	}
+}
+
+#[try_fn]
+#[context("Failure composing patches")]
+#[auto_context]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn compose_patches(base: &Entity, patches: &[Patch]) -> Result<Patch, QuickEntityError> {
+	check_patches_are_for(base, patches)?;
+
+	let mut entity = base.to_owned();
+
+	for (index, patch) in patches.iter().enumerate() {
+		apply_patch(&mut entity, patch.to_owned(), false)
+			.map_err(|x| x.context(format!("Patch {} couldn't be applied", index)))?;
+	}
+
+	// Regenerating the patch from the end result leaves out any operations that were cancelled out or overwritten by later ones
+	generate_patch(base, &entity)?
+}
+
+/// Check that every patch was made for the given entity, by its factory and blueprint hashes.
+fn check_patches_are_for(base: &Entity, patches: &[Patch]) -> Result<(), QuickEntityError> {
+	for (index, patch) in patches.iter().enumerate() {
+		for (expected, found) in [
+			(&base.factory_hash, &patch.factory_hash),
+			(&base.blueprint_hash, &patch.blueprint_hash)
+		] {
+			if !hashes_match(expected, found) {
+				return Err(QuickEntityError::PatchEntityMismatch {
+					index,
+					expected: expected.to_owned(),
+					found: found.to_owned()
+				});
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Whether two operations from different patches can both be applied, given that they affect the same part of the entity.
+fn patch_operations_are_compatible(first: &PatchOperation, second: &PatchOperation) -> bool {
+	first == second
+		|| match (first, second) {
+			(
+				PatchOperation::SubEntityOperation(_, SubEntityOperation::PatchArrayPropertyValue(_, first)),
+				PatchOperation::SubEntityOperation(_, SubEntityOperation::PatchArrayPropertyValue(_, second))
+			)
+			| (
+				PatchOperation::SubEntityOperation(
+					_,
+					SubEntityOperation::PatchPlatformSpecificArrayPropertyValue(_, _, first)
+				),
+				PatchOperation::SubEntityOperation(
+					_,
+					SubEntityOperation::PatchPlatformSpecificArrayPropertyValue(_, _, second)
+				)
+			) => {
+				// Occurrences are counted in the array a patch was made against, so they can't be combined with other changes to it
+				!array_patch_is_positional(first) && !array_patch_is_positional(second)
+			}
+
+			(
+				PatchOperation::SubEntityOperation(_, SubEntityOperation::PatchPropertyValue(_, first)),
+				PatchOperation::SubEntityOperation(_, SubEntityOperation::PatchPropertyValue(_, second))
+			)
+			| (
+				PatchOperation::SubEntityOperation(
+					_,
+					SubEntityOperation::PatchPlatformSpecificPropertyValue(_, _, first)
+				),
+				PatchOperation::SubEntityOperation(
+					_,
+					SubEntityOperation::PatchPlatformSpecificPropertyValue(_, _, second)
+				)
+			) => first.iter().all(|first| {
+				second
+					.iter()
+					.all(|second| !value_pointers_overlap(value_patch_pointer(first), value_patch_pointer(second)))
+			}),
+
+			_ => false
+		}
+}
+
+fn value_patch_pointer(operation: &ValuePatchOperation) -> &str {
+	match operation {
+		ValuePatchOperation::SetValue(pointer, _) | ValuePatchOperation::RemoveValue(pointer) => pointer
+	}
+}
+
+/// Whether one JSON pointer is within the other (or they're the same).
+fn value_pointers_overlap(first: &str, second: &str) -> bool {
+	let within = |x: &str, y: &str| x == y || x.strip_prefix(y).is_some_and(|x| x.starts_with('/'));
+
+	within(first, second) || within(second, first)
+}
+
+fn array_patch_is_positional(patch: &[ArrayPatchOperation]) -> bool {
+	patch.iter().any(|x| {
+		matches!(
+			x,
+			ArrayPatchOperation::RemoveItemOccurrence(..)
+				| ArrayPatchOperation::InsertItemAfterOccurrence(..)
+				| ArrayPatchOperation::SetItemOccurrence(..)
+		)
+	})
+}
+
+/// Get the path to the part of the entity that a patch operation affects.
+///
+/// Operations affecting a part of the entity also affect all parts within it, so an operation's scope is a prefix of the scopes of any operations it could conflict with.
+#[try_fn]
+#[context("Failure getting patch operation scope")]
+#[auto_context]
+fn get_patch_operation_scope(operation: &PatchOperation) -> Result<Vec<String>> {
+	match operation {
+		PatchOperation::SetRootEntity(_) => vec!["rootEntity".into()],
+		PatchOperation::SetSubType(_) => vec!["subType".into()],
+
+		PatchOperation::AddEntity(id, _) | PatchOperation::RemoveEntityByID(id) => {
+			vec!["entities".into(), normalise_entity_id(id)?]
+		}
+
+		PatchOperation::SubEntityOperation(id, op) => [
+			vec!["entities".into(), normalise_entity_id(id)?],
+			match op {
+				SubEntityOperation::SetParent(_) => vec!["parent".into()],
+				SubEntityOperation::SetName(_) => vec!["name".into()],
+				SubEntityOperation::SetFactory(_) => vec!["factory".into()],
+				SubEntityOperation::SetFactoryFlag(_) => vec!["factoryFlag".into()],
+				SubEntityOperation::SetBlueprint(_) => vec!["blueprint".into()],
+				SubEntityOperation::SetEditorOnly(_) => vec!["editorOnly".into()],
+
+				SubEntityOperation::AddProperty(name, _) | SubEntityOperation::RemovePropertyByName(name) => {
+					vec!["properties".into(), name.to_owned()]
 				}
-			}
 
-			if old_entity_data.output_copying.is_none() {
-				old_entity_data.output_copying = Some(IndexMap::new());
-			}
+				SubEntityOperation::SetPropertyType(name, _) => {
+					vec!["properties".into(), name.to_owned(), "type".into()]
+				}
 
-			if new_entity_data.output_copying.is_none() {
-				new_entity_data.output_copying = Some(IndexMap::new());
-			}
+				SubEntityOperation::SetPropertyValue(SetPropertyValue { property_name, .. })
+				| SubEntityOperation::PatchArrayPropertyValue(property_name, _)
+				| SubEntityOperation::PatchPropertyValue(property_name, _) => {
+					vec!["properties".into(), property_name.to_owned(), "value".into()]
+				}
 
-			for event_name in old_entity_data.output_copying.as_ref().ctx?.keys() {
-				if !new_entity_data.output_copying.as_ref().ctx?.contains_key(event_name) {
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemoveAllOutputCopyConnectionsForOutput(event_name.to_owned())
-					));
+				SubEntityOperation::SetPropertyPostInit(name, _) => {
+					vec!["properties".into(), name.to_owned(), "postInit".into()]
 				}
-			}
 
-			for (event_name, new_output_copying_data) in new_entity_data.output_copying.as_ref().ctx? {
-				if let Some(old_output_copying_data) = old_entity_data.output_copying.as_ref().ctx?.get(event_name) {
-					for trigger_name in old_output_copying_data.keys() {
-						if !new_output_copying_data.contains_key(trigger_name) {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::RemoveAllOutputCopyConnectionsForPropagate(
-									event_name.to_owned(),
-									trigger_name.to_owned()
-								)
-							));
-						}
-					}
+				SubEntityOperation::RemovePlatformSpecificPropertiesForPlatform(platform) => {
+					vec!["platformSpecificProperties".into(), platform.to_owned()]
+				}
 
-					for (trigger_name, new_refs_data) in new_output_copying_data {
-						if let Some(old_refs_data) = old_output_copying_data.get(trigger_name) {
-							for i in old_refs_data {
-								if !new_refs_data.contains(i) {
-									patch.push(PatchOperation::SubEntityOperation(
-										entity_id.to_owned(),
-										SubEntityOperation::RemoveOutputCopyConnection(
-											event_name.to_owned(),
-											trigger_name.to_owned(),
-											i.to_owned()
-										)
-									));
-								}
-							}
+				SubEntityOperation::AddPlatformSpecificProperty(platform, name, _)
+				| SubEntityOperation::RemovePlatformSpecificPropertyByName(platform, name) => {
+					vec![
+						"platformSpecificProperties".into(),
+						platform.to_owned(),
+						name.to_owned(),
+					]
+				}
 
-							for i in new_refs_data {
-								if !old_refs_data.contains(i) {
-									patch.push(PatchOperation::SubEntityOperation(
-										entity_id.to_owned(),
-										SubEntityOperation::AddOutputCopyConnection(
-											event_name.to_owned(),
-											trigger_name.to_owned(),
-											i.to_owned()
-										)
-									));
-								}
-							}
-						} else {
-							for i in new_refs_data {
-								patch.push(PatchOperation::SubEntityOperation(
-									entity_id.to_owned(),
-									SubEntityOperation::AddOutputCopyConnection(
-										event_name.to_owned(),
-										trigger_name.to_owned(),
-										i.to_owned()
-									)
-								));
-							}
-						}
-					}
-				} else {
-					for (trigger_name, new_refs_data) in new_output_copying_data {
-						for i in new_refs_data {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::AddOutputCopyConnection(
-									event_name.to_owned(),
-									trigger_name.to_owned(),
-									i.to_owned()
-								)
-							));
-						}
-					}
+				SubEntityOperation::SetPlatformSpecificPropertyType(platform, name, _) => vec![
+					"platformSpecificProperties".into(),
+					platform.to_owned(),
+					name.to_owned(),
+					"type".into(),
+				],
+
+				SubEntityOperation::SetPlatformSpecificPropertyValue(SetPlatformSpecificPropertyValue {
+					platform,
+					property_name,
+					..
+				})
+				| SubEntityOperation::PatchPlatformSpecificArrayPropertyValue(platform, property_name, _)
+				| SubEntityOperation::PatchPlatformSpecificPropertyValue(platform, property_name, _) => vec![
+					"platformSpecificProperties".into(),
+					platform.to_owned(),
+					property_name.to_owned(),
+					"value".into(),
+				],
+
+				SubEntityOperation::SetPlatformSpecificPropertyPostInit(platform, name, _) => vec![
+					"platformSpecificProperties".into(),
+					platform.to_owned(),
+					name.to_owned(),
+					"postInit".into(),
+				],
+
+				SubEntityOperation::AddEventConnection(event, trigger, reference)
+				| SubEntityOperation::RemoveEventConnection(event, trigger, reference) => {
+					vec![
+						"events".into(),
+						event.to_owned(),
+						trigger.to_owned(),
+						to_string(reference)?,
+					]
+				}
+
+				SubEntityOperation::RemoveAllEventConnectionsForTrigger(event, trigger) => {
+					vec!["events".into(), event.to_owned(), trigger.to_owned()]
+				}
+
+				SubEntityOperation::RemoveAllEventConnectionsForEvent(event) => vec!["events".into(), event.to_owned()],
+
+				SubEntityOperation::AddInputCopyConnection(input, trigger, reference)
+				| SubEntityOperation::RemoveInputCopyConnection(input, trigger, reference) => vec![
+					"inputCopying".into(),
+					input.to_owned(),
+					trigger.to_owned(),
+					to_string(reference)?,
+				],
+
+				SubEntityOperation::RemoveAllInputCopyConnectionsForTrigger(input, trigger) => {
+					vec!["inputCopying".into(), input.to_owned(), trigger.to_owned()]
+				}
+
+				SubEntityOperation::RemoveAllInputCopyConnectionsForInput(input) => {
+					vec!["inputCopying".into(), input.to_owned()]
+				}
+
+				SubEntityOperation::AddOutputCopyConnection(event, propagate, reference)
+				| SubEntityOperation::RemoveOutputCopyConnection(event, propagate, reference) => vec![
+					"outputCopying".into(),
+					event.to_owned(),
+					propagate.to_owned(),
+					to_string(reference)?,
+				],
+
+				SubEntityOperation::RemoveAllOutputCopyConnectionsForPropagate(event, propagate) => {
+					vec!["outputCopying".into(), event.to_owned(), propagate.to_owned()]
+				}
+
+				SubEntityOperation::RemoveAllOutputCopyConnectionsForOutput(event) => {
+					vec!["outputCopying".into(), event.to_owned()]
+				}
+
+				SubEntityOperation::AddPropertyAliasConnection(alias, connection)
+				| SubEntityOperation::RemoveConnectionForPropertyAlias(alias, connection) => {
+					vec!["propertyAliases".into(), alias.to_owned(), to_string(connection)?]
 				}
+
+				SubEntityOperation::RemovePropertyAlias(alias) => vec!["propertyAliases".into(), alias.to_owned()],
+
+				SubEntityOperation::SetExposedEntity(name, _) | SubEntityOperation::RemoveExposedEntity(name) => {
+					vec!["exposedEntities".into(), name.to_owned()]
+				}
+
+				SubEntityOperation::SetExposedInterface(name, _) | SubEntityOperation::RemoveExposedInterface(name) => {
+					vec!["exposedInterfaces".into(), name.to_owned()]
+				}
+
+				SubEntityOperation::AddSubset(subset, entity) | SubEntityOperation::RemoveSubset(subset, entity) => {
+					vec!["subsets".into(), subset.to_owned(), normalise_entity_id(entity)?]
+				}
+
+				SubEntityOperation::RemoveAllSubsetsFor(subset) => vec!["subsets".into(), subset.to_owned()]
 			}
+		]
+		.concat(),
 
-			if old_entity_data.property_aliases.is_none() {
-				old_entity_data.property_aliases = Some(IndexMap::new());
+		PatchOperation::AddPropertyOverride(value) | PatchOperation::RemovePropertyOverride(value) => {
+			vec!["propertyOverrides".into(), to_string(value)?]
+		}
+
+		PatchOperation::AddPropertyOverrideConnection(value)
+		| PatchOperation::RemovePropertyOverrideConnection(value) => vec![
+			"propertyOverrides".into(),
+			to_string(&value.entity)?,
+			value.property_name.to_owned(),
+		],
+
+		PatchOperation::AddOverrideDelete(value) | PatchOperation::RemoveOverrideDelete(value) => {
+			vec!["overrideDeletes".into(), to_string(value)?]
+		}
+
+		PatchOperation::AddPinConnectionOverride(value) | PatchOperation::RemovePinConnectionOverride(value) => {
+			vec!["pinConnectionOverrides".into(), to_string(value)?]
+		}
+
+		PatchOperation::AddPinConnectionOverrideDelete(value)
+		| PatchOperation::RemovePinConnectionOverrideDelete(value) => {
+			vec!["pinConnectionOverrideDeletes".into(), to_string(value)?]
+		}
+
+		PatchOperation::AddExternalScene(value) | PatchOperation::RemoveExternalScene(value) => {
+			vec!["externalScenes".into(), value.to_owned()]
+		}
+
+		PatchOperation::AddExtraFactoryDependency(value) | PatchOperation::RemoveExtraFactoryDependency(value) => {
+			vec!["extraFactoryDependencies".into(), to_string(value)?]
+		}
+
+		PatchOperation::AddExtraBlueprintDependency(value) | PatchOperation::RemoveExtraBlueprintDependency(value) => {
+			vec!["extraBlueprintDependencies".into(), to_string(value)?]
+		}
+
+		PatchOperation::AddComment(value) | PatchOperation::RemoveComment(value) => {
+			vec!["comments".into(), to_string(value)?]
+		}
+	}
+}
+
+#[try_fn]
+#[context("Failure inverting patch")]
+#[auto_context]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn invert_patch(entity: &Entity, patch: &Patch) -> Result<Patch, QuickEntityError> {
+	let mut entity = entity.to_owned();
+	let mut inverse = vec![];
+
+	check_patch_version(patch.patch_version)?;
+
+	// Each operation is inverted against the state of the entity just before it's applied
+	for (index, operation) in patch.patch.iter().enumerate() {
+		let operation_failed = |source: Error| QuickEntityError::PatchOperationFailed {
+			index,
+			source: Box::new(source.into())
+		};
+
+		for operation in upgrade_patch_operation(patch.patch_version, operation.to_owned()) {
+			inverse.push(invert_patch_operation(&entity, &operation).map_err(operation_failed)?);
+			apply_patch_operation(&mut entity, operation, false, &mut vec![]).map_err(operation_failed)?;
+		}
+	}
+
+	Patch {
+		factory_hash: patch.factory_hash.to_owned(),
+		blueprint_hash: patch.blueprint_hash.to_owned(),
+		patch: inverse.into_iter().rev().flatten().collect(),
+		patch_version: CURRENT_PATCH_VERSION
+	}
+}
+
+/// Get the operations that undo a patch operation, given the entity it's about to be applied to.
+///
+/// If the operation couldn't be applied to the entity, no operations are returned; the failure is left to be reported when it's applied.
+#[try_fn]
+#[context("Failure inverting patch operation")]
+#[auto_context]
+fn invert_patch_operation(entity: &Entity, operation: &PatchOperation) -> Result<Vec<PatchOperation>> {
+	match operation {
+		PatchOperation::SetRootEntity(_) => vec![PatchOperation::SetRootEntity(entity.root_entity.to_owned())],
+
+		PatchOperation::SetSubType(_) => vec![PatchOperation::SetSubType(entity.sub_type.to_owned())],
+
+		PatchOperation::AddEntity(id, _) => match entity.entities.get(id) {
+			Some(old) => vec![PatchOperation::AddEntity(id.to_owned(), old.to_owned())],
+			None => vec![PatchOperation::RemoveEntityByID(id.to_owned())]
+		},
+
+		PatchOperation::RemoveEntityByID(id) => {
+			let id = normalise_entity_id(id)?;
+
+			match entity.entities.get(&id) {
+				Some(old) => vec![PatchOperation::AddEntity(id, old.to_owned())],
+				None => vec![]
 			}
+		}
 
-			if new_entity_data.property_aliases.is_none() {
-				new_entity_data.property_aliases = Some(IndexMap::new());
+		PatchOperation::SubEntityOperation(id, op) => match entity.entities.get(&normalise_entity_id(id)?) {
+			Some(sub_entity) => invert_sub_entity_operation(sub_entity, op)?
+				.into_iter()
+				.map(|x| PatchOperation::SubEntityOperation(id.to_owned(), x))
+				.collect(),
+
+			None => vec![]
+		},
+
+		PatchOperation::AddPropertyOverride(value) => vec![PatchOperation::RemovePropertyOverride(value.to_owned())],
+
+		PatchOperation::RemovePropertyOverride(value) => vec![PatchOperation::AddPropertyOverride(value.to_owned())],
+
+		PatchOperation::AddPropertyOverrideConnection(value) => {
+			let value = PropertyOverrideConnection {
+				entity: normalise_ref(&value.entity)?,
+				..value.to_owned()
+			};
+
+			// Adding a connection replaces any existing override of the same property on the same entity
+			match find_property_override_connection(entity, &value.entity, &value.property_name)? {
+				Some(old) if old == value => vec![],
+
+				Some(old) => vec![
+					PatchOperation::RemovePropertyOverrideConnection(value),
+					PatchOperation::AddPropertyOverrideConnection(old),
+				],
+
+				None => vec![PatchOperation::RemovePropertyOverrideConnection(value)]
 			}
+		}
 
-			for alias_name in old_entity_data.property_aliases.as_ref().ctx?.keys() {
-				if !new_entity_data.property_aliases.as_ref().ctx?.contains_key(alias_name) {
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemovePropertyAlias(alias_name.to_owned())
-					));
+		PatchOperation::RemovePropertyOverrideConnection(value) => {
+			let value = PropertyOverrideConnection {
+				entity: normalise_ref(&value.entity)?,
+				..value.to_owned()
+			};
+
+			match find_property_override_connection(entity, &value.entity, &value.property_name)? {
+				Some(old) if old == value => vec![PatchOperation::AddPropertyOverrideConnection(value)],
+				_ => vec![]
+			}
+		}
+
+		PatchOperation::AddOverrideDelete(value) => vec![PatchOperation::RemoveOverrideDelete(value.to_owned())],
+
+		PatchOperation::RemoveOverrideDelete(value) => vec![PatchOperation::AddOverrideDelete(normalise_ref(value)?)],
+
+		PatchOperation::AddPinConnectionOverride(value) => {
+			vec![PatchOperation::RemovePinConnectionOverride(value.to_owned())]
+		}
+
+		PatchOperation::RemovePinConnectionOverride(value) => {
+			vec![PatchOperation::AddPinConnectionOverride(PinConnectionOverride {
+				from_entity: normalise_ref(&value.from_entity)?,
+				to_entity: normalise_ref(&value.to_entity)?,
+				..value.to_owned()
+			})]
+		}
+
+		PatchOperation::AddPinConnectionOverrideDelete(value) => {
+			vec![PatchOperation::RemovePinConnectionOverrideDelete(value.to_owned())]
+		}
+
+		PatchOperation::RemovePinConnectionOverrideDelete(value) => {
+			vec![PatchOperation::AddPinConnectionOverrideDelete(
+				PinConnectionOverrideDelete {
+					from_entity: normalise_ref(&value.from_entity)?,
+					to_entity: normalise_ref(&value.to_entity)?,
+					..value.to_owned()
 				}
+			)]
+		}
+
+		PatchOperation::AddExternalScene(value) => vec![PatchOperation::RemoveExternalScene(value.to_owned())],
+
+		PatchOperation::RemoveExternalScene(value) => {
+			// The scene may have been removed by its hash rather than its path
+			match entity.external_scenes.iter().find(|x| hashes_match(value, x)) {
+				Some(old) => vec![PatchOperation::AddExternalScene(old.to_owned())],
+				None => vec![]
 			}
+		}
 
-			for (alias_name, new_alias_connections) in new_entity_data.property_aliases.as_ref().ctx? {
-				if let Some(old_alias_connections) = old_entity_data.property_aliases.as_ref().ctx?.get(alias_name) {
-					for connection in new_alias_connections {
-						if !old_alias_connections.contains(connection) {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::AddPropertyAliasConnection(
-									alias_name.to_owned(),
-									connection.to_owned()
-								)
-							));
-						}
-					}
+		PatchOperation::AddExtraFactoryDependency(value) => {
+			vec![PatchOperation::RemoveExtraFactoryDependency(value.to_owned())]
+		}
 
-					for connection in old_alias_connections {
-						if !new_alias_connections.contains(connection) {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::RemoveConnectionForPropertyAlias(
-									alias_name.to_owned(),
-									connection.to_owned()
-								)
-							));
-						}
-					}
-				} else {
-					for connection in new_alias_connections {
-						patch.push(PatchOperation::SubEntityOperation(
-							entity_id.to_owned(),
-							SubEntityOperation::AddPropertyAliasConnection(
-								alias_name.to_owned(),
-								connection.to_owned()
-							)
-						));
+		PatchOperation::RemoveExtraFactoryDependency(value) => {
+			vec![PatchOperation::AddExtraFactoryDependency(value.to_owned())]
+		}
+
+		PatchOperation::AddExtraBlueprintDependency(value) => {
+			vec![PatchOperation::RemoveExtraBlueprintDependency(value.to_owned())]
+		}
+
+		PatchOperation::RemoveExtraBlueprintDependency(value) => {
+			vec![PatchOperation::AddExtraBlueprintDependency(value.to_owned())]
+		}
+
+		PatchOperation::AddComment(value) => vec![PatchOperation::RemoveComment(value.to_owned())],
+
+		PatchOperation::RemoveComment(value) => vec![PatchOperation::AddComment(CommentEntity {
+			parent: normalise_ref(&value.parent)?,
+			..value.to_owned()
+		})]
+	}
+}
+
+#[try_fn]
+#[context("Failure inverting sub-entity operation")]
+#[auto_context]
+fn invert_sub_entity_operation(
+	sub_entity: &SubEntity,
+	operation: &SubEntityOperation
+) -> Result<Vec<SubEntityOperation>> {
+	let property = |name: &str| sub_entity.properties.as_ref().and_then(|x| x.get(name));
+
+	let platform_specific_property = |platform: &str, name: &str| {
+		sub_entity
+			.platform_specific_properties
+			.as_ref()
+			.and_then(|x| x.get(platform))
+			.and_then(|x| x.get(name))
+	};
+
+	match operation {
+		SubEntityOperation::SetParent(_) => vec![SubEntityOperation::SetParent(sub_entity.parent.to_owned())],
+		SubEntityOperation::SetName(_) => vec![SubEntityOperation::SetName(sub_entity.name.to_owned())],
+		SubEntityOperation::SetFactory(_) => vec![SubEntityOperation::SetFactory(sub_entity.factory.to_owned())],
+
+		SubEntityOperation::SetFactoryFlag(_) => {
+			vec![SubEntityOperation::SetFactoryFlag(sub_entity.factory_flag.to_owned())]
+		}
+
+		SubEntityOperation::SetBlueprint(_) => vec![SubEntityOperation::SetBlueprint(sub_entity.blueprint.to_owned())],
+
+		SubEntityOperation::SetEditorOnly(_) => vec![SubEntityOperation::SetEditorOnly(sub_entity.editor_only)],
+
+		SubEntityOperation::AddProperty(name, _) => match property(name) {
+			Some(old) => vec![SubEntityOperation::AddProperty(name.to_owned(), old.to_owned())],
+			None => vec![SubEntityOperation::RemovePropertyByName(name.to_owned())]
+		},
+
+		SubEntityOperation::RemovePropertyByName(name) => {
+			match sub_entity.properties.as_ref().and_then(|x| x.get_full(name)) {
+				Some((index, _, old)) => {
+					let mut inverse = vec![SubEntityOperation::AddProperty(name.to_owned(), old.to_owned())];
+
+					// Re-adding the property puts it at the end, so move the properties that came after it back behind it
+					for (name, property) in sub_entity.properties.as_ref().ctx?.iter().skip(index + 1) {
+						inverse.push(SubEntityOperation::RemovePropertyByName(name.to_owned()));
+						inverse.push(SubEntityOperation::AddProperty(name.to_owned(), property.to_owned()));
 					}
+
+					inverse
 				}
+
+				None => vec![]
 			}
+		}
+
+		SubEntityOperation::SetPropertyType(name, _) => property(name)
+			.map(|old| SubEntityOperation::SetPropertyType(name.to_owned(), old.property_type.to_owned()))
+			.into_iter()
+			.collect(),
+
+		SubEntityOperation::SetPropertyValue(SetPropertyValue { property_name, .. }) => property(property_name)
+			.map(|old| {
+				SubEntityOperation::SetPropertyValue(SetPropertyValue {
+					property_name: property_name.to_owned(),
+					value: old.value.to_owned()
+				})
+			})
+			.into_iter()
+			.collect(),
 
-			if old_entity_data.exposed_entities.is_none() {
-				old_entity_data.exposed_entities = Some(IndexMap::new());
-			}
+		SubEntityOperation::PatchArrayPropertyValue(property_name, array_patch) => match property(property_name) {
+			Some(old) => match invert_array_patch(
+				&old.value,
+				array_patch,
+				old.property_type == "TArray<SEntityTemplateReference>"
+			)? {
+				Some(inverse) => vec![SubEntityOperation::PatchArrayPropertyValue(
+					property_name.to_owned(),
+					inverse
+				)],
+
+				None => vec![SubEntityOperation::SetPropertyValue(SetPropertyValue {
+					property_name: property_name.to_owned(),
+					value: old.value.to_owned()
+				})]
+			},
 
-			if new_entity_data.exposed_entities.is_none() {
-				new_entity_data.exposed_entities = Some(IndexMap::new());
-			}
+			None => vec![]
+		},
 
-			for exposed_entity in old_entity_data.exposed_entities.as_ref().ctx?.keys() {
-				if !new_entity_data
-					.exposed_entities
-					.as_ref()
-					.ctx?
-					.contains_key(exposed_entity)
-				{
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemoveExposedEntity(exposed_entity.to_owned())
-					));
-				}
-			}
+		SubEntityOperation::PatchPropertyValue(property_name, value_patch) => match property(property_name) {
+			Some(old) => {
+				let mut patched = old.value.to_owned();
 
-			for (exposed_entity, data) in new_entity_data.exposed_entities.as_ref().ctx? {
-				if !old_entity_data
-					.exposed_entities
-					.as_ref()
-					.ctx?
-					.contains_key(exposed_entity)
-					|| old_entity_data.exposed_entities.as_ref().ctx?.get(exposed_entity).ctx? != data
-				{
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::SetExposedEntity(exposed_entity.to_owned(), data.to_owned())
-					));
+				if apply_value_patch(&mut patched, value_patch.to_owned(), false).is_ok() {
+					let mut inverse = vec![];
+					generate_value_patch(&patched, &old.value, "", &mut inverse);
+
+					vec![SubEntityOperation::PatchPropertyValue(
+						property_name.to_owned(),
+						inverse
+					)]
+				} else {
+					vec![]
 				}
 			}
 
-			if old_entity_data.exposed_interfaces.is_none() {
-				old_entity_data.exposed_interfaces = Some(IndexMap::new());
-			}
+			None => vec![]
+		},
 
-			if new_entity_data.exposed_interfaces.is_none() {
-				new_entity_data.exposed_interfaces = Some(IndexMap::new());
-			}
+		SubEntityOperation::SetPropertyPostInit(name, _) => property(name)
+			.map(|old| SubEntityOperation::SetPropertyPostInit(name.to_owned(), old.post_init.unwrap_or(false)))
+			.into_iter()
+			.collect(),
 
-			for exposed_interface in old_entity_data.exposed_interfaces.as_ref().ctx?.keys() {
-				if !new_entity_data
-					.exposed_interfaces
-					.as_ref()
-					.ctx?
-					.contains_key(exposed_interface)
-				{
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemoveExposedInterface(exposed_interface.to_owned())
-					));
-				}
+		SubEntityOperation::AddPlatformSpecificProperty(platform, name, _) => {
+			match platform_specific_property(platform, name) {
+				Some(old) => vec![SubEntityOperation::AddPlatformSpecificProperty(
+					platform.to_owned(),
+					name.to_owned(),
+					old.to_owned()
+				)],
+
+				None => vec![SubEntityOperation::RemovePlatformSpecificPropertyByName(
+					platform.to_owned(),
+					name.to_owned()
+				)]
 			}
+		}
 
-			for (exposed_interface, data) in new_entity_data.exposed_interfaces.as_ref().ctx? {
-				if !old_entity_data
-					.exposed_interfaces
-					.as_ref()
-					.ctx?
-					.contains_key(exposed_interface)
-					|| old_entity_data
-						.exposed_interfaces
-						.as_ref()
-						.ctx?
-						.get(exposed_interface)
-						.ctx? != data
-				{
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::SetExposedInterface(exposed_interface.to_owned(), data.to_owned())
-					));
-				}
-			}
+		SubEntityOperation::RemovePlatformSpecificPropertiesForPlatform(platform) => sub_entity
+			.platform_specific_properties
+			.as_ref()
+			.and_then(|x| x.get(platform))
+			.into_iter()
+			.flatten()
+			.map(|(name, old)| {
+				SubEntityOperation::AddPlatformSpecificProperty(platform.to_owned(), name.to_owned(), old.to_owned())
+			})
+			.collect(),
 
-			if old_entity_data.subsets.is_none() {
-				old_entity_data.subsets = Some(IndexMap::new());
-			}
+		SubEntityOperation::RemovePlatformSpecificPropertyByName(platform, name) => {
+			platform_specific_property(platform, name)
+				.map(|old| {
+					SubEntityOperation::AddPlatformSpecificProperty(
+						platform.to_owned(),
+						name.to_owned(),
+						old.to_owned()
+					)
+				})
+				.into_iter()
+				.collect()
+		}
 
-			if new_entity_data.subsets.is_none() {
-				new_entity_data.subsets = Some(IndexMap::new());
+		SubEntityOperation::SetPlatformSpecificPropertyType(platform, name, _) => {
+			platform_specific_property(platform, name)
+				.map(|old| {
+					SubEntityOperation::SetPlatformSpecificPropertyType(
+						platform.to_owned(),
+						name.to_owned(),
+						old.property_type.to_owned()
+					)
+				})
+				.into_iter()
+				.collect()
+		}
+
+		SubEntityOperation::SetPlatformSpecificPropertyValue(SetPlatformSpecificPropertyValue {
+			platform,
+			property_name,
+			..
+		}) => platform_specific_property(platform, property_name)
+			.map(|old| {
+				SubEntityOperation::SetPlatformSpecificPropertyValue(SetPlatformSpecificPropertyValue {
+					platform: platform.to_owned(),
+					property_name: property_name.to_owned(),
+					value: old.value.to_owned()
+				})
+			})
+			.into_iter()
+			.collect(),
+
+		SubEntityOperation::PatchPlatformSpecificArrayPropertyValue(platform, property_name, array_patch) => {
+			match platform_specific_property(platform, property_name) {
+				Some(old) => match invert_array_patch(
+					&old.value,
+					array_patch,
+					old.property_type == "TArray<SEntityTemplateReference>"
+				)? {
+					Some(inverse) => vec![SubEntityOperation::PatchPlatformSpecificArrayPropertyValue(
+						platform.to_owned(),
+						property_name.to_owned(),
+						inverse
+					)],
+
+					None => vec![SubEntityOperation::SetPlatformSpecificPropertyValue(
+						SetPlatformSpecificPropertyValue {
+							platform: platform.to_owned(),
+							property_name: property_name.to_owned(),
+							value: old.value.to_owned()
+						}
+					)]
+				},
+
+				None => vec![]
 			}
+		}
 
-			for subset_name in old_entity_data.subsets.as_ref().ctx?.keys() {
-				if !new_entity_data.subsets.as_ref().ctx?.contains_key(subset_name) {
-					patch.push(PatchOperation::SubEntityOperation(
-						entity_id.to_owned(),
-						SubEntityOperation::RemoveAllSubsetsFor(subset_name.to_owned())
-					));
+		SubEntityOperation::PatchPlatformSpecificPropertyValue(platform, property_name, value_patch) => {
+			match platform_specific_property(platform, property_name) {
+				Some(old) => {
+					let mut patched = old.value.to_owned();
+
+					if apply_value_patch(&mut patched, value_patch.to_owned(), false).is_ok() {
+						let mut inverse = vec![];
+						generate_value_patch(&patched, &old.value, "", &mut inverse);
+
+						vec![SubEntityOperation::PatchPlatformSpecificPropertyValue(
+							platform.to_owned(),
+							property_name.to_owned(),
+							inverse
+						)]
+					} else {
+						vec![]
+					}
 				}
+
+				None => vec![]
 			}
+		}
 
-			for (subset_name, new_refs_data) in new_entity_data.subsets.as_ref().ctx? {
-				if let Some(old_refs_data) = old_entity_data.subsets.as_ref().ctx?.get(subset_name) {
-					for i in old_refs_data {
-						if !new_refs_data.contains(i) {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::RemoveSubset(subset_name.to_owned(), i.to_owned())
+		SubEntityOperation::SetPlatformSpecificPropertyPostInit(platform, name, _) => {
+			platform_specific_property(platform, name)
+				.map(|old| {
+					SubEntityOperation::SetPlatformSpecificPropertyPostInit(
+						platform.to_owned(),
+						name.to_owned(),
+						old.post_init.unwrap_or(false)
+					)
+				})
+				.into_iter()
+				.collect()
+		}
+
+		SubEntityOperation::AddEventConnection(event, trigger, reference) => {
+			vec![SubEntityOperation::RemoveEventConnection(
+				event.to_owned(),
+				trigger.to_owned(),
+				reference.to_owned()
+			)]
+		}
+
+		SubEntityOperation::RemoveEventConnection(event, trigger, reference) => {
+			let reference = normalise_ref_maybe_constant_value(reference)?;
+
+			let position = sub_entity.events.as_ref().and_then(|events| {
+				let (event_index, _, triggers) = events.get_full(event)?;
+				let (trigger_index, _, references) = triggers.get_full(trigger)?;
+				let reference_index = references.iter().position(|x| *x == reference)?;
+
+				Some((
+					events,
+					event_index,
+					triggers,
+					trigger_index,
+					references,
+					reference_index
+				))
+			});
+
+			match position {
+				Some((events, event_index, triggers, trigger_index, references, reference_index))
+					// Re-adding the connection puts it at the end (of its trigger, and of its event and the events if
+					// removing it removed those), so anything after it has to be moved back behind it
+					if reference_index + 1 != references.len()
+						|| (references.len() == 1
+							&& (trigger_index + 1 != triggers.len()
+								|| (triggers.len() == 1 && event_index + 1 != events.len()))) =>
+				{
+					let mut inverse = vec![];
+
+					// The event itself is only still there if the connection wasn't its last one
+					let event_remains = triggers.values().map(|x| x.len()).sum::<usize>() > 1;
+
+					for (other_event, _) in events.iter().skip(event_index) {
+						if other_event != event || event_remains {
+							inverse.push(SubEntityOperation::RemoveAllEventConnectionsForEvent(
+								other_event.to_owned()
 							));
 						}
 					}
 
-					for i in new_refs_data {
-						if !old_refs_data.contains(i) {
-							patch.push(PatchOperation::SubEntityOperation(
-								entity_id.to_owned(),
-								SubEntityOperation::AddSubset(subset_name.to_owned(), i.to_owned())
-							));
+					for (other_event, triggers) in events.iter().skip(event_index) {
+						for (trigger, references) in triggers {
+							for reference in references {
+								inverse.push(SubEntityOperation::AddEventConnection(
+									other_event.to_owned(),
+									trigger.to_owned(),
+									reference.to_owned()
+								));
+							}
 						}
 					}
-				} else {
-					for i in new_refs_data {
-						patch.push(PatchOperation::SubEntityOperation(
-							entity_id.to_owned(),
-							SubEntityOperation::AddSubset(subset_name.to_owned(), i.to_owned())
-						));
-					}
+
+					inverse
 				}
+
+				_ => vec![SubEntityOperation::AddEventConnection(
+					event.to_owned(),
+					trigger.to_owned(),
+					reference
+				)]
 			}
-		} else {
-			patch.push(PatchOperation::AddEntity(
-				entity_id.to_owned(),
-				new_entity_data.to_owned()
-			));
 		}
-	}
 
-	let original_unravelled_overrides: Vec<PropertyOverrideConnection> = original
-		.property_overrides
-		.iter()
-		.flat_map(|property_override| {
-			property_override
-				.entities
-				.iter()
-				.flat_map(|ent| {
-					property_override
-						.properties
-						.iter()
-						.map(|(prop_name, prop_val)| PropertyOverrideConnection {
-							entity: ent.to_owned(),
-							property_name: prop_name.to_owned(),
-							property_override: prop_val.to_owned()
-						})
-						.collect_vec()
-				})
-				.collect_vec()
-		})
-		.collect();
+		SubEntityOperation::RemoveAllEventConnectionsForTrigger(event, trigger) => sub_entity
+			.events
+			.as_ref()
+			.and_then(|x| x.get(event))
+			.and_then(|x| x.get(trigger))
+			.into_iter()
+			.flatten()
+			.map(|x| SubEntityOperation::AddEventConnection(event.to_owned(), trigger.to_owned(), x.to_owned()))
+			.collect(),
 
-	let modified_unravelled_overrides: Vec<PropertyOverrideConnection> = modified
-		.property_overrides
-		.iter()
-		.flat_map(|property_override| {
-			property_override
-				.entities
-				.iter()
-				.flat_map(|ent| {
-					property_override
-						.properties
-						.iter()
-						.map(|(prop_name, prop_val)| PropertyOverrideConnection {
-							entity: ent.to_owned(),
-							property_name: prop_name.to_owned(),
-							property_override: prop_val.to_owned()
-						})
-						.collect_vec()
+		SubEntityOperation::RemoveAllEventConnectionsForEvent(event) => sub_entity
+			.events
+			.as_ref()
+			.and_then(|x| x.get(event))
+			.into_iter()
+			.flatten()
+			.flat_map(|(trigger, references)| {
+				references
+					.iter()
+					.map(|x| SubEntityOperation::AddEventConnection(event.to_owned(), trigger.to_owned(), x.to_owned()))
+			})
+			.collect(),
+
+		SubEntityOperation::AddInputCopyConnection(input, trigger, reference) => {
+			vec![SubEntityOperation::RemoveInputCopyConnection(
+				input.to_owned(),
+				trigger.to_owned(),
+				reference.to_owned()
+			)]
+		}
+
+		SubEntityOperation::RemoveInputCopyConnection(input, trigger, reference) => {
+			vec![SubEntityOperation::AddInputCopyConnection(
+				input.to_owned(),
+				trigger.to_owned(),
+				normalise_ref_maybe_constant_value(reference)?
+			)]
+		}
+
+		SubEntityOperation::RemoveAllInputCopyConnectionsForTrigger(input, trigger) => sub_entity
+			.input_copying
+			.as_ref()
+			.and_then(|x| x.get(input))
+			.and_then(|x| x.get(trigger))
+			.into_iter()
+			.flatten()
+			.map(|x| SubEntityOperation::AddInputCopyConnection(input.to_owned(), trigger.to_owned(), x.to_owned()))
+			.collect(),
+
+		SubEntityOperation::RemoveAllInputCopyConnectionsForInput(input) => sub_entity
+			.input_copying
+			.as_ref()
+			.and_then(|x| x.get(input))
+			.into_iter()
+			.flatten()
+			.flat_map(|(trigger, references)| {
+				references.iter().map(|x| {
+					SubEntityOperation::AddInputCopyConnection(input.to_owned(), trigger.to_owned(), x.to_owned())
 				})
-				.collect_vec()
-		})
-		.collect();
+			})
+			.collect(),
 
-	for x in &original_unravelled_overrides {
-		if modified_unravelled_overrides
-			.iter()
-			.try_find(|val| -> Result<bool> {
-				Ok(val.entity == x.entity
-					&& val.property_name == x.property_name
-					&& property_is_roughly_identical(&val.property_override, &x.property_override)?)
-			})?
-			.is_none()
-		{
-			patch.push(PatchOperation::RemovePropertyOverrideConnection(x.to_owned()))
+		SubEntityOperation::AddOutputCopyConnection(event, propagate, reference) => {
+			vec![SubEntityOperation::RemoveOutputCopyConnection(
+				event.to_owned(),
+				propagate.to_owned(),
+				reference.to_owned()
+			)]
 		}
-	}
 
-	for x in &modified_unravelled_overrides {
-		if original_unravelled_overrides
-			.iter()
-			.try_find(|val| -> Result<bool> {
-				Ok(val.entity == x.entity
-					&& val.property_name == x.property_name
-					&& property_is_roughly_identical(&val.property_override, &x.property_override)?)
-			})?
-			.is_none()
-		{
-			patch.push(PatchOperation::AddPropertyOverrideConnection(x.to_owned()))
+		SubEntityOperation::RemoveOutputCopyConnection(event, propagate, reference) => {
+			vec![SubEntityOperation::AddOutputCopyConnection(
+				event.to_owned(),
+				propagate.to_owned(),
+				normalise_ref_maybe_constant_value(reference)?
+			)]
 		}
-	}
 
-	for x in &original.override_deletes {
-		if !modified.override_deletes.contains(x) {
-			patch.push(PatchOperation::RemoveOverrideDelete(x.to_owned()))
+		SubEntityOperation::RemoveAllOutputCopyConnectionsForPropagate(event, propagate) => sub_entity
+			.output_copying
+			.as_ref()
+			.and_then(|x| x.get(event))
+			.and_then(|x| x.get(propagate))
+			.into_iter()
+			.flatten()
+			.map(|x| SubEntityOperation::AddOutputCopyConnection(event.to_owned(), propagate.to_owned(), x.to_owned()))
+			.collect(),
+
+		SubEntityOperation::RemoveAllOutputCopyConnectionsForOutput(event) => sub_entity
+			.output_copying
+			.as_ref()
+			.and_then(|x| x.get(event))
+			.into_iter()
+			.flatten()
+			.flat_map(|(propagate, references)| {
+				references.iter().map(|x| {
+					SubEntityOperation::AddOutputCopyConnection(event.to_owned(), propagate.to_owned(), x.to_owned())
+				})
+			})
+			.collect(),
+
+		SubEntityOperation::AddPropertyAliasConnection(alias, data) => {
+			vec![SubEntityOperation::RemoveConnectionForPropertyAlias(
+				alias.to_owned(),
+				data.to_owned()
+			)]
 		}
-	}
 
-	for x in &modified.override_deletes {
-		if !original.override_deletes.contains(x) {
-			patch.push(PatchOperation::AddOverrideDelete(x.to_owned()))
-		}
-	}
+		SubEntityOperation::RemovePropertyAlias(alias) => sub_entity
+			.property_aliases
+			.as_ref()
+			.and_then(|x| x.get(alias))
+			.into_iter()
+			.flatten()
+			.map(|x| SubEntityOperation::AddPropertyAliasConnection(alias.to_owned(), x.to_owned()))
+			.collect(),
 
-	for x in &original.pin_connection_overrides {
-		if !modified.pin_connection_overrides.contains(x) {
-			patch.push(PatchOperation::RemovePinConnectionOverride(x.to_owned()))
+		SubEntityOperation::RemoveConnectionForPropertyAlias(alias, data) => {
+			vec![SubEntityOperation::AddPropertyAliasConnection(
+				alias.to_owned(),
+				PropertyAlias {
+					original_property: data.original_property.to_owned(),
+					original_entity: normalise_ref(&data.original_entity)?
+				}
+			)]
 		}
-	}
 
-	for x in &modified.pin_connection_overrides {
-		if !original.pin_connection_overrides.contains(x) {
-			patch.push(PatchOperation::AddPinConnectionOverride(x.to_owned()))
+		SubEntityOperation::SetExposedEntity(name, _) => {
+			match sub_entity.exposed_entities.as_ref().and_then(|x| x.get(name)) {
+				Some(old) => vec![SubEntityOperation::SetExposedEntity(name.to_owned(), old.to_owned())],
+				None => vec![SubEntityOperation::RemoveExposedEntity(name.to_owned())]
+			}
 		}
-	}
 
-	for x in &original.pin_connection_override_deletes {
-		if !modified.pin_connection_override_deletes.contains(x) {
-			patch.push(PatchOperation::RemovePinConnectionOverrideDelete(x.to_owned()))
-		}
-	}
+		SubEntityOperation::RemoveExposedEntity(name) => sub_entity
+			.exposed_entities
+			.as_ref()
+			.and_then(|x| x.get(name))
+			.map(|old| SubEntityOperation::SetExposedEntity(name.to_owned(), old.to_owned()))
+			.into_iter()
+			.collect(),
 
-	for x in &modified.pin_connection_override_deletes {
-		if !original.pin_connection_override_deletes.contains(x) {
-			patch.push(PatchOperation::AddPinConnectionOverrideDelete(x.to_owned()))
+		SubEntityOperation::SetExposedInterface(name, _) => {
+			match sub_entity.exposed_interfaces.as_ref().and_then(|x| x.get(name)) {
+				Some(old) => vec![SubEntityOperation::SetExposedInterface(name.to_owned(), old.to_owned())],
+				None => vec![SubEntityOperation::RemoveExposedInterface(name.to_owned())]
+			}
 		}
-	}
 
-	for x in &original.external_scenes {
-		if !modified.external_scenes.contains(x) {
-			patch.push(PatchOperation::RemoveExternalScene(x.to_owned()))
-		}
-	}
+		SubEntityOperation::RemoveExposedInterface(name) => sub_entity
+			.exposed_interfaces
+			.as_ref()
+			.and_then(|x| x.get(name))
+			.map(|old| SubEntityOperation::SetExposedInterface(name.to_owned(), old.to_owned()))
+			.into_iter()
+			.collect(),
 
-	for x in &modified.external_scenes {
-		if !original.external_scenes.contains(x) {
-			patch.push(PatchOperation::AddExternalScene(x.to_owned()))
+		SubEntityOperation::AddSubset(name, ent) => {
+			vec![SubEntityOperation::RemoveSubset(name.to_owned(), ent.to_owned())]
 		}
-	}
 
-	for x in &original.extra_factory_dependencies {
-		if !modified.extra_factory_dependencies.contains(x) {
-			patch.push(PatchOperation::RemoveExtraFactoryDependency(x.to_owned()))
+		SubEntityOperation::RemoveSubset(name, ent) => {
+			vec![SubEntityOperation::AddSubset(
+				name.to_owned(),
+				normalise_entity_id(ent)?
+			)]
 		}
-	}
 
-	for x in &modified.extra_factory_dependencies {
-		if !original.extra_factory_dependencies.contains(x) {
-			patch.push(PatchOperation::AddExtraFactoryDependency(x.to_owned()))
-		}
+		SubEntityOperation::RemoveAllSubsetsFor(name) => sub_entity
+			.subsets
+			.as_ref()
+			.and_then(|x| x.get(name))
+			.into_iter()
+			.flatten()
+			.map(|x| SubEntityOperation::AddSubset(name.to_owned(), x.to_owned()))
+			.collect()
 	}
+}
 
-	for x in &original.extra_blueprint_dependencies {
-		if !modified.extra_blueprint_dependencies.contains(x) {
-			patch.push(PatchOperation::RemoveExtraBlueprintDependency(x.to_owned()))
+/// Get array patch operations that undo an array patch, or None if the array patch can't be undone by another array patch (for instance if it removed an item that appeared more than once).
+#[try_fn]
+#[context("Failure inverting array patch")]
+#[auto_context]
+fn invert_array_patch(
+	value: &Value,
+	patch: &[ArrayPatchOperation],
+	is_ref_array: bool
+) -> Result<Option<Vec<ArrayPatchOperation>>> {
+	let normalise = |x: &Value| {
+		if is_ref_array {
+			normalise_ref_value(x.to_owned())
+		} else {
+			Ok(x.to_owned())
 		}
+	};
+
+	// Applying an empty array patch normalises refs the same way the real patch will
+	let mut current = value.to_owned();
+	if apply_array_patch(&mut current, vec![], false, is_ref_array).is_err() {
+		return Ok(None);
 	}
 
-	for x in &modified.extra_blueprint_dependencies {
-		if !original.extra_blueprint_dependencies.contains(x) {
-			patch.push(PatchOperation::AddExtraBlueprintDependency(x.to_owned()))
+	let original = current.to_owned();
+	let mut inverse = vec![];
+
+	for op in patch {
+		let before = current.as_array().ctx?.to_owned();
+
+		inverse.push(match op {
+			ArrayPatchOperation::AddItem(new)
+			| ArrayPatchOperation::AddItemAfter(_, new)
+			| ArrayPatchOperation::AddItemBefore(_, new) => ArrayPatchOperation::RemoveItemByValue(normalise(new)?),
+
+			ArrayPatchOperation::RemoveItemByValue(val) => {
+				let val = normalise(val)?;
+
+				match before.iter().position(|x| *x == val) {
+					Some(pos) if pos > 0 => ArrayPatchOperation::AddItemAfter(before[pos - 1].to_owned(), val),
+					Some(pos) if pos + 1 < before.len() => {
+						ArrayPatchOperation::AddItemBefore(before[pos + 1].to_owned(), val)
+					}
+					Some(_) => ArrayPatchOperation::AddItem(val),
+					None => continue
+				}
+			}
+
+			ArrayPatchOperation::RemoveItemOccurrence(val, occurrence) => {
+				match find_occurrence(&before, &normalise(val)?, *occurrence) {
+					Some(pos) => ArrayPatchOperation::InsertItemAfterOccurrence(
+						pos.checked_sub(1).map(|x| occurrence_at(&before, x)),
+						before[pos].to_owned()
+					),
+
+					None => continue
+				}
+			}
+
+			ArrayPatchOperation::InsertItemAfterOccurrence(after, new) => {
+				let pos = match after {
+					Some((val, occurrence)) => match find_occurrence(&before, &normalise(val)?, *occurrence) {
+						Some(pos) => pos + 1,
+						None => continue
+					},
+
+					None => 0
+				};
+
+				let new = normalise(new)?;
+				let occurrence = before[..pos].iter().filter(|x| **x == new).count();
+
+				ArrayPatchOperation::RemoveItemOccurrence(new, occurrence)
+			}
+
+			ArrayPatchOperation::SetItemOccurrence(val, occurrence, new) => {
+				match find_occurrence(&before, &normalise(val)?, *occurrence) {
+					Some(pos) => {
+						let new = normalise(new)?;
+						let occurrence = before[..pos].iter().filter(|x| **x == new).count();
+
+						ArrayPatchOperation::SetItemOccurrence(new, occurrence, before[pos].to_owned())
+					}
+
+					None => continue
+				}
+			}
+		});
+
+		if apply_array_patch(&mut current, vec![op.to_owned()], false, is_ref_array).is_err() {
+			return Ok(None);
 		}
 	}
 
-	for x in &original.comments {
-		if !modified.comments.contains(x) {
-			patch.push(PatchOperation::RemoveComment(x.to_owned()))
-		}
+	inverse.reverse();
+
+	// The inverse is only usable if it actually restores the original array
+	let mut restored = current;
+	if apply_array_patch(&mut restored, inverse.to_owned(), false, is_ref_array).is_ok() && restored == original {
+		Some(inverse)
+	} else {
+		None
 	}
+}
 
-	for x in &modified.comments {
-		if !original.comments.contains(x) {
-			patch.push(PatchOperation::AddComment(x.to_owned()))
+/// Find the override of a property on an entity, as a single connection.
+#[try_fn]
+#[context("Failure finding property override connection")]
+#[auto_context]
+fn find_property_override_connection(
+	entity: &Entity,
+	reference: &Ref,
+	property_name: &str
+) -> Result<Option<PropertyOverrideConnection>> {
+	let mut found = None;
+
+	for property_override in &entity.property_overrides {
+		if let Some(property_override_value) = property_override.properties.get(property_name) {
+			for ent in &property_override.entities {
+				if normalise_ref(ent)? == *reference {
+					found = Some(PropertyOverrideConnection {
+						entity: reference.to_owned(),
+						property_name: property_name.to_owned(),
+						property_override: property_override_value.to_owned()
+					});
+				}
+			}
 		}
 	}
 
-	Patch {
-		factory_hash: modified.factory_hash,
-		blueprint_hash: modified.blueprint_hash,
-		patch,
-		patch_version: 6
+	found
+}
+
+#[try_fn]
+#[context("Failure normalising ref with possible constant value")]
+#[auto_context]
+fn normalise_ref_maybe_constant_value(reference: &RefMaybeConstantValue) -> Result<RefMaybeConstantValue> {
+	match reference {
+		RefMaybeConstantValue::Ref(x) => RefMaybeConstantValue::Ref(normalise_ref(x)?),
+
+		RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue { entity_ref, value }) => {
+			RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
+				entity_ref: normalise_ref(entity_ref)?,
+				value: value.to_owned()
+			})
+		}
 	}
 }
 
@@ -2439,7 +4584,9 @@ fn convert_qn_reference_to_rt(
 			external_scene_index: -1,
 			entity_index: entity_id_to_index_mapping
 				.get(&normalise_entity_id(ent)?)
-				.with_context(|| format!("Short ref referred to a nonexistent entity ID: {}", ent))?
+				.ok_or_else(|| QuickEntityError::EntityNotFound {
+					entity_id: ent.to_owned()
+				})?
 				.to_owned() as i32,
 			exposed_entity: "".to_string()
 		},
@@ -2456,11 +4603,14 @@ fn convert_qn_reference_to_rt(
 						.external_scene_type_indices_in_resource_header
 						.iter()
 						.try_position(|x| {
-							Ok(factory_meta
-								.hash_reference_data
-								.get(*x)
-								.context("TEMP referenced external scene not found in meta in externalScenes")?
-								.hash == *extscene)
+							Ok(hashes_match(
+								&factory_meta
+									.hash_reference_data
+									.get(*x)
+									.context("TEMP referenced external scene not found in meta in externalScenes")?
+									.hash,
+								extscene
+							))
 						})?
 						.context("TEMP referenced external scene not found in externalScenes in sub-entity")?
 						.try_into()
@@ -2470,7 +4620,9 @@ fn convert_qn_reference_to_rt(
 			entity_index: match &fullref.external_scene {
 				None => entity_id_to_index_mapping
 					.get(&normalise_entity_id(&fullref.entity_ref)?)
-					.with_context(|| format!("Full ref referred to a nonexistent entity ID: {}", fullref.entity_ref))?
+					.ok_or_else(|| QuickEntityError::EntityNotFound {
+						entity_id: fullref.entity_ref.to_owned()
+					})?
 					.to_owned() as i32,
 				Some(_) => -2
 			},
@@ -2487,7 +4639,7 @@ pub fn convert_rt_property_value_to_qn(
 	factory: &resourcelib::EntityFactory,
 	factory_meta: &RpkgResourceMeta,
 	blueprint: &resourcelib::EntityBlueprint,
-	convert_lossless: bool
+	options: &ConvertOptions
 ) -> Result<Value> {
 	match property.property_type.as_str() {
 		"SEntityTemplateReference" => to_value(convert_rt_reference_to_qn(
@@ -2529,94 +4681,82 @@ pub fn convert_rt_property_value_to_qn(
 			}
 		}
 
+		"SMatrix43" if options.transform_representation == TransformRepresentation::Matrix => {
+			from_value::<SMatrix43PropertyValue>(property.property_value.to_owned())
+				.context("SMatrix43 did not have a valid format")?;
+
+			property.property_value.to_owned()
+		}
+
+		"SMatrix43" if options.transform_representation == TransformRepresentation::Quaternion => {
+			let matrix = from_value::<SMatrix43PropertyValue>(property.property_value.to_owned())
+				.context("SMatrix43 did not have a valid format")?;
+
+			match decompose_matrix(&matrix) {
+				Some(([[m11, m12, m13], [m21, m22, m23], [m31, m32, m33]], scale)) => {
+					// this is also from three.js
+					let trace = m11 + m22 + m33;
+
+					let (quat_x, quat_y, quat_z, quat_w) = if trace > 0.0 {
+						let s = 0.5 / (trace + 1.0).sqrt();
+						((m32 - m23) * s, (m13 - m31) * s, (m21 - m12) * s, 0.25 / s)
+					} else if m11 > m22 && m11 > m33 {
+						let s = 2.0 * (1.0 + m11 - m22 - m33).sqrt();
+						(0.25 * s, (m12 + m21) / s, (m13 + m31) / s, (m32 - m23) / s)
+					} else if m22 > m33 {
+						let s = 2.0 * (1.0 + m22 - m11 - m33).sqrt();
+						((m12 + m21) / s, 0.25 * s, (m23 + m32) / s, (m13 - m31) / s)
+					} else {
+						let s = 2.0 * (1.0 + m33 - m11 - m22).sqrt();
+						((m13 + m31) / s, (m23 + m32) / s, 0.25 * s, (m21 - m12) / s)
+					};
+
+					if [quat_x, quat_y, quat_z, quat_w].iter().all(|x| x.is_finite()) {
+						let mut value = json!({
+							"quaternion": { "x": quat_x, "y": quat_y, "z": quat_z, "w": quat_w },
+							"position": { "x": matrix.Trans.x, "y": matrix.Trans.y, "z": matrix.Trans.z }
+						});
+
+						if scale_is_significant(scale, options.scale_precision) {
+							value["scale"] = json!({ "x": scale[0], "y": scale[1], "z": scale[2] });
+						}
+
+						value
+					} else {
+						property.property_value.to_owned()
+					}
+				}
+
+				// A zero scale has no rotation, so keep the raw matrix rather than writing NaN
+				None => property.property_value.to_owned()
+			}
+		}
+
 		"SMatrix43" => {
-			let mut matrix = from_value::<SMatrix43PropertyValue>(property.property_value.to_owned())
+			let matrix = from_value::<SMatrix43PropertyValue>(property.property_value.to_owned())
 				.context("SMatrix43 did not have a valid format")?;
 
-			// this is all from three.js
-
-			let n11 = matrix.XAxis.x;
-			let n12 = matrix.XAxis.y;
-			let n13 = matrix.XAxis.z;
-			let n14 = 0.0;
-			let n21 = matrix.YAxis.x;
-			let n22 = matrix.YAxis.y;
-			let n23 = matrix.YAxis.z;
-			let n24 = 0.0;
-			let n31 = matrix.ZAxis.x;
-			let n32 = matrix.ZAxis.y;
-			let n33 = matrix.ZAxis.z;
-			let n34 = 0.0;
-			let n41 = matrix.Trans.x;
-			let n42 = matrix.Trans.y;
-			let n43 = matrix.Trans.z;
-			let n44 = 1.0;
-
-			let det = n41
-				* (n14 * n23 * n32 - n13 * n24 * n32 - n14 * n22 * n33 + n12 * n24 * n33 + n13 * n22 * n34
-					- n12 * n23 * n34)
-				+ n42
-					* (n11 * n23 * n34 - n11 * n24 * n33 + n14 * n21 * n33 - n13 * n21 * n34 + n13 * n24 * n31
-						- n14 * n23 * n31)
-				+ n43
-					* (n11 * n24 * n32 - n11 * n22 * n34 - n14 * n21 * n32 + n12 * n21 * n34 + n14 * n22 * n31
-						- n12 * n24 * n31)
-				+ n44
-					* (-n13 * n22 * n31 - n11 * n23 * n32 + n11 * n22 * n33 + n13 * n21 * n32 - n12 * n21 * n33
-						+ n12 * n23 * n31);
-
-			let mut sx = n11 * n11 + n21 * n21 + n31 * n31;
-			let sy = n12 * n12 + n22 * n22 + n32 * n32;
-			let sz = n13 * n13 + n23 * n23 + n33 * n33;
-
-			if det < 0.0 {
-				sx = -sx
-			};
+			match decompose_matrix(&matrix) {
+				Some(([[m11, m12, m13], [_, m22, m23], [_, m32, m33]], scale)) => {
+					// this is all from three.js
+					let mut value = json!({
+						"rotation": {
+							"x": (if m13.abs() < 0.9999999 { (-m23).atan2(m33) } else { m32.atan2(m22) }) * RAD2DEG,
+							"y": m13.clamp(-1.0, 1.0).asin() * RAD2DEG,
+							"z": (if m13.abs() < 0.9999999 { (-m12).atan2(m11) } else { 0.0 }) * RAD2DEG
+						},
+						"position": { "x": matrix.Trans.x, "y": matrix.Trans.y, "z": matrix.Trans.z }
+					});
 
-			let pos = json!({ "x": n41, "y": n42, "z": n43 });
-			let scale = json!({ "x": sx, "y": sy, "z": sz });
-
-			let inv_sx = 1.0 / sx;
-			let inv_sy = 1.0 / sy;
-			let inv_sz = 1.0 / sz;
-
-			matrix.XAxis.x *= inv_sx;
-			matrix.YAxis.x *= inv_sx;
-			matrix.ZAxis.x *= inv_sx;
-			matrix.XAxis.y *= inv_sy;
-			matrix.YAxis.y *= inv_sy;
-			matrix.ZAxis.y *= inv_sy;
-			matrix.XAxis.z *= inv_sz;
-			matrix.YAxis.z *= inv_sz;
-			matrix.ZAxis.z *= inv_sz;
-
-			if if convert_lossless {
-				scale.get("x").ctx?.as_f64().ctx? != 1.0
-					|| scale.get("y").ctx?.as_f64().ctx? != 1.0
-					|| scale.get("z").ctx?.as_f64().ctx? != 1.0
-			} else {
-				format!("{:.2}", scale.get("x").ctx?.as_f64().ctx?) != "1.00"
-					|| format!("{:.2}", scale.get("y").ctx?.as_f64().ctx?) != "1.00"
-					|| format!("{:.2}", scale.get("z").ctx?.as_f64().ctx?) != "1.00"
-			} {
-				json!({
-					"rotation": {
-						"x": (if matrix.XAxis.z.abs() < 0.9999999 { (- matrix.YAxis.z).atan2(matrix.ZAxis.z) } else { (matrix.ZAxis.y).atan2(matrix.YAxis.y) }) * RAD2DEG,
-						"y": matrix.XAxis.z.clamp(-1.0, 1.0).asin() * RAD2DEG,
-						"z": (if matrix.XAxis.z.abs() < 0.9999999 { (- matrix.XAxis.y).atan2(matrix.XAxis.x) } else { 0.0 }) * RAD2DEG
-					},
-					"position": pos,
-					"scale": scale
-				})
-			} else {
-				json!({
-					"rotation": {
-						"x": (if matrix.XAxis.z.abs() < 0.9999999 { (- matrix.YAxis.z).atan2(matrix.ZAxis.z) } else { (matrix.ZAxis.y).atan2(matrix.YAxis.y) }) * RAD2DEG,
-						"y": matrix.XAxis.z.clamp(-1.0, 1.0).asin() * RAD2DEG,
-						"z": (if matrix.XAxis.z.abs() < 0.9999999 { (- matrix.XAxis.y).atan2(matrix.XAxis.x) } else { 0.0 }) * RAD2DEG
-					},
-					"position": pos
-				})
+					if scale_is_significant(scale, options.scale_precision) {
+						value["scale"] = json!({ "x": scale[0], "y": scale[1], "z": scale[2] });
+					}
+
+					value
+				}
+
+				// A zero scale has no rotation, so keep the raw matrix rather than writing NaN
+				None => property.property_value.to_owned()
 			}
 		}
 
@@ -2635,13 +4775,27 @@ pub fn convert_rt_property_value_to_qn(
 			let guid = from_value::<ZGuidPropertyValue>(property.property_value.to_owned())
 				.context("ZGuid did not have a valid format")?;
 
-			to_value(format!(
+			let guid = format!(
 				"{:0>8x}-{:0>4x}-{:0>4x}-{:0>2x}{:0>2x}-{:0>2x}{:0>2x}{:0>2x}{:0>2x}{:0>2x}{:0>2x}",
 				guid._a, guid._b, guid._c, guid._d, guid._e, guid._f, guid._g, guid._h, guid._i, guid._j, guid._k
-			))
+			);
+
+			to_value(match options.guid_case {
+				GuidCase::Lower => guid,
+				GuidCase::Upper => guid.to_uppercase()
+			})
 			.ctx?
 		}
 
+		"SColorRGB" | "SColorRGBA" if options.colour_representation == ColourRepresentation::Float => {
+			property
+				.property_value
+				.as_object()
+				.with_context(|| format!("{} was not an object", property.property_type))?;
+
+			property.property_value.to_owned()
+		}
+
 		"SColorRGB" => {
 			let map = property
 				.property_value
@@ -2705,6 +4859,70 @@ pub fn convert_rt_property_value_to_qn(
 	}
 }
 
+/// Split an SMatrix43's axes into a rotation matrix (as rows, in three.js's element order) and a scale, as three.js's `Matrix4.decompose` does; a mirrored matrix is given a negative X scale.
+///
+/// Returns none if any of the scales is zero, since the rotation can't be recovered.
+fn decompose_matrix(matrix: &SMatrix43PropertyValue) -> Option<([[f64; 3]; 3], [f64; 3])> {
+	let (n11, n12, n13) = (matrix.XAxis.x, matrix.XAxis.y, matrix.XAxis.z);
+	let (n21, n22, n23) = (matrix.YAxis.x, matrix.YAxis.y, matrix.YAxis.z);
+	let (n31, n32, n33) = (matrix.ZAxis.x, matrix.ZAxis.y, matrix.ZAxis.z);
+
+	let mut sx = (n11 * n11 + n21 * n21 + n31 * n31).sqrt();
+	let sy = (n12 * n12 + n22 * n22 + n32 * n32).sqrt();
+	let sz = (n13 * n13 + n23 * n23 + n33 * n33).sqrt();
+
+	if [sx, sy, sz].contains(&0.0) {
+		return None;
+	}
+
+	if n11 * (n22 * n33 - n23 * n32) - n12 * (n21 * n33 - n23 * n31) + n13 * (n21 * n32 - n22 * n31) < 0.0 {
+		sx = -sx;
+	}
+
+	Some((
+		[
+			[n11 / sx, n12 / sy, n13 / sz],
+			[n21 / sx, n22 / sy, n23 / sz],
+			[n31 / sx, n32 / sy, n33 / sz]
+		],
+		[sx, sy, sz]
+	))
+}
+
+/// Whether a scale differs enough from 1 to be included in QN, when rounded to the given precision.
+fn scale_is_significant(scale: [f64; 3], precision: Option<u32>) -> bool {
+	match precision {
+		Some(precision) => scale
+			.iter()
+			.any(|x| format!("{:.*}", precision as usize, x) != format!("{:.*}", precision as usize, 1.0)),
+
+		None => scale.iter().any(|&x| x != 1.0)
+	}
+}
+
+/// Get the QN name of a property, looking up its ID in the known property names if RT only stores the ID.
+fn convert_rt_property_id_to_qn(property_id: &resourcelib::PropertyID, options: &ConvertOptions) -> String {
+	match property_id {
+		resourcelib::PropertyID::Int(id) => u32::try_from(*id)
+			.ok()
+			.and_then(|id| options.property_names.as_ref()?.name(id))
+			.unwrap_or_else(|| id.to_string()),
+
+		resourcelib::PropertyID::String(id) => id.to_owned()
+	}
+}
+
+fn invalid_rt_property_value(source: anyhow::Error, property: &resourcelib::Property) -> QuickEntityError {
+	QuickEntityError::for_property(
+		source,
+		&match &property.n_property_id {
+			resourcelib::PropertyID::Int(id) => id.to_string(),
+			resourcelib::PropertyID::String(id) => id.to_owned()
+		},
+		&property.value.property_type
+	)
+}
+
 #[try_fn]
 #[context("Failure converting RT property to QN")]
 #[auto_context]
@@ -2714,7 +4932,7 @@ fn convert_rt_property_to_qn(
 	factory: &resourcelib::EntityFactory,
 	factory_meta: &RpkgResourceMeta,
 	blueprint: &resourcelib::EntityBlueprint,
-	convert_lossless: bool
+	options: &ConvertOptions
 ) -> Result<Property> {
 	Property {
 		property_type: property.value.property_type.to_owned(),
@@ -2739,14 +4957,16 @@ fn convert_rt_property_to_qn(
 							factory,
 							factory_meta,
 							blueprint,
-							convert_lossless
+							options
 						)
 					})
-					.collect::<Result<Vec<Value>>>()?
+					.collect::<Result<Vec<Value>>>()
+					.map_err(|source| invalid_rt_property_value(source, property))?
 			)
 			.ctx?
 		} else {
-			convert_rt_property_value_to_qn(&property.value, factory, factory_meta, blueprint, convert_lossless)?
+			convert_rt_property_value_to_qn(&property.value, factory, factory_meta, blueprint, options)
+				.map_err(|source| invalid_rt_property_value(source, property))?
 		},
 		post_init: if post_init { Some(true) } else { None }
 	}
@@ -2779,41 +4999,76 @@ pub fn convert_qn_property_value_to_rt(
 					"m_IDLow": 4294967295u32
 				})
 			} else if property.value.is_string() {
+				let resource = property.value.as_str().ctx?;
+
 				json!({
 					"m_IDHigh": 0, // I doubt we'll ever have that many dependencies
-					"m_IDLow": factory_dependencies_index_mapping.get(property.value.as_str().ctx?).ctx?
+					"m_IDLow": factory_dependencies_index_mapping.get(&normalise_hash(resource)).ok_or_else(|| QuickEntityError::NonexistentDependency { hash: resource.to_owned() })?
 				})
 			} else if property.value.is_object() {
+				let resource = property
+					.value
+					.get("resource")
+					.context("ZRuntimeResourceID didn't have resource despite being object")?
+					.as_str()
+					.context("ZRuntimeResourceID resource must be string")?;
+
 				json!({
 					"m_IDHigh": 0,
-					"m_IDLow": factory_dependencies_index_mapping.get(property.value.get("resource").context("ZRuntimeResourceID didn't have resource despite being object")?.as_str().context("ZRuntimeResourceID resource must be string")?).ctx?
+					"m_IDLow": factory_dependencies_index_mapping.get(&normalise_hash(resource)).ok_or_else(|| QuickEntityError::NonexistentDependency { hash: resource.to_owned() })?
 				})
 			} else {
 				bail!("ZRuntimeResourceID was not of a valid type")
 			}
 		}
 
+		// the raw matrix is passed through as it is
+		"SMatrix43" if property.value.get("XAxis").is_some() => {
+			to_value(
+				from_value::<SMatrix43PropertyValue>(property.value.to_owned())
+					.context("SMatrix43 matrix did not have a valid format")?
+			)
+			.ctx?
+		}
+
 		"SMatrix43" => {
 			// this is from three.js
 
 			let obj = property.value.as_object().context("SMatrix43 must be object")?;
 
-			let x = obj.get("rotation").ctx?.get("x").ctx?.as_f64().ctx? * DEG2RAD;
-			let y = obj.get("rotation").ctx?.get("y").ctx?.as_f64().ctx? * DEG2RAD;
-			let z = obj.get("rotation").ctx?.get("z").ctx?.as_f64().ctx? * DEG2RAD;
+			let (quat_x, quat_y, quat_z, quat_w) = if let Some(quaternion) = obj.get("quaternion") {
+				let quat_x = quaternion.get("x").ctx?.as_f64().ctx?;
+				let quat_y = quaternion.get("y").ctx?.as_f64().ctx?;
+				let quat_z = quaternion.get("z").ctx?.as_f64().ctx?;
+				let quat_w = quaternion.get("w").ctx?.as_f64().ctx?;
 
-			let c1 = (x / 2.0).cos();
-			let c2 = (y / 2.0).cos();
-			let c3 = (z / 2.0).cos();
+				let length = (quat_x * quat_x + quat_y * quat_y + quat_z * quat_z + quat_w * quat_w).sqrt();
 
-			let s1 = (x / 2.0).sin();
-			let s2 = (y / 2.0).sin();
-			let s3 = (z / 2.0).sin();
+				if length == 0.0 {
+					bail!("SMatrix43 quaternion must not be zero");
+				}
 
-			let quat_x = s1 * c2 * c3 + c1 * s2 * s3;
-			let quat_y = c1 * s2 * c3 - s1 * c2 * s3;
-			let quat_z = c1 * c2 * s3 + s1 * s2 * c3;
-			let quat_w = c1 * c2 * c3 - s1 * s2 * s3;
+				(quat_x / length, quat_y / length, quat_z / length, quat_w / length)
+			} else {
+				let x = obj.get("rotation").ctx?.get("x").ctx?.as_f64().ctx? * DEG2RAD;
+				let y = obj.get("rotation").ctx?.get("y").ctx?.as_f64().ctx? * DEG2RAD;
+				let z = obj.get("rotation").ctx?.get("z").ctx?.as_f64().ctx? * DEG2RAD;
+
+				let c1 = (x / 2.0).cos();
+				let c2 = (y / 2.0).cos();
+				let c3 = (z / 2.0).cos();
+
+				let s1 = (x / 2.0).sin();
+				let s2 = (y / 2.0).sin();
+				let s3 = (z / 2.0).sin();
+
+				(
+					s1 * c2 * c3 + c1 * s2 * s3,
+					c1 * s2 * c3 - s1 * c2 * s3,
+					c1 * c2 * s3 + s1 * s2 * c3,
+					c1 * c2 * c3 - s1 * s2 * s3
+				)
+			};
 
 			let x2 = quat_x + quat_x;
 			let y2 = quat_y + quat_y;
@@ -2896,6 +5151,8 @@ pub fn convert_qn_property_value_to_rt(
 			"_k": u8::from_str_radix(&property.value.as_str().ctx?.split('-').nth(4).ctx?.chars().skip(10).take(2).collect::<String>(), 16).ctx?
 		}),
 
+		"SColorRGB" | "SColorRGBA" if property.value.is_object() => property.value.to_owned(),
+
 		"SColorRGB" => json!({
 			"r": f64::from(u8::from_str_radix(&property.value.as_str().ctx?.chars().skip(1).take(2).collect::<String>(), 16).ctx?) / 255.0,
 			"g": f64::from(u8::from_str_radix(&property.value.as_str().ctx?.chars().skip(1).skip(2).take(2).collect::<String>(), 16).ctx?) / 255.0,
@@ -2952,7 +5209,10 @@ fn convert_qn_property_to_rt(
 								factory_dependencies_index_mapping
 							)
 						})
-						.collect::<Result<Vec<Value>>>()?
+						.collect::<Result<Vec<Value>>>()
+						.map_err(|source| {
+							QuickEntityError::for_property(source, property_name, &property_value.property_type)
+						})?
 				)
 				.ctx?
 			} else {
@@ -2962,7 +5222,10 @@ fn convert_qn_property_to_rt(
 					factory_meta,
 					entity_id_to_index_mapping,
 					factory_dependencies_index_mapping
-				)?
+				)
+				.map_err(|source| {
+					QuickEntityError::for_property(source, property_name, &property_value.property_type)
+				})?
 			}
 		}
 	}
@@ -2972,18 +5235,9 @@ fn convert_qn_property_to_rt(
 #[context("Failure converting string property name to RT id")]
 #[auto_context]
 fn convert_string_property_name_to_rt_id(property_name: &str) -> Result<resourcelib::PropertyID> {
-	if let Ok(i) = property_name.parse::<u64>() {
-		let is_crc_length = {
-			let x = format!("{:x}", i).chars().count();
-
-			x == 8 || x == 7
-		};
-
-		if is_crc_length {
-			resourcelib::PropertyID::Int(property_name.parse().ctx?)
-		} else {
-			resourcelib::PropertyID::String(property_name.to_owned())
-		}
+	// Numeric names are the IDs of properties whose names aren't known; other names are left for ResourceTool to hash
+	if let Ok(id) = property_name.parse::<u32>() {
+		resourcelib::PropertyID::Int(id.into())
 	} else {
 		resourcelib::PropertyID::String(property_name.to_owned())
 	}
@@ -3019,6 +5273,15 @@ fn get_factory_dependencies(entity: &Entity) -> Result<Vec<RpkgResourceReference
 				flag: sub_entity.factory_flag.to_owned().unwrap_or_else(|| "1F".to_string()) // this is slightly more efficient
 			})
 			.collect(),
+		// then the comment entity factory, if there are any comments
+		if entity.comments.is_empty() {
+			vec![]
+		} else {
+			vec![RpkgResourceReference {
+				hash: COMMENT_ENTITY_FACTORY.to_string(),
+				flag: "1F".to_string()
+			}]
+		},
 		// then sub-entity ZRuntimeResourceIDs
 		entity
 			.entities
@@ -3278,7 +5541,11 @@ fn get_factory_dependencies(entity: &Entity) -> Result<Vec<RpkgResourceReference
 	.into_iter()
 	.concat()
 	.into_iter()
-	.unique()
+	.map(|x| RpkgResourceReference {
+		hash: strip_annotation(&x.hash).to_owned(),
+		..x
+	})
+	.unique_by(|x| (normalise_hash(&x.hash), x.flag.to_owned()))
 	.collect()
 }
 
@@ -3300,11 +5567,23 @@ fn get_blueprint_dependencies(entity: &Entity) -> Vec<RpkgResourceReference> {
 				flag: "1F".to_string()
 			})
 			.collect(),
+		if entity.comments.is_empty() {
+			vec![]
+		} else {
+			vec![RpkgResourceReference {
+				hash: COMMENT_ENTITY_BLUEPRINT.to_string(),
+				flag: "1F".to_string()
+			}]
+		},
 	]
 	.into_iter()
 	.concat()
 	.into_iter()
-	.unique()
+	.map(|x| RpkgResourceReference {
+		hash: strip_annotation(&x.hash).to_owned(),
+		..x
+	})
+	.unique_by(|x| (normalise_hash(&x.hash), x.flag.to_owned()))
 	.collect()
 }
 
@@ -3318,9 +5597,9 @@ pub fn convert_to_qn(
 	factory_meta: &RpkgResourceMeta,
 	blueprint: &resourcelib::EntityBlueprint,
 	blueprint_meta: &RpkgResourceMeta,
-	convert_lossless: bool
-) -> Result<Entity> {
-	let pool = rayon::ThreadPoolBuilder::new().build()?;
+	options: &ConvertOptions
+) -> Result<Entity, QuickEntityError> {
+	let pool = rayon::ThreadPoolBuilder::new().build().ctx?;
 	pool.install(|| {
 		{
 			let mut unique = blueprint.sub_entities.to_owned();
@@ -3331,6 +5610,21 @@ pub fn convert_to_qn(
 			}
 		}
 
+		// comments are stored as editor-only sub-entities, so they need to be kept out of the entities map
+		let comment_indices: Vec<usize> = factory
+			.sub_entities
+			.iter()
+			.enumerate()
+			.filter(|(_, sub_entity_factory)| {
+				factory_meta
+					.hash_reference_data
+					.get(sub_entity_factory.entity_type_resource_index)
+					.map(|x| hashes_match(&x.hash, COMMENT_ENTITY_FACTORY))
+					.unwrap_or(false)
+			})
+			.map(|(index, _)| index)
+			.collect();
+
 		let mut entity = Entity {
 			factory_hash: factory_meta.hash_value.to_owned(),
 			blueprint_hash: blueprint_meta.hash_value.to_owned(),
@@ -3346,6 +5640,7 @@ pub fn convert_to_qn(
 				.sub_entities
 				.par_iter() // rayon automatically makes this run in parallel for s p e e d
 				.enumerate()
+				.filter(|(index, _)| !comment_indices.contains(index))
 				.map(|(index, sub_entity_factory)| -> Result<(String, SubEntity)> {
 					let sub_entity_blueprint = blueprint
 						.sub_entities
@@ -3389,17 +5684,14 @@ pub fn convert_to_qn(
 									.iter()
 									.map(|property| -> Result<_> {
 										Ok((
-											match &property.n_property_id {
-												resourcelib::PropertyID::Int(id) => id.to_string(),
-												resourcelib::PropertyID::String(id) => id.to_owned()
-											}, // key
+											convert_rt_property_id_to_qn(&property.n_property_id, options), // key
 											convert_rt_property_to_qn(
 												property,
 												false,
 												factory,
 												factory_meta,
 												blueprint,
-												convert_lossless
+												options
 											)? // value
 										))
 									})
@@ -3407,17 +5699,14 @@ pub fn convert_to_qn(
 										|property| -> Result<_> {
 											Ok((
 												// we do a little code duplication
-												match &property.n_property_id {
-													resourcelib::PropertyID::Int(id) => id.to_string(),
-													resourcelib::PropertyID::String(id) => id.to_owned()
-												},
+												convert_rt_property_id_to_qn(&property.n_property_id, options),
 												convert_rt_property_to_qn(
 													property,
 													true,
 													factory,
 													factory_meta,
 													blueprint,
-													convert_lossless
+													options
 												)?
 											))
 										}
@@ -3445,17 +5734,17 @@ pub fn convert_to_qn(
 												.map(|property| -> Result<(String, Property)> {
 													Ok((
 														// we do a little code duplication
-														match &property.property_value.n_property_id {
-															resourcelib::PropertyID::Int(id) => id.to_string(),
-															resourcelib::PropertyID::String(id) => id.to_owned()
-														},
+														convert_rt_property_id_to_qn(
+															&property.property_value.n_property_id,
+															options
+														),
 														convert_rt_property_to_qn(
 															&property.property_value,
 															property.post_init.to_owned(),
 															factory,
 															factory_meta,
 															blueprint,
-															convert_lossless
+															options
 														)?
 													))
 												})
@@ -3631,35 +5920,52 @@ pub fn convert_to_qn(
 				0 => SubType::Template,
 				_ => bail!("Invalid subtype {}", blueprint.sub_type)
 			},
-			quick_entity_version: 3.1,
+			quick_entity_version: CURRENT_QUICKENTITY_VERSION,
 			extra_factory_dependencies: vec![],
 			extra_blueprint_dependencies: vec![],
-			comments: vec![]
+			comments: comment_indices
+				.iter()
+				.map(|index| -> Result<_> {
+					let sub_entity_factory = factory.sub_entities.get(*index).ctx?;
+
+					Ok(CommentEntity {
+						parent: convert_rt_reference_to_qn(
+							&sub_entity_factory.logical_parent,
+							factory,
+							blueprint,
+							factory_meta
+						)?,
+						name: blueprint
+							.sub_entities
+							.get(*index)
+							.context("Comment entity had no equivalent by index in blueprint")?
+							.entity_name
+							.to_owned(),
+						text: sub_entity_factory
+							.property_values
+							.iter()
+							.find(
+								|x| matches!(&x.n_property_id, resourcelib::PropertyID::String(id) if id == "m_sText")
+							)
+							.and_then(|x| x.value.property_value.as_str())
+							.unwrap_or_default()
+							.to_owned()
+					})
+				})
+				.collect::<Result<_>>()?
 		};
 
 		{
-			let depends = get_factory_dependencies(&entity)?;
+			let depends: HashSet<_> = get_factory_dependencies(&entity)?
+				.into_iter()
+				.map(|x| (normalise_hash(&x.hash), x.flag))
+				.collect();
 
 			entity.extra_factory_dependencies = factory_meta
 				.hash_reference_data
 				.iter()
-				.filter(|x| {
-					if x.hash.contains(':') {
-						!depends.contains(&RpkgResourceReference {
-							hash: format!(
-								"00{}",
-								format!("{:X}", md5::compute(&x.hash))
-									.chars()
-									.skip(2)
-									.take(14)
-									.collect::<String>()
-							),
-							flag: x.flag.to_owned()
-						}) && !depends.contains(x)
-					} else {
-						!depends.contains(x)
-					}
-				})
+				.filter(|x| !depends.contains(&(normalise_hash(&x.hash), x.flag.to_owned())))
+				.filter(|x| entity.comments.is_empty() || !hashes_match(&x.hash, COMMENT_ENTITY_FACTORY))
 				.map(|x| match x {
 					RpkgResourceReference { hash, flag } if flag == "1F" => Dependency::Short(hash.to_owned()),
 					RpkgResourceReference { hash, flag } => Dependency::Full(DependencyWithFlag {
@@ -3671,28 +5977,16 @@ pub fn convert_to_qn(
 		}
 
 		{
-			let depends = get_blueprint_dependencies(&entity);
+			let depends: HashSet<_> = get_blueprint_dependencies(&entity)
+				.into_iter()
+				.map(|x| (normalise_hash(&x.hash), x.flag))
+				.collect();
 
 			entity.extra_blueprint_dependencies = blueprint_meta
 				.hash_reference_data
 				.iter()
-				.filter(|x| {
-					if x.hash.contains(':') {
-						!depends.contains(&RpkgResourceReference {
-							hash: format!(
-								"00{}",
-								format!("{:X}", md5::compute(&x.hash))
-									.chars()
-									.skip(2)
-									.take(14)
-									.collect::<String>()
-							),
-							flag: x.flag.to_owned()
-						}) && !depends.contains(x)
-					} else {
-						!depends.contains(x)
-					}
-				})
+				.filter(|x| !depends.contains(&(normalise_hash(&x.hash), x.flag.to_owned())))
+				.filter(|x| entity.comments.is_empty() || !hashes_match(&x.hash, COMMENT_ENTITY_BLUEPRINT))
 				.map(|x| match x {
 					RpkgResourceReference { hash, flag } if flag == "1F" => Dependency::Short(hash.to_owned()),
 					RpkgResourceReference { hash, flag } => Dependency::Full(DependencyWithFlag {
@@ -3953,10 +6247,7 @@ pub fn convert_to_qn(
 			)?];
 
 			let props = [(
-				match &property_override.property_value.n_property_id {
-					resourcelib::PropertyID::Int(id) => id.to_string(),
-					resourcelib::PropertyID::String(id) => id.to_owned()
-				},
+				convert_rt_property_id_to_qn(&property_override.property_value.n_property_id, options),
 				{
 					let prop = convert_rt_property_to_qn(
 						&property_override.property_value,
@@ -3964,7 +6255,7 @@ pub fn convert_to_qn(
 						factory,
 						factory_meta,
 						blueprint,
-						convert_lossless
+						options
 					)?;
 
 					OverriddenProperty {
@@ -3977,7 +6268,10 @@ pub fn convert_to_qn(
 			.collect();
 
 			// if same entity being overridden, merge props
-			if let Some(found) = pass1.iter_mut().find(|x| x.entities == ents) {
+			if let Some(found) = pass1
+				.iter_mut()
+				.find(|x| options.group_property_overrides && x.entities == ents)
+			{
 				found.properties.extend(props);
 			} else {
 				pass1.push(PropertyOverride {
@@ -3992,7 +6286,7 @@ pub fn convert_to_qn(
 			if let Some(found) = entity
 				.property_overrides
 				.iter_mut()
-				.find(|x| x.properties == property_override.properties)
+				.find(|x| options.group_property_overrides && x.properties == property_override.properties)
 			{
 				found.entities.extend(property_override.entities);
 			} else {
@@ -4000,6 +6294,14 @@ pub fn convert_to_qn(
 			}
 		}
 
+		if options.sort_entities {
+			entity.entities.sort_keys();
+		}
+
+		if let Some(hash_list) = &options.hash_list {
+			hash::apply_hash_list(&mut entity, hash_list, options.hash_list_mode);
+		}
+
 		Ok(entity)
 	})?
 }
@@ -4010,14 +6312,28 @@ pub fn convert_to_qn(
 #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
 #[cfg_attr(feature = "rune", rune::function(keep))]
 pub fn convert_to_rt(
-	entity: &Entity
-) -> Result<(
-	resourcelib::EntityFactory,
-	RpkgResourceMeta,
-	resourcelib::EntityBlueprint,
-	RpkgResourceMeta
-)> {
-	let pool = rayon::ThreadPoolBuilder::new().build()?;
+	entity: &Entity,
+	options: &ConvertOptions
+) -> Result<
+	(
+		resourcelib::EntityFactory,
+		RpkgResourceMeta,
+		resourcelib::EntityBlueprint,
+		RpkgResourceMeta
+	),
+	QuickEntityError
+> {
+	let entity = if options.sort_entities {
+		let mut entity = entity.to_owned();
+		entity.entities.sort_keys();
+		Cow::Owned(entity)
+	} else {
+		Cow::Borrowed(entity)
+	};
+
+	let entity = entity.as_ref();
+
+	let pool = rayon::ThreadPoolBuilder::new().build().ctx?;
 	pool.install(|| {
 		let entity_id_to_index_mapping: HashMap<String, usize> = entity
 			.entities
@@ -4035,42 +6351,37 @@ pub fn convert_to_rt(
 			blueprint_index_in_resource_header: 0,
 			root_entity_index: *entity_id_to_index_mapping
 				.get(&normalise_entity_id(&entity.root_entity)?)
-				.context("Root entity was non-existent")?,
+				.ok_or_else(|| QuickEntityError::EntityNotFound {
+					entity_id: entity.root_entity.to_owned()
+				})?,
 			sub_entities: vec![],
 			property_overrides: vec![],
 			external_scene_type_indices_in_resource_header: (1..entity.external_scenes.len() + 1).collect()
 		};
 
-		let factory_meta = RpkgResourceMeta {
-			hash_offset: 1367, // none of this data actually matters except for dependencies and resource type
-			hash_reference_data: [
+		let factory_meta = meta::new_meta(
+			strip_annotation(&entity.factory_hash),
+			"TEMP",
+			[
 				get_factory_dependencies(entity)?,
 				entity
 					.extra_factory_dependencies
 					.iter()
 					.map(|x| match x {
 						Dependency::Short(hash) => RpkgResourceReference {
-							hash: hash.to_owned(),
+							hash: strip_annotation(hash).to_owned(),
 							flag: "1F".to_string()
 						},
 						Dependency::Full(DependencyWithFlag { resource, flag }) => RpkgResourceReference {
-							hash: resource.to_owned(),
+							hash: strip_annotation(resource).to_owned(),
 							flag: flag.to_owned()
 						}
 					})
 					.collect()
 			]
 			.concat(),
-			hash_reference_table_dummy: 0,
-			hash_reference_table_size: 193,
-			hash_resource_type: "TEMP".to_string(),
-			hash_size: 2147484657,
-			hash_size_final: 2377,
-			hash_size_in_memory: 1525,
-			hash_size_in_video_memory: 4294967295,
-			hash_value: entity.factory_hash.to_owned(),
-			hash_path: None
-		};
+			None
+		)?;
 
 		let mut blueprint = resourcelib::EntityBlueprint {
 			sub_type: match entity.sub_type {
@@ -4080,7 +6391,9 @@ pub fn convert_to_rt(
 			},
 			root_entity_index: *entity_id_to_index_mapping
 				.get(&normalise_entity_id(&entity.root_entity)?)
-				.context("Root entity was non-existent")?,
+				.ok_or_else(|| QuickEntityError::EntityNotFound {
+					entity_id: entity.root_entity.to_owned()
+				})?,
 			sub_entities: vec![],
 			pin_connections: vec![],
 			input_pin_forwardings: vec![],
@@ -4254,51 +6567,62 @@ pub fn convert_to_rt(
 			external_scene_type_indices_in_resource_header: (0..entity.external_scenes.len()).collect()
 		};
 
-		let blueprint_meta = RpkgResourceMeta {
-			hash_offset: 1367,
-			hash_reference_data: [
+		let blueprint_meta = meta::new_meta(
+			strip_annotation(&entity.blueprint_hash),
+			"TBLU",
+			[
 				get_blueprint_dependencies(entity),
 				entity
 					.extra_blueprint_dependencies
 					.iter()
 					.map(|x| match x {
 						Dependency::Short(hash) => RpkgResourceReference {
-							hash: hash.to_owned(),
+							hash: strip_annotation(hash).to_owned(),
 							flag: "1F".to_string()
 						},
 						Dependency::Full(DependencyWithFlag { resource, flag }) => RpkgResourceReference {
-							hash: resource.to_owned(),
+							hash: strip_annotation(resource).to_owned(),
 							flag: flag.to_owned()
 						}
 					})
 					.collect()
 			]
 			.concat(),
-			hash_reference_table_dummy: 0,
-			hash_reference_table_size: 193,
-			hash_resource_type: "TBLU".to_string(),
-			hash_size: 2147484657,
-			hash_size_final: 2377,
-			hash_size_in_memory: 1525,
-			hash_size_in_video_memory: 4294967295,
-			hash_value: entity.blueprint_hash.to_owned(),
-			hash_path: None
-		};
+			None
+		)?;
 
 		let factory_dependencies_index_mapping: HashMap<String, usize> = factory_meta
 			.hash_reference_data
 			.par_iter()
 			.enumerate()
-			.map(|(x, y)| (y.hash.to_owned(), x.to_owned()))
+			.map(|(x, y)| (normalise_hash(&y.hash), x.to_owned()))
 			.collect();
 
 		let blueprint_dependencies_index_mapping: HashMap<String, usize> = blueprint_meta
 			.hash_reference_data
 			.par_iter()
 			.enumerate()
-			.map(|(x, y)| (y.hash.to_owned(), x.to_owned()))
+			.map(|(x, y)| (normalise_hash(&y.hash), x.to_owned()))
 			.collect();
 
+		// comments are stored as editor-only sub-entities placed after all of the actual sub-entities
+		let comment_entity_ids: Vec<u64> = {
+			let mut ids = vec![];
+
+			for (index, comment) in entity.comments.iter().enumerate() {
+				let digest = md5::compute(format!("{}:{}:{}", index, comment.name, comment.text));
+				let mut id = u64::from_le_bytes(digest.0[..8].try_into().ctx?);
+
+				while entity_id_to_index_mapping.contains_key(&format!("{:0>16x}", id)) || ids.contains(&id) {
+					id = id.wrapping_add(1);
+				}
+
+				ids.push(id);
+			}
+
+			ids
+		};
+
 		factory.property_overrides = entity
 			.property_overrides
 			.par_iter()
@@ -4362,7 +6686,11 @@ pub fn convert_to_rt(
 						&factory_meta,
 						&entity_id_to_index_mapping
 					)?,
-					entity_type_resource_index: *factory_dependencies_index_mapping.get(&sub_entity.factory).ctx?,
+					entity_type_resource_index: *factory_dependencies_index_mapping
+						.get(&normalise_hash(&sub_entity.factory))
+						.ok_or_else(|| QuickEntityError::NonexistentDependency {
+							hash: sub_entity.factory.to_owned()
+						})?,
 					property_values: if let Some(props) = sub_entity.properties.to_owned() {
 						props
 							.iter()
@@ -4444,7 +6772,11 @@ pub fn convert_to_rt(
 						&factory_meta,
 						&entity_id_to_index_mapping
 					)?,
-					entity_type_resource_index: *blueprint_dependencies_index_mapping.get(&sub_entity.blueprint).ctx?,
+					entity_type_resource_index: *blueprint_dependencies_index_mapping
+						.get(&normalise_hash(&sub_entity.blueprint))
+						.ok_or_else(|| QuickEntityError::NonexistentDependency {
+							hash: sub_entity.blueprint.to_owned()
+						})?,
 					entity_id: u64::from_str_radix(entity_id, 16).context("entity_id must be valid hex")?,
 					editor_only: sub_entity.editor_only.unwrap_or(false),
 					entity_name: sub_entity.name.to_owned(),
@@ -4539,7 +6871,9 @@ pub fn convert_to_rt(
 									interface.to_owned(),
 									entity_id_to_index_mapping
 										.get(&normalise_entity_id(implementor)?)
-										.context("Exposed interface referenced nonexistent local entity")?
+										.ok_or_else(|| QuickEntityError::EntityNotFound {
+											entity_id: implementor.to_owned()
+										})?
 										.to_owned()
 								))
 							})
@@ -4552,19 +6886,64 @@ pub fn convert_to_rt(
 			})
 			.collect::<Result<_>>()?;
 
+		for comment in &entity.comments {
+			factory.sub_entities.push(resourcelib::FactorySubEntity {
+				logical_parent: convert_qn_reference_to_rt(
+					&comment.parent,
+					&factory,
+					&factory_meta,
+					&entity_id_to_index_mapping
+				)?,
+				entity_type_resource_index: *factory_dependencies_index_mapping
+					.get(&normalise_hash(COMMENT_ENTITY_FACTORY))
+					.ctx?,
+				property_values: vec![resourcelib::Property {
+					n_property_id: resourcelib::PropertyID::String("m_sText".to_string()),
+					value: resourcelib::PropertyValue {
+						property_type: "ZString".to_string(),
+						property_value: to_value(&comment.text).ctx?
+					}
+				}],
+				post_init_property_values: vec![],
+				platform_specific_property_values: vec![]
+			});
+		}
+
+		for (comment, comment_entity_id) in entity.comments.iter().zip(comment_entity_ids) {
+			blueprint.sub_entities.push(resourcelib::BlueprintSubEntity {
+				logical_parent: convert_qn_reference_to_rt(
+					&comment.parent,
+					&factory,
+					&factory_meta,
+					&entity_id_to_index_mapping
+				)?,
+				entity_type_resource_index: *blueprint_dependencies_index_mapping
+					.get(&normalise_hash(COMMENT_ENTITY_BLUEPRINT))
+					.ctx?,
+				entity_id: comment_entity_id,
+				editor_only: true,
+				entity_name: comment.name.to_owned(),
+				property_aliases: vec![],
+				exposed_entities: vec![],
+				exposed_interfaces: vec![],
+				entity_subsets: vec![]
+			});
+		}
+
 		for (entity_index, (_, sub_entity)) in entity.entities.iter().enumerate() {
 			if sub_entity.subsets.is_some() {
 				for (subset, ents) in sub_entity.subsets.as_ref().ctx?.iter() {
 					for ent in ents.iter() {
-						let ent_subs = &mut blueprint
-							.sub_entities
-							.get_mut(
-								*entity_id_to_index_mapping
-									.get(&normalise_entity_id(ent)?)
-									.context("Entity subset referenced nonexistent local entity")?
-							)
-							.ctx?
-							.entity_subsets;
+						let ent_subs =
+							&mut blueprint
+								.sub_entities
+								.get_mut(*entity_id_to_index_mapping.get(&normalise_entity_id(ent)?).ok_or_else(
+									|| QuickEntityError::EntityNotFound {
+										entity_id: ent.to_owned()
+									}
+								)?)
+								.ctx?
+								.entity_subsets;
 
 						if let Some((_, subset_entities)) = ent_subs.iter_mut().find(|(s, _)| s == subset) {
 							subset_entities.entities.push(entity_index);
@@ -4666,6 +7045,21 @@ pub fn convert_to_rt(
 			.flatten()
 			.collect();
 
+		// The metas' sizes are those of the binary resources, which can't be written when they have properties of types such as enums; the sizes are left as zero then
+		let factory_meta = meta::new_meta(
+			&factory_meta.hash_value,
+			&factory_meta.hash_resource_type,
+			factory_meta.hash_reference_data,
+			binary::write_binary_factory(&factory).ok().as_deref()
+		)?;
+
+		let blueprint_meta = meta::new_meta(
+			&blueprint_meta.hash_value,
+			&blueprint_meta.hash_resource_type,
+			blueprint_meta.hash_reference_data,
+			binary::write_binary_blueprint(&blueprint).ok().as_deref()
+		)?;
+
 		Ok::<_, Error>((factory, factory_meta, blueprint, blueprint_meta))
 	})?
 }
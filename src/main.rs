@@ -4,15 +4,28 @@ use clap::{Parser, Subcommand};
 use std::fs;
 use tryvial::try_fn;
 
-use quickentity_rs::{apply_patch, convert_to_qn, convert_to_rt, generate_patch};
-
-use anyhow::Result;
+use quickentity_rs::hash::HashListMode;
+use quickentity_rs::migration::{upgrade_entity, upgrade_patch};
+use quickentity_rs::patch_structs::{
+	DiffAlgorithm, FloatTolerance, MergeResult, PatchGenOptions, PatchOperationStatus
+};
+use quickentity_rs::project::{explode_entity, implode_entity};
+use quickentity_rs::qn_structs::{ColourRepresentation, ConvertOptions, GuidCase, TransformRepresentation};
+use quickentity_rs::schema::{generate_schema, SchemaKind};
+use quickentity_rs::typescript::generate_typescript;
+use quickentity_rs::{
+	apply_patch, apply_patch_with_report, compose_patches, convert_to_qn, convert_to_rt, generate_patch_with,
+	invert_patch, merge_patches
+};
+
+use anyhow::{bail, Result};
 use serde_json::from_slice;
 
 use io_utils::*;
+use quickentity_rs::io::to_vec_float_format;
 
 #[derive(Parser)]
-#[command(author = "Atampy26", version, about = "A tool for parsing ResourceTool/RPKG entity JSON files into a more readable format and back again.", long_about = None)]
+#[command(author = "Atampy26", version, about = "A tool for parsing ResourceTool/RPKG entity JSON files into a more readable format and back again.", long_about = None, after_help = "QuickEntity entities and patches can be read and written as YAML or RON instead of JSON by using paths ending in .yaml, .yml or .ron.")]
 struct Args {
 	#[command(subcommand)]
 	command: Command
@@ -34,19 +47,19 @@ enum Command {
 
 	/// Convert RT source files, apply a series of QuickEntity patch JSONs and generate RT source files for the result.
 	ConvertPatchGenerate {
-		/// Input factory (TEMP) JSON path.
+		/// Input factory (TEMP) path (ResourceTool JSON, or binary if it ends in .temp).
 		#[arg(short = 'i', long)]
 		input_factory: String,
 
-		/// Input factory (TEMP) meta JSON path.
+		/// Input factory (TEMP) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'j', long)]
 		input_factory_meta: String,
 
-		/// Input blueprint (TBLU) JSON path.
+		/// Input blueprint (TBLU) path (ResourceTool JSON, or binary if it ends in .tblu).
 		#[arg(short = 'k', long)]
 		input_blueprint: String,
 
-		/// Input blueprint (TBLU) meta JSON path.
+		/// Input blueprint (TBLU) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'l', long)]
 		input_blueprint_meta: String,
 
@@ -54,25 +67,24 @@ enum Command {
 		#[arg(num_args = 1..)]
 		patches: Vec<String>,
 
-		/// Output factory (TEMP) JSON path.
+		/// Output factory (TEMP) path (ResourceTool JSON, or binary if it ends in .temp).
 		#[arg(short = 'o', long)]
 		output_factory: String,
 
-		/// Output factory (TEMP) meta JSON path.
+		/// Output factory (TEMP) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'p', long)]
 		output_factory_meta: String,
 
-		/// Output blueprint (TBLU) JSON path.
+		/// Output blueprint (TBLU) path (ResourceTool JSON, or binary if it ends in .tblu).
 		#[arg(short = 'q', long)]
 		output_blueprint: String,
 
-		/// Output blueprint (TBLU) meta JSON path.
+		/// Output blueprint (TBLU) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'r', long)]
 		output_blueprint_meta: String,
 
-		/// Convert keeping all scale values, no matter if insignificant (1.00 when rounded to 2 d.p.).
-		#[arg(short = 's', long, action)]
-		lossless: bool,
+		#[command(flatten)]
+		options: ConvertArgs,
 
 		/// Be more permissive with certain unexpected scenarios in patching, such as properties that should be removed already being gone.
 		#[arg(long, action)]
@@ -81,6 +93,23 @@ enum Command {
 		/// Generate RT JSON files compatible with HITMAN (2016).
 		#[arg(long, action)]
 		h1: bool
+	},
+
+	/// Generate a JSON Schema for QuickEntity entities or patches, for editors to validate and autocomplete them with.
+	Schema {
+		/// The format to generate a schema for (entity or patch).
+		kind: SchemaKind,
+
+		/// Output JSON Schema path.
+		#[arg(short = 'o', long)]
+		output: String
+	},
+
+	/// Generate TypeScript definitions for the QuickEntity entity and patch types.
+	ExportTypes {
+		/// Output TypeScript definition (.d.ts) path.
+		#[arg(short = 'o', long)]
+		output: String
 	}
 }
 
@@ -88,19 +117,19 @@ enum Command {
 enum EntityCommand {
 	/// Convert a set of JSON files into a QuickEntity JSON file.
 	Convert {
-		/// Factory (TEMP) JSON path.
+		/// Factory (TEMP) path (ResourceTool JSON, or binary if it ends in .temp).
 		#[arg(short = 'i', long)]
 		input_factory: String,
 
-		/// Factory (TEMP) meta JSON path.
+		/// Factory (TEMP) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'j', long)]
 		input_factory_meta: String,
 
-		/// Blueprint (TBLU) JSON path.
+		/// Blueprint (TBLU) path (ResourceTool JSON, or binary if it ends in .tblu).
 		#[arg(short = 'k', long)]
 		input_blueprint: String,
 
-		/// Blueprint (TBLU) meta JSON path.
+		/// Blueprint (TBLU) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'l', long)]
 		input_blueprint_meta: String,
 
@@ -108,9 +137,8 @@ enum EntityCommand {
 		#[arg(short = 'o', long)]
 		output: String,
 
-		/// Convert keeping all scale values, no matter if insignificant (1.00 when rounded to 2 d.p.).
-		#[arg(short = 's', long, action)]
-		lossless: bool
+		#[command(flatten)]
+		options: ConvertArgs
 	},
 
 	/// Generate a set of JSON files from a QuickEntity JSON file.
@@ -119,25 +147,29 @@ enum EntityCommand {
 		#[arg(short = 'i', long)]
 		input: String,
 
-		/// Factory (TEMP) JSON path.
+		/// Factory (TEMP) path (ResourceTool JSON, or binary if it ends in .temp).
 		#[arg(short = 'o', long)]
 		output_factory: String,
 
-		/// Factory (TEMP) meta JSON path.
+		/// Factory (TEMP) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'p', long)]
 		output_factory_meta: String,
 
-		/// Blueprint (TBLU) JSON path.
+		/// Blueprint (TBLU) path (ResourceTool JSON, or binary if it ends in .tblu).
 		#[arg(short = 'q', long)]
 		output_blueprint: String,
 
-		/// Blueprint (TBLU) meta JSON path.
+		/// Blueprint (TBLU) meta path (RPKG tool JSON, or binary if it ends in .meta).
 		#[arg(short = 'r', long)]
 		output_blueprint_meta: String,
 
 		/// Output RT JSON files compatible with HITMAN (2016).
 		#[arg(long, action)]
-		h1: bool
+		h1: bool,
+
+		/// Order sub-entities by ID in the output rather than keeping the order of the QuickEntity JSON.
+		#[arg(long, action)]
+		sort_entities: bool
 	},
 
 	/// Output the same QuickEntity JSON in standard form, including consistent entity ID lengths and sorted JSON keys.
@@ -150,9 +182,48 @@ enum EntityCommand {
 		#[arg(short = 'o', long)]
 		output: String,
 
-		/// Convert keeping all scale values, no matter if insignificant (1.00 when rounded to 2 d.p.).
-		#[arg(short = 's', long, action)]
-		lossless: bool
+		#[command(flatten)]
+		options: ConvertArgs
+	},
+
+	/// Check a QuickEntity JSON file for problems, such as references to nonexistent entities or unparseable property values, and list them all.
+	Validate {
+		/// Input QuickEntity JSON path.
+		#[arg(short = 'i', long)]
+		input: String
+	},
+
+	/// Rewrite a QuickEntity JSON file from an older QuickEntity version to the current one.
+	Upgrade {
+		/// Input QuickEntity JSON path.
+		#[arg(short = 'i', long)]
+		input: String,
+
+		/// Output QuickEntity JSON path.
+		#[arg(short = 'o', long)]
+		output: String
+	},
+
+	/// Split a QuickEntity JSON file into a project directory, with a file for each sub-entity, so that it can be diffed and merged more easily.
+	Explode {
+		/// Input QuickEntity JSON path.
+		#[arg(short = 'i', long)]
+		input: String,
+
+		/// Output project directory path.
+		#[arg(short = 'o', long)]
+		output: String
+	},
+
+	/// Combine a project directory back into a single QuickEntity JSON file.
+	Implode {
+		/// Input project directory path.
+		#[arg(short = 'i', long)]
+		input: String,
+
+		/// Output QuickEntity JSON path.
+		#[arg(short = 'o', long)]
+		output: String
 	}
 }
 
@@ -172,9 +243,61 @@ enum PatchCommand {
 		#[arg(short = 'o', long)]
 		output: String,
 
-		/// Mitigate a serde-json issue where numbers are sometimes not considered equal by parsing JSON files twice.
+		/// Mitigate a serde-json issue where numbers are sometimes not considered equal by parsing JSON files twice. Not needed for files written by QuickEntity, which are written in canonical form.
+		#[arg(long, action)]
+		format_fix: bool,
+
+		/// Replace changed array property values entirely, rather than diffing them item by item.
+		#[arg(long, action)]
+		replace_arrays: bool,
+
+		/// Replace changed object property values (such as transforms) entirely, rather than patching only the parts which changed.
+		#[arg(long, action)]
+		replace_values: bool,
+
+		/// How far apart numbers can be while still being considered equal.
+		#[arg(long, default_value_t = 0.0)]
+		tolerance: f64,
+
+		/// How far apart SMatrix43 positions can be while still being considered equal.
+		#[arg(long, default_value_t = 0.0)]
+		position_tolerance: f64,
+
+		/// How far apart SMatrix43 rotations (in degrees) can be while still being considered equal.
+		#[arg(long, default_value_t = 0.0)]
+		rotation_tolerance: f64,
+
+		/// How far apart SMatrix43 scales can be while still being considered equal.
+		#[arg(long, default_value_t = 0.0)]
+		scale_tolerance: f64,
+
+		/// Don't include changes to comments.
+		#[arg(long, action)]
+		ignore_comments: bool,
+
+		/// Don't include changes to extra factory and blueprint dependencies.
 		#[arg(long, action)]
-		format_fix: bool
+		ignore_dependencies: bool,
+
+		/// Don't include changes to external scenes.
+		#[arg(long, action)]
+		ignore_external_scenes: bool,
+
+		/// Normalise entity IDs in both entities before comparing them, so that IDs which only differ in length are the same.
+		#[arg(long, action)]
+		normalise_ids: bool,
+
+		/// The algorithm used to diff arrays (myers, patience or lcs).
+		#[arg(long, default_value = "patience")]
+		algorithm: DiffAlgorithm,
+
+		/// Convert both entities to RT and back with the conversion options before comparing them, so that values which are only represented differently (such as transforms written as Euler angles in one and quaternions in the other) aren't included as changes.
+		#[arg(long, action)]
+		normalise: bool,
+
+		/// Conversion options, used when normalising.
+		#[command(flatten)]
+		options: ConvertArgs
 	},
 
 	/// Apply a patch JSON to an entity JSON file.
@@ -188,8 +311,8 @@ enum PatchCommand {
 		patch: String,
 
 		/// Output QuickEntity JSON path.
-		#[arg(short = 'o', long)]
-		output: String,
+		#[arg(short = 'o', long, required_unless_present = "dry_run")]
+		output: Option<String>,
 
 		/// Be more permissive with certain unexpected scenarios, such as properties that should be removed already being gone.
 		#[arg(long, action)]
@@ -199,9 +322,130 @@ enum PatchCommand {
 		#[arg(long, action)]
 		normalise: bool,
 
-		/// Mitigate a serde-json issue where numbers are sometimes not considered equal by parsing JSON files twice.
+		/// Mitigate a serde-json issue where numbers are sometimes not considered equal by parsing JSON files twice. Not needed for files written by QuickEntity, which are written in canonical form.
+		#[arg(long, action)]
+		format_fix: bool,
+
+		/// Print whether each patch operation would be applied, skipped or fail, without writing any output.
 		#[arg(long, action)]
-		format_fix: bool
+		dry_run: bool
+	},
+
+	/// Combine several patch JSONs made against the same entity into one, failing if any of them conflict.
+	Merge {
+		/// Base QuickEntity JSON path.
+		#[arg(short = 'i', long)]
+		input: String,
+
+		/// Patch JSON paths.
+		#[arg(num_args = 1..)]
+		patches: Vec<String>,
+
+		/// Output patch JSON path.
+		#[arg(short = 'o', long)]
+		output: String
+	},
+
+	/// Generate a patch JSON that undoes the changes a patch JSON makes to an entity JSON file.
+	Invert {
+		/// QuickEntity JSON path of the entity the patch applies to.
+		#[arg(short = 'i', long)]
+		input: String,
+
+		/// Patch JSON path.
+		#[arg(short = 'j', long)]
+		patch: String,
+
+		/// Output patch JSON path.
+		#[arg(short = 'o', long)]
+		output: String
+	},
+
+	/// Combine a sequence of patch JSONs into a single patch JSON with the same effect, leaving out redundant operations.
+	Squash {
+		/// Base QuickEntity JSON path.
+		#[arg(short = 'i', long)]
+		input: String,
+
+		/// Patch JSON paths, in the order they would be applied.
+		#[arg(num_args = 1..)]
+		patches: Vec<String>,
+
+		/// Output patch JSON path.
+		#[arg(short = 'o', long)]
+		output: String
+	},
+
+	/// Rewrite a patch JSON from an older patch version to the current one, replacing deprecated operations.
+	Upgrade {
+		/// Patch JSON path.
+		#[arg(short = 'i', long)]
+		input: String,
+
+		/// Output patch JSON path.
+		#[arg(short = 'o', long)]
+		output: String
+	}
+}
+
+/// Options for converting RT source files to QuickEntity JSON.
+#[derive(clap::Args)]
+struct ConvertArgs {
+	/// Convert keeping all scale values, no matter if insignificant (1.00 when rounded to 2 d.p.).
+	#[arg(short = 's', long, action, conflicts_with = "scale_precision")]
+	lossless: bool,
+
+	/// The number of decimal places scale values are rounded to when deciding whether they're insignificant.
+	#[arg(long, default_value_t = 2)]
+	scale_precision: u32,
+
+	/// How to represent SMatrix43 transforms (euler, quaternion or matrix). Matrix keeps transforms exactly as they are.
+	#[arg(long, default_value = "euler")]
+	transform: TransformRepresentation,
+
+	/// The case to write ZGuid values in (lower or upper).
+	#[arg(long, default_value = "lower")]
+	guid_case: GuidCase,
+
+	/// How to represent SColorRGB and SColorRGBA values (hex or float).
+	#[arg(long, default_value = "hex")]
+	colours: ColourRepresentation,
+
+	/// Order sub-entities by ID in RT output rather than keeping the order of the QuickEntity JSON. QuickEntity JSON, YAML and RON files are always written with sub-entities ordered by ID, so this has no effect on them.
+	#[arg(long, action)]
+	sort_entities: bool,
+
+	/// Give each overridden entity its own property overrides rather than grouping entities with the same overrides.
+	#[arg(long, action)]
+	no_group_overrides: bool,
+
+	/// Path to a dictionary of known property names (one per line), used to name properties which RT only stores the CRC32 of.
+	#[arg(long)]
+	property_names: Option<String>,
+
+	/// Path to a hash list of known IOI paths (lines of `hash,path` or just a path), used to make the runtime IDs of resources readable.
+	#[arg(long)]
+	hash_list: Option<String>,
+
+	/// Whether to annotate runtime IDs with their paths from the hash list or replace them (annotate or replace).
+	#[arg(long, default_value = "annotate", requires = "hash_list")]
+	hash_list_mode: HashListMode
+}
+
+impl ConvertArgs {
+	#[try_fn]
+	fn to_options(&self) -> Result<ConvertOptions> {
+		ConvertOptions {
+			scale_precision: (!self.lossless).then_some(self.scale_precision),
+			transform_representation: self.transform,
+			guid_case: self.guid_case,
+			colour_representation: self.colours,
+			sort_entities: self.sort_entities,
+			group_property_overrides: !self.no_group_overrides,
+			property_names: self.property_names.as_deref().map(read_as_property_names).transpose()?,
+			hash_list: self.hash_list.as_deref().map(read_as_hash_list).transpose()?,
+			hash_list_mode: self.hash_list_mode
+		}
 	}
 }
 
@@ -224,17 +468,23 @@ fn main() -> Result<()> {
 					input_blueprint,
 					input_blueprint_meta,
 					output,
-					lossless
+					options
 				}
 		} => {
-			let factory = read_as_rtfactory(&input_factory);
-			let factory_meta = read_as_meta(&input_factory_meta);
-			let blueprint = read_as_rtblueprint(&input_blueprint);
-			let blueprint_meta = read_as_meta(&input_blueprint_meta);
-
-			let entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, lossless)?;
-
-			fs::write(output, to_vec_float_format(&entity)).unwrap();
+			let factory = read_as_rtfactory(&input_factory)?;
+			let factory_meta = read_as_meta(&input_factory_meta)?;
+			let blueprint = read_as_rtblueprint(&input_blueprint)?;
+			let blueprint_meta = read_as_meta(&input_blueprint_meta)?;
+
+			let entity = convert_to_qn(
+				&factory,
+				&factory_meta,
+				&blueprint,
+				&blueprint_meta,
+				&options.to_options()?
+			)?;
+
+			write_as_value(&output, &entity)?;
 		}
 
 		Command::Entity {
@@ -245,71 +495,142 @@ fn main() -> Result<()> {
 					output_factory_meta,
 					output_blueprint,
 					output_blueprint_meta,
-					h1
+					h1,
+					sort_entities
 				}
 		} => {
-			let entity = read_as_entity(&input);
-
-			let (converted_fac, converted_fac_meta, converted_blu, converted_blu_meta) = convert_to_rt(&entity)?;
+			let entity = read_as_entity(&input)?;
 
-			fs::write(output_factory, {
-				if h1 {
-					to_vec_float_format(&converted_fac.into_legacy())
-				} else {
-					to_vec_float_format(&converted_fac)
+			let (converted_fac, converted_fac_meta, converted_blu, converted_blu_meta) = convert_to_rt(
+				&entity,
+				&ConvertOptions {
+					sort_entities,
+					..Default::default()
 				}
-			})
-			.unwrap();
+			)?;
 
-			fs::write(output_factory_meta, to_vec_float_format(&converted_fac_meta)).unwrap();
+			write_rtfactory(&output_factory, converted_fac, h1)?;
 
-			fs::write(output_blueprint, {
-				if h1 {
-					to_vec_float_format(&converted_blu.into_legacy())
-				} else {
-					to_vec_float_format(&converted_blu)
-				}
-			})
-			.unwrap();
+			write_meta(&output_factory_meta, &converted_fac_meta)?;
+
+			write_rtblueprint(&output_blueprint, converted_blu, h1)?;
 
-			fs::write(output_blueprint_meta, to_vec_float_format(&converted_blu_meta)).unwrap();
+			write_meta(&output_blueprint_meta, &converted_blu_meta)?;
 		}
 
 		Command::Entity {
-			subcommand: EntityCommand::Normalise {
-				input,
-				output,
-				lossless
+			subcommand: EntityCommand::Normalise { input, output, options }
+		} => {
+			let mut entity = read_as_entity(&input)?;
+			let options = options.to_options()?;
+
+			let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(&entity, &options)?;
+			entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, &options)?;
+
+			write_as_value(&output, &entity)?;
+		}
+
+		Command::Entity {
+			subcommand: EntityCommand::Validate { input }
+		} => {
+			let entity = read_as_entity(&input)?;
+
+			let diagnostics = entity.validate();
+
+			for diagnostic in &diagnostics {
+				println!("{}", diagnostic);
+			}
+
+			if !diagnostics.is_empty() {
+				bail!("Entity has {} problem(s)", diagnostics.len());
 			}
+		}
+
+		Command::Entity {
+			subcommand: EntityCommand::Upgrade { input, output }
+		} => {
+			let entity = upgrade_entity(read_as_value(&input)?)?;
+
+			write_as_value(&output, &entity)?;
+		}
+
+		Command::Entity {
+			subcommand: EntityCommand::Explode { input, output }
 		} => {
-			let mut entity = read_as_entity(&input);
+			let entity = read_as_entity(&input)?;
 
-			let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(&entity)?;
-			entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, lossless)?;
+			explode_entity(&entity, output)?;
+		}
+
+		Command::Entity {
+			subcommand: EntityCommand::Implode { input, output }
+		} => {
+			let entity = implode_entity(input)?;
 
-			fs::write(output, to_vec_float_format(&entity)).unwrap();
+			write_as_value(&output, &entity)?;
 		}
 
 		Command::Patch {
-			subcommand: PatchCommand::Generate {
-				input1,
-				input2,
-				output,
-				format_fix
-			}
+			subcommand:
+				PatchCommand::Generate {
+					input1,
+					input2,
+					output,
+					format_fix,
+					replace_arrays,
+					replace_values,
+					tolerance,
+					position_tolerance,
+					rotation_tolerance,
+					scale_tolerance,
+					ignore_comments,
+					ignore_dependencies,
+					ignore_external_scenes,
+					normalise_ids,
+					algorithm,
+					normalise,
+					options
+				}
 		} => {
-			let mut entity1 = read_as_entity(&input1);
-			let mut entity2 = read_as_entity(&input2);
+			let mut entity1 = read_as_entity(&input1)?;
+			let mut entity2 = read_as_entity(&input2)?;
 
 			if format_fix {
-				entity1 = from_slice(&to_vec_float_format(&entity1))?;
+				entity1 = from_slice(&to_vec_float_format(&entity1)?)?;
 
-				entity2 = from_slice(&to_vec_float_format(&entity2))?;
+				entity2 = from_slice(&to_vec_float_format(&entity2)?)?;
 			}
 
-			let patch = generate_patch(&entity1, &entity2)?;
+			if normalise {
+				let options = options.to_options()?;
+
+				for entity in [&mut entity1, &mut entity2] {
+					let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(entity, &options)?;
+					*entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, &options)?;
+				}
+			}
 
-			fs::write(output, to_vec_float_format(&patch)).unwrap();
+			let patch = generate_patch_with(
+				&entity1,
+				&entity2,
+				&PatchGenOptions {
+					diff_arrays: !replace_arrays,
+					partial_values: !replace_values,
+					tolerance: FloatTolerance {
+						value: tolerance,
+						position: position_tolerance,
+						rotation: rotation_tolerance,
+						scale: scale_tolerance
+					},
+					diff_comments: !ignore_comments,
+					diff_dependencies: !ignore_dependencies,
+					diff_external_scenes: !ignore_external_scenes,
+					normalise_entity_ids: normalise_ids,
+					algorithm
+				}
+			)?;
+
+			write_as_value(&output, &patch)?;
 		}
 
 		Command::Patch {
@@ -320,30 +641,129 @@ fn main() -> Result<()> {
 					output,
 					permissive,
 					normalise,
-					format_fix
+					format_fix,
+					dry_run
 				}
 		} => {
-			let mut entity = read_as_entity(&input);
-			let mut patch = read_as_patch(&patch);
+			let mut entity = read_as_entity(&input)?;
+			let mut patch = read_as_patch(&patch)?;
+
+			let lossless = ConvertOptions {
+				scale_precision: None,
+				..Default::default()
+			};
 
 			if format_fix {
-				entity = from_slice(&to_vec_float_format(&entity))?;
-				patch = from_slice(&to_vec_float_format(&patch))?;
+				entity = from_slice(&to_vec_float_format(&entity)?)?;
+				patch = from_slice(&to_vec_float_format(&patch)?)?;
 			}
 
 			if normalise {
-				let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(&entity)?;
-				entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, true)?;
+				let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(&entity, &lossless)?;
+				entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, &lossless)?;
 			}
 
-			apply_patch(&mut entity, patch, permissive)?;
+			if dry_run {
+				let report = apply_patch_with_report(&mut entity, patch, permissive)?;
+
+				for operation in &report.operations {
+					println!(
+						"{}: {:?}{}{}",
+						operation.index,
+						operation.status,
+						operation
+							.entity_id
+							.as_ref()
+							.map(|x| format!(" (entity {})", x))
+							.unwrap_or_default(),
+						operation
+							.reason
+							.as_ref()
+							.map(|x| format!(" - {}", x))
+							.unwrap_or_default()
+					);
+				}
 
-			if normalise {
-				let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(&entity)?;
-				entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, true)?;
+				let failed = report
+					.operations
+					.iter()
+					.filter(|x| x.status == PatchOperationStatus::Failed)
+					.count();
+
+				if failed != 0 {
+					bail!("{} patch operation(s) would fail", failed);
+				}
+			} else {
+				apply_patch(&mut entity, patch, permissive)?;
+
+				if normalise {
+					let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(&entity, &lossless)?;
+					entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, &lossless)?;
+				}
+
+				write_as_value(&output.expect("Output is required"), &entity)?;
 			}
+		}
 
-			fs::write(output, to_vec_float_format(&entity)).unwrap();
+		Command::Patch {
+			subcommand: PatchCommand::Merge { input, patches, output }
+		} => {
+			let entity = read_as_entity(&input)?;
+
+			match merge_patches(
+				&entity,
+				&patches.iter().map(|x| read_as_patch(x)).collect::<Result<Vec<_>>>()?
+			)? {
+				MergeResult::Merged(patch) => {
+					write_as_value(&output, &patch)?;
+				}
+
+				MergeResult::Conflicts(conflicts) => {
+					for conflict in &conflicts {
+						println!(
+							"{}: {}\nconflicts with\n{}: {}\n",
+							patches[conflict.first_patch],
+							serde_json::to_string(&conflict.first_operation)?,
+							patches[conflict.second_patch],
+							serde_json::to_string(&conflict.second_operation)?
+						);
+					}
+
+					bail!("Patches have {} conflict(s)", conflicts.len());
+				}
+			}
+		}
+
+		Command::Patch {
+			subcommand: PatchCommand::Invert { input, patch, output }
+		} => {
+			let entity = read_as_entity(&input)?;
+			let patch = read_as_patch(&patch)?;
+
+			let inverse = invert_patch(&entity, &patch)?;
+
+			write_as_value(&output, &inverse)?;
+		}
+
+		Command::Patch {
+			subcommand: PatchCommand::Squash { input, patches, output }
+		} => {
+			let entity = read_as_entity(&input)?;
+
+			let patch = compose_patches(
+				&entity,
+				&patches.iter().map(|x| read_as_patch(x)).collect::<Result<Vec<_>>>()?
+			)?;
+
+			write_as_value(&output, &patch)?;
+		}
+
+		Command::Patch {
+			subcommand: PatchCommand::Upgrade { input, output }
+		} => {
+			let patch = upgrade_patch(read_as_patch(&input)?)?;
+
+			write_as_value(&output, &patch)?;
 		}
 
 		Command::ConvertPatchGenerate {
@@ -357,45 +777,42 @@ fn main() -> Result<()> {
 			output_blueprint_meta,
 			patches,
 			h1,
-			lossless,
+			options,
 			permissive
 		} => {
-			let factory = read_as_rtfactory(&input_factory);
-			let factory_meta = read_as_meta(&input_factory_meta);
-			let blueprint = read_as_rtblueprint(&input_blueprint);
-			let blueprint_meta = read_as_meta(&input_blueprint_meta);
+			let factory = read_as_rtfactory(&input_factory)?;
+			let factory_meta = read_as_meta(&input_factory_meta)?;
+			let blueprint = read_as_rtblueprint(&input_blueprint)?;
+			let blueprint_meta = read_as_meta(&input_blueprint_meta)?;
+
+			let options = options.to_options()?;
 
-			let mut entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, lossless)?;
+			let mut entity = convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, &options)?;
 
 			for patch in patches {
-				let patch = read_as_patch(&patch);
+				let patch = read_as_patch(&patch)?;
 
 				apply_patch(&mut entity, patch, permissive)?;
 			}
 
-			let (converted_fac, converted_fac_meta, converted_blu, converted_blu_meta) = convert_to_rt(&entity)?;
+			let (converted_fac, converted_fac_meta, converted_blu, converted_blu_meta) =
+				convert_to_rt(&entity, &options)?;
 
-			fs::write(output_factory, {
-				if h1 {
-					to_vec_float_format(&converted_fac.into_legacy())
-				} else {
-					to_vec_float_format(&converted_fac)
-				}
-			})
-			.unwrap();
+			write_rtfactory(&output_factory, converted_fac, h1)?;
 
-			fs::write(output_factory_meta, to_vec_float_format(&converted_fac_meta)).unwrap();
+			write_meta(&output_factory_meta, &converted_fac_meta)?;
 
-			fs::write(output_blueprint, {
-				if h1 {
-					to_vec_float_format(&converted_blu.into_legacy())
-				} else {
-					to_vec_float_format(&converted_blu)
-				}
-			})
-			.unwrap();
+			write_rtblueprint(&output_blueprint, converted_blu, h1)?;
+
+			write_meta(&output_blueprint_meta, &converted_blu_meta)?;
+		}
+
+		Command::Schema { kind, output } => {
+			write_as_value(&output, &generate_schema(kind))?;
+		}
 
-			fs::write(output_blueprint_meta, to_vec_float_format(&converted_blu_meta)).unwrap();
+		Command::ExportTypes { output } => {
+			fs::write(output, generate_typescript()?)?;
 		}
 	}
 }
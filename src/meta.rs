@@ -0,0 +1,193 @@
+use anyhow::{bail, Context, Result};
+use fn_error_context::context;
+use hitman_commons::rpkg_tool::{RpkgResourceMeta, RpkgResourceReference};
+use tryvial::try_fn;
+
+use crate::binary::binary_data_size;
+use crate::error::QuickEntityError;
+use crate::hash::parse_hash;
+
+/// The size of the fixed part of a binary meta, before the reference table.
+const HEADER_SIZE: usize = 44;
+
+/// The top bits of the reference count, which RPKG uses to mark the reference table as having its flags stored before its hashes.
+const REFERENCE_COUNT_FLAGS: u32 = 0xC000_0000;
+
+/// Video memory size of resources that don't use any.
+pub const NO_VIDEO_MEMORY: u32 = u32::MAX;
+
+/// Create the meta for a resource that hasn't been packed yet.
+///
+/// The sizes are computed from the serialised resource (in the BIN1 format), which should be given when it's available; without it, they're left as zero. Some fields can't be known until the resource is packed into an RPKG, so they're always zero:
+///
+/// - the offset, which is where the resource ends up in the RPKG
+/// - the (compressed) size, since zero marks the resource as being stored uncompressed
+///
+/// TEMP and TBLU resources don't use video memory, so their video memory size is always `NO_VIDEO_MEMORY`.
+#[try_fn]
+#[context("Failure creating meta")]
+pub fn new_meta(
+	hash: &str,
+	resource_type: &str,
+	references: Vec<RpkgResourceReference>,
+	resource: Option<&[u8]>
+) -> Result<RpkgResourceMeta, QuickEntityError> {
+	let (size_final, size_in_memory) = match resource {
+		Some(resource) => (
+			u32::try_from(resource.len()).context("Resource is too large")?,
+			binary_data_size(resource)?
+		),
+		None => (0, 0)
+	};
+
+	RpkgResourceMeta {
+		hash_value: hash.to_owned(),
+		hash_offset: 0,
+		hash_size: 0,
+		hash_resource_type: resource_type.to_owned(),
+		hash_reference_table_size: reference_table_size(references.len()),
+		hash_reference_table_dummy: 0,
+		hash_size_final: size_final,
+		hash_size_in_memory: size_in_memory,
+		hash_size_in_video_memory: NO_VIDEO_MEMORY,
+		hash_reference_data: references,
+		hash_path: None
+	}
+}
+
+/// The size of a reference table with the given number of references: a four byte count, then a one byte flag and an eight byte hash for each reference.
+///
+/// Resources without references have no reference table at all.
+pub fn reference_table_size(references: usize) -> u32 {
+	if references == 0 {
+		0
+	} else {
+		4 + references as u32 * 9
+	}
+}
+
+/// Serialise a meta to the binary format RPKG uses.
+///
+/// References given as paths are hashed.
+#[try_fn]
+#[context("Failure writing binary meta")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn write_binary_meta(meta: &RpkgResourceMeta) -> Result<Vec<u8>, QuickEntityError> {
+	let references = &meta.hash_reference_data;
+	let reference_table_size = reference_table_size(references.len());
+
+	let mut data = Vec::with_capacity(HEADER_SIZE + reference_table_size as usize);
+
+	data.extend(parse_hash(&meta.hash_value)?.to_le_bytes());
+	data.extend(meta.hash_offset.to_le_bytes());
+	data.extend(meta.hash_size.to_le_bytes());
+	data.extend(resource_type_bytes(&meta.hash_resource_type)?);
+	data.extend(reference_table_size.to_le_bytes());
+	data.extend(meta.hash_reference_table_dummy.to_le_bytes());
+	data.extend(meta.hash_size_final.to_le_bytes());
+	data.extend(meta.hash_size_in_memory.to_le_bytes());
+	data.extend(meta.hash_size_in_video_memory.to_le_bytes());
+
+	if !references.is_empty() {
+		data.extend((references.len() as u32 | REFERENCE_COUNT_FLAGS).to_le_bytes());
+
+		for reference in references {
+			data.push(
+				u8::from_str_radix(&reference.flag, 16)
+					.with_context(|| format!("Invalid reference flag: {}", reference.flag))?
+			);
+		}
+
+		for reference in references {
+			data.extend(parse_hash(&reference.hash)?.to_le_bytes());
+		}
+	}
+
+	data
+}
+
+/// Read a meta from the binary format RPKG uses.
+#[try_fn]
+#[context("Failure reading binary meta")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn read_binary_meta(data: &[u8]) -> Result<RpkgResourceMeta, QuickEntityError> {
+	let mut reader = Reader { data, position: 0 };
+
+	let hash_value = format!("{:016X}", reader.u64()?);
+	let hash_offset = reader.u64()?;
+	let hash_size = reader.u32()?;
+	let hash_resource_type = reader.bytes(4)?.iter().rev().map(|&x| x as char).collect::<String>();
+	let hash_reference_table_size = reader.u32()?;
+	let hash_reference_table_dummy = reader.u32()?;
+	let hash_size_final = reader.u32()?;
+	let hash_size_in_memory = reader.u32()?;
+	let hash_size_in_video_memory = reader.u32()?;
+
+	let mut hash_reference_data = vec![];
+
+	if hash_reference_table_size != 0 {
+		let count = (reader.u32()? & !REFERENCE_COUNT_FLAGS) as usize;
+
+		let flags = reader.bytes(count)?.to_owned();
+
+		for flag in flags {
+			hash_reference_data.push(RpkgResourceReference {
+				hash: format!("{:016X}", reader.u64()?),
+				flag: format!("{:02X}", flag)
+			});
+		}
+	}
+
+	RpkgResourceMeta {
+		hash_value,
+		hash_offset,
+		hash_size,
+		hash_resource_type,
+		hash_reference_table_size,
+		hash_reference_table_dummy,
+		hash_size_final,
+		hash_size_in_memory,
+		hash_size_in_video_memory,
+		hash_reference_data,
+		hash_path: None
+	}
+}
+
+/// RPKG stores the resource type as a little-endian integer, so its characters are reversed.
+#[try_fn]
+fn resource_type_bytes(resource_type: &str) -> Result<[u8; 4]> {
+	let mut bytes: [u8; 4] = resource_type
+		.as_bytes()
+		.try_into()
+		.with_context(|| format!("Resource type must be four characters: {}", resource_type))?;
+
+	bytes.reverse();
+	bytes
+}
+
+struct Reader<'a> {
+	data: &'a [u8],
+	position: usize
+}
+
+impl<'a> Reader<'a> {
+	#[try_fn]
+	fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+		let Some(bytes) = self.data.get(self.position..self.position + len) else {
+			bail!("Unexpected end of data at offset {}", self.position);
+		};
+
+		self.position += len;
+		bytes
+	}
+
+	#[try_fn]
+	fn u32(&mut self) -> Result<u32> {
+		u32::from_le_bytes(self.bytes(4)?.try_into()?)
+	}
+
+	#[try_fn]
+	fn u64(&mut self) -> Result<u64> {
+		u64::from_le_bytes(self.bytes(8)?.try_into()?)
+	}
+}
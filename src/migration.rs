@@ -0,0 +1,166 @@
+use anyhow::Context;
+use fn_error_context::context;
+use serde_json::{json, Value};
+use tryvial::try_fn;
+
+use crate::error::QuickEntityError;
+use crate::patch_structs::{Patch, PatchOperation, PropertyOverrideConnection};
+use crate::qn_structs::{Entity, PropertyOverride};
+
+/// The patch version written by this version of QuickEntity.
+pub const CURRENT_PATCH_VERSION: u8 = 7;
+
+/// The earliest patch version with its own migration.
+///
+/// Earlier patches were applied as they were before patch versions were checked, so they're upgraded as if they were of this version; any operations they contain that no longer exist fail to deserialise instead.
+pub const MINIMUM_PATCH_VERSION: u8 = 5;
+
+/// A migration of a patch operation from one patch version to the next, giving the equivalent operations.
+type PatchMigration = fn(PatchOperation) -> Vec<PatchOperation>;
+
+/// The migrations from each supported patch version to the next, by the version they upgrade from.
+///
+/// There must be exactly one for every version from `MINIMUM_PATCH_VERSION` up to (but not including) `CURRENT_PATCH_VERSION`, in order.
+const PATCH_MIGRATIONS: &[(u8, PatchMigration)] =
+	&[(5, upgrade_patch_operation_from_5), (6, upgrade_patch_operation_from_6)];
+
+/// The QuickEntity format version written by this version of QuickEntity.
+pub const CURRENT_QUICKENTITY_VERSION: f64 = 3.1;
+
+/// The earliest QuickEntity format version that can be upgraded.
+pub const MINIMUM_QUICKENTITY_VERSION: f64 = 3.0;
+
+#[cfg(feature = "rune")]
+pub fn rune_module() -> Result<rune::Module, rune::ContextError> {
+	let mut module = rune::Module::with_crate_item("quickentity_rs", ["migration"])?;
+
+	module.function_meta(upgrade_patch__meta)?;
+
+	Ok(module)
+}
+
+/// Upgrade a patch from an earlier patch version to the current one, replacing deprecated operations with their current equivalents.
+///
+/// Patches from a newer version of QuickEntity are rejected, since they may contain operations that can't be represented.
+#[try_fn]
+#[context("Failure upgrading patch")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn upgrade_patch(patch: Patch) -> Result<Patch, QuickEntityError> {
+	check_patch_version(patch.patch_version)?;
+
+	Patch {
+		factory_hash: patch.factory_hash,
+		blueprint_hash: patch.blueprint_hash,
+		patch: patch
+			.patch
+			.into_iter()
+			.flat_map(|operation| upgrade_patch_operation(patch.patch_version, operation))
+			.collect(),
+		patch_version: CURRENT_PATCH_VERSION
+	}
+}
+
+/// Check that a patch version is one this version of QuickEntity can apply, which is any version up to the current one.
+pub(crate) fn check_patch_version(version: u8) -> Result<(), QuickEntityError> {
+	if version <= CURRENT_PATCH_VERSION {
+		Ok(())
+	} else {
+		Err(QuickEntityError::UnsupportedPatchVersion { version })
+	}
+}
+
+/// Get the current operations equivalent to an operation from a patch of the given (supported) patch version, by running the migrations from that version onwards in order.
+///
+/// Versions before `MINIMUM_PATCH_VERSION` run every migration.
+pub(crate) fn upgrade_patch_operation(version: u8, operation: PatchOperation) -> Vec<PatchOperation> {
+	PATCH_MIGRATIONS
+		.iter()
+		.filter(|(from_version, _)| *from_version >= version)
+		.fold(vec![operation], |operations, (_, migration)| {
+			operations.into_iter().flat_map(migration).collect()
+		})
+}
+
+/// Property overrides used to be added and removed as a whole; since patch version 6 they're added and removed one entity and property at a time, which lets patches from different sources touch the same override without conflicting.
+fn upgrade_patch_operation_from_5(operation: PatchOperation) -> Vec<PatchOperation> {
+	match operation {
+		PatchOperation::AddPropertyOverride(value) => split_property_override(value)
+			.map(PatchOperation::AddPropertyOverrideConnection)
+			.collect(),
+
+		PatchOperation::RemovePropertyOverride(value) => split_property_override(value)
+			.map(PatchOperation::RemovePropertyOverrideConnection)
+			.collect(),
+
+		operation => vec![operation]
+	}
+}
+
+/// Patch version 7 added the positional array operations, `PatchPropertyValue` and `PatchPlatformSpecificPropertyValue` (which partial changes to property values, such as moving a transform, are now written as), which older versions can't read, but didn't change any existing operations.
+fn upgrade_patch_operation_from_6(operation: PatchOperation) -> Vec<PatchOperation> {
+	vec![operation]
+}
+
+fn split_property_override(property_override: PropertyOverride) -> impl Iterator<Item = PropertyOverrideConnection> {
+	property_override.entities.into_iter().flat_map(move |entity| {
+		property_override
+			.properties
+			.clone()
+			.into_iter()
+			.map(move |(property_name, property_override)| PropertyOverrideConnection {
+				entity: entity.to_owned(),
+				property_name,
+				property_override
+			})
+	})
+}
+
+/// Load an entity from the JSON of any QuickEntity format version from 3.0 onwards, upgrading it to the current version.
+#[try_fn]
+#[context("Failure upgrading entity")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn upgrade_entity(mut entity: Value) -> Result<Entity, QuickEntityError> {
+	let version = entity
+		.get("quickEntityVersion")
+		.and_then(Value::as_f64)
+		.context("Entity has no QuickEntity version")?;
+
+	if !(MINIMUM_QUICKENTITY_VERSION..=CURRENT_QUICKENTITY_VERSION).contains(&version) {
+		return Err(QuickEntityError::UnsupportedEntityVersion { version });
+	}
+
+	if version < 3.1 {
+		upgrade_entity_from_3_0(&mut entity)?;
+	}
+
+	entity["quickEntityVersion"] = json!(CURRENT_QUICKENTITY_VERSION);
+
+	serde_path_to_error::deserialize::<_, Entity>(entity).context("Upgraded entity is invalid")?
+}
+
+/// QuickEntity 3.0 called factories templates, and had no extra dependencies or comments.
+#[try_fn]
+#[context("Failure upgrading entity from QuickEntity 3.0")]
+fn upgrade_entity_from_3_0(entity: &mut Value) -> anyhow::Result<()> {
+	let entity = entity.as_object_mut().context("Entity must be an object")?;
+
+	for sub_entity in entity
+		.get_mut("entities")
+		.and_then(Value::as_object_mut)
+		.context("Entity has no entities")?
+		.values_mut()
+	{
+		let sub_entity = sub_entity.as_object_mut().context("Sub-entity must be an object")?;
+
+		for (old, new) in [("template", "factory"), ("templateFlag", "factoryFlag")] {
+			if let Some(value) = sub_entity.shift_remove(old) {
+				sub_entity.entry(new).or_insert(value);
+			}
+		}
+	}
+
+	for key in ["extraFactoryDependencies", "extraBlueprintDependencies", "comments"] {
+		entity.entry(key).or_insert_with(|| json!([]));
+	}
+}
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use specta::Type;
@@ -15,7 +17,16 @@ pub fn rune_module() -> Result<rune::Module, rune::ContextError> {
 	module.ty::<PatchOperation>()?;
 	module.ty::<SubEntityOperation>()?;
 	module.ty::<ArrayPatchOperation>()?;
+	module.ty::<ValuePatchOperation>()?;
 	module.ty::<PropertyOverrideConnection>()?;
+	module.ty::<MergeResult>()?;
+	module.ty::<MergeConflict>()?;
+	module.ty::<PatchReport>()?;
+	module.ty::<PatchOperationReport>()?;
+	module.ty::<PatchOperationStatus>()?;
+	module.ty::<FloatTolerance>()?;
+	module.ty::<PatchGenOptions>()?;
+	module.ty::<DiffAlgorithm>()?;
 
 	Ok(module)
 }
@@ -38,7 +49,7 @@ pub struct Patch {
 	/// The patch operations to apply.
 	pub patch: Vec<PatchOperation>,
 
-	/// The patch version. The current version is 6.
+	/// The patch version. The current version is 7.
 	#[serde(rename = "patchVersion")]
 	pub patch_version: u8
 }
@@ -69,11 +80,11 @@ pub enum PatchOperation {
 		#[cfg_attr(feature = "rune", rune(get, set))] SubEntityOperation
 	),
 
-	/// Should no longer be emitted by patch generators.
+	/// Should no longer be emitted by patch generators. Upgraded to `AddPropertyOverrideConnection` operations when applied.
 	#[cfg_attr(feature = "rune", rune(constructor))]
 	AddPropertyOverride(#[cfg_attr(feature = "rune", rune(get, set))] PropertyOverride),
 
-	/// Should no longer be emitted by patch generators.
+	/// Should no longer be emitted by patch generators. Upgraded to `RemovePropertyOverrideConnection` operations when applied.
 	#[cfg_attr(feature = "rune", rune(constructor))]
 	RemovePropertyOverride(#[cfg_attr(feature = "rune", rune(get, set))] PropertyOverride),
 
@@ -170,6 +181,12 @@ pub enum SubEntityOperation {
 		#[cfg_attr(feature = "rune", rune(get, set))] Vec<ArrayPatchOperation>
 	),
 
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	PatchPropertyValue(
+		#[cfg_attr(feature = "rune", rune(get, set))] String,
+		#[cfg_attr(feature = "rune", rune(get, set))] Vec<ValuePatchOperation>
+	),
+
 	#[cfg_attr(feature = "rune", rune(constructor))]
 	SetPropertyPostInit(
 		#[cfg_attr(feature = "rune", rune(get, set))] String,
@@ -203,6 +220,13 @@ pub enum SubEntityOperation {
 		#[cfg_attr(feature = "rune", rune(get, set))] Vec<ArrayPatchOperation>
 	),
 
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	PatchPlatformSpecificPropertyValue(
+		#[cfg_attr(feature = "rune", rune(get, set))] String,
+		#[cfg_attr(feature = "rune", rune(get, set))] String,
+		#[cfg_attr(feature = "rune", rune(get, set))] Vec<ValuePatchOperation>
+	),
+
 	#[cfg_attr(feature = "rune", rune(constructor))]
 	SetPlatformSpecificPropertyPostInit(
 		#[cfg_attr(feature = "rune", rune(get, set))] String,
@@ -427,7 +451,31 @@ pub enum ArrayPatchOperation {
 	RemoveItemByValue(Value),
 	AddItemAfter(Value, Value),
 	AddItemBefore(Value, Value),
-	AddItem(Value)
+	AddItem(Value),
+
+	/// Remove an occurrence of a value: the item equal to the value that has the given number of equal items before it.
+	///
+	/// Positional operations are used for arrays with duplicate values, where matching by value alone would be ambiguous. Occurrences are counted in the array as it is when the operation is applied, after any earlier operations in the same array patch.
+	RemoveItemOccurrence(Value, usize),
+
+	/// Insert an item after an occurrence of a value (counted as in `RemoveItemOccurrence`), or at the start of the array if there's no value to insert after.
+	InsertItemAfterOccurrence(Option<(Value, usize)>, Value),
+
+	/// Replace an occurrence of a value (counted as in `RemoveItemOccurrence`) with another value.
+	SetItemOccurrence(Value, usize, Value)
+}
+
+/// An operation on part of a property value, addressed by a JSON pointer into the value (such as `/position/z`).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum ValuePatchOperation {
+	/// Set the value at a pointer, adding it if the pointer is to a key that doesn't exist yet in an object.
+	SetValue(String, Value),
+
+	/// Remove the key at a pointer from its object.
+	RemoveValue(String)
 }
 
 /// A single entity-property override.
@@ -450,3 +498,215 @@ pub struct PropertyOverrideConnection {
 	#[serde(rename = "propertyOverride")]
 	pub property_override: OverriddenProperty
 }
+
+/// The result of merging several patches.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum MergeResult {
+	/// The patches didn't conflict, and were combined into a single patch.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Merged(#[cfg_attr(feature = "rune", rune(get, set))] Patch),
+
+	/// The patches conflicted with each other, so couldn't be combined.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Conflicts(#[cfg_attr(feature = "rune", rune(get, set))] Vec<MergeConflict>)
+}
+
+/// A pair of operations from different patches that can't both be applied.
+#[cfg_attr(feature = "rune", serde_with::apply(_ => #[rune(get, set)]))]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[cfg_attr(feature = "rune", rune(constructor))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+pub struct MergeConflict {
+	/// The index of the first patch involved.
+	#[serde(rename = "firstPatch")]
+	pub first_patch: usize,
+
+	/// The operation from the first patch.
+	#[serde(rename = "firstOperation")]
+	pub first_operation: PatchOperation,
+
+	/// The index of the second patch involved.
+	#[serde(rename = "secondPatch")]
+	pub second_patch: usize,
+
+	/// The operation from the second patch.
+	#[serde(rename = "secondOperation")]
+	pub second_operation: PatchOperation
+}
+
+/// The outcome of applying each operation of a patch.
+#[cfg_attr(feature = "rune", serde_with::apply(_ => #[rune(get, set)]))]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[cfg_attr(feature = "rune", rune(constructor))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+pub struct PatchReport {
+	/// A report for each operation of the patch, in order.
+	pub operations: Vec<PatchOperationReport>
+}
+
+/// The outcome of applying a single patch operation.
+#[cfg_attr(feature = "rune", serde_with::apply(_ => #[rune(get, set)]))]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[cfg_attr(feature = "rune", rune(constructor))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+pub struct PatchOperationReport {
+	/// The index of the operation in the patch.
+	pub index: usize,
+
+	pub status: PatchOperationStatus,
+
+	/// Why the operation was skipped or failed.
+	pub reason: Option<String>,
+
+	/// The ID of the sub-entity the operation targeted, if it targeted one.
+	#[serde(rename = "entity")]
+	pub entity_id: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum PatchOperationStatus {
+	/// The operation was applied as written.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Applied,
+
+	/// The operation couldn't be applied as written, but was permitted in permissive mode.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Skipped,
+
+	/// The operation couldn't be applied.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Failed
+}
+
+/// How far apart numbers can be while still being considered equal when generating a patch, so that float noise from editors doesn't produce operations.
+///
+/// The components of SMatrix43 values use their own tolerances; every other number uses the value tolerance. A tolerance of zero only considers equal numbers equal.
+#[cfg_attr(feature = "rune", serde_with::apply(_ => #[rune(get, set)]))]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[cfg_attr(feature = "rune", rune(constructor))]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Type)]
+pub struct FloatTolerance {
+	/// The tolerance for numbers other than SMatrix43 components.
+	pub value: f64,
+
+	/// The tolerance for SMatrix43 positions.
+	pub position: f64,
+
+	/// The tolerance for SMatrix43 rotations, in degrees. Rotations a whole turn apart are always equal.
+	pub rotation: f64,
+
+	/// The tolerance for SMatrix43 scales. A missing scale is equal to a scale of 1.
+	pub scale: f64
+}
+
+/// Options controlling how a patch is generated.
+#[cfg_attr(feature = "rune", serde_with::apply(_ => #[rune(get, set)]))]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[cfg_attr(feature = "rune", rune(constructor))]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Type)]
+pub struct PatchGenOptions {
+	/// Whether to diff array property values item by item, rather than replacing the whole value when it changes.
+	#[serde(rename = "diffArrays")]
+	pub diff_arrays: bool,
+
+	/// Whether to patch only the parts of object property values (such as transforms) which changed, rather than replacing the whole value when it changes.
+	#[serde(rename = "partialValues")]
+	pub partial_values: bool,
+
+	/// How far apart numbers can be while still being considered equal.
+	#[serde(rename = "tolerance")]
+	pub tolerance: FloatTolerance,
+
+	/// Whether to include changes to comments.
+	#[serde(rename = "diffComments")]
+	pub diff_comments: bool,
+
+	/// Whether to include changes to extra factory and blueprint dependencies.
+	#[serde(rename = "diffDependencies")]
+	pub diff_dependencies: bool,
+
+	/// Whether to include changes to external scenes.
+	#[serde(rename = "diffExternalScenes")]
+	pub diff_external_scenes: bool,
+
+	/// Whether to normalise entity IDs in both entities before comparing them, so that IDs which only differ in length are the same.
+	#[serde(rename = "normaliseEntityIDs")]
+	pub normalise_entity_ids: bool,
+
+	/// The algorithm used to diff arrays.
+	#[serde(rename = "algorithm")]
+	pub algorithm: DiffAlgorithm
+}
+
+impl Default for PatchGenOptions {
+	fn default() -> Self {
+		Self {
+			diff_arrays: true,
+			partial_values: true,
+			tolerance: FloatTolerance::default(),
+			diff_comments: true,
+			diff_dependencies: true,
+			diff_external_scenes: true,
+			normalise_entity_ids: false,
+			algorithm: DiffAlgorithm::Patience
+		}
+	}
+}
+
+/// An algorithm for diffing arrays.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::patch_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum DiffAlgorithm {
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Myers,
+
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Patience,
+
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Lcs
+}
+
+impl From<DiffAlgorithm> for similar::Algorithm {
+	fn from(algorithm: DiffAlgorithm) -> Self {
+		match algorithm {
+			DiffAlgorithm::Myers => similar::Algorithm::Myers,
+			DiffAlgorithm::Patience => similar::Algorithm::Patience,
+			DiffAlgorithm::Lcs => similar::Algorithm::Lcs
+		}
+	}
+}
+
+impl FromStr for DiffAlgorithm {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"myers" => Ok(Self::Myers),
+			"patience" => Ok(Self::Patience),
+			"lcs" => Ok(Self::Lcs),
+			_ => Err(format!(
+				"Unknown diff algorithm {} (expected myers, patience or lcs)",
+				s
+			))
+		}
+	}
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use fn_error_context::context;
+use indexmap::IndexMap;
+use serde_json::{to_value, Map, Value};
+use tryvial::try_fn;
+
+use crate::error::QuickEntityError;
+use crate::io::{from_slice, from_value, to_vec_canonical};
+use crate::qn_structs::{Entity, SubEntity};
+
+/// The directory sub-entities are stored in.
+const ENTITIES_DIRECTORY: &str = "entities";
+
+/// The key in `entity.json` listing the order of the sub-entities, when they aren't in order of ID.
+const ENTITY_ORDER_KEY: &str = "entityOrder";
+
+/// The files other than `entity.json`, and the top-level entity keys that each contains.
+///
+/// Files with a single key contain that key's value directly.
+const FILES: &[(&str, &[&str])] = &[
+	(
+		"overrides.json",
+		&[
+			"propertyOverrides",
+			"overrideDeletes",
+			"pinConnectionOverrides",
+			"pinConnectionOverrideDeletes"
+		]
+	),
+	("externalScenes.json", &["externalScenes"]),
+	(
+		"dependencies.json",
+		&["extraFactoryDependencies", "extraBlueprintDependencies"]
+	),
+	("comments.json", &["comments"])
+];
+
+/// Write an entity to a project directory, which splits it across files so that large entities can be diffed and merged more easily. The directory is created if it doesn't exist.
+///
+/// The directory contains `entity.json`, with the entity's hashes, root entity, sub-type and version (and the order of its sub-entities, if they aren't in order of ID); a file in `entities` for each sub-entity, named by its ID; and `overrides.json`, `externalScenes.json`, `dependencies.json` and `comments.json`. Every file is written as canonical JSON.
+///
+/// Sub-entity files left over from a previous version of the entity are removed.
+#[try_fn]
+#[context("Failure exploding entity")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn explode_entity(entity: &Entity, directory: impl AsRef<Path>) -> Result<(), QuickEntityError> {
+	let directory = directory.as_ref();
+	let entities_directory = directory.join(ENTITIES_DIRECTORY);
+
+	fs::create_dir_all(&entities_directory)
+		.with_context(|| format!("Couldn't create directory {}", entities_directory.display()))?;
+
+	for file in fs::read_dir(&entities_directory)
+		.with_context(|| format!("Couldn't read directory {}", entities_directory.display()))?
+	{
+		let path = file.context("Couldn't read directory entry")?.path();
+
+		if path.extension().is_some_and(|x| x == "json")
+			&& path
+				.file_stem()
+				.and_then(|x| x.to_str())
+				.is_none_or(|x| !entity.entities.contains_key(x))
+		{
+			fs::remove_file(&path).with_context(|| format!("Couldn't remove {}", path.display()))?;
+		}
+	}
+
+	for (entity_id, sub_entity) in &entity.entities {
+		write_file(&entities_directory.join(format!("{}.json", entity_id)), sub_entity)?;
+	}
+
+	let Value::Object(mut manifest) = to_value(entity).context("Couldn't convert entity to JSON")? else {
+		unreachable!("entities are serialised as objects");
+	};
+
+	manifest.remove("entities");
+
+	if !entity.entities.keys().is_sorted() {
+		manifest.insert(
+			ENTITY_ORDER_KEY.to_owned(),
+			to_value(entity.entities.keys().collect::<Vec<_>>()).context("Couldn't convert entity order to JSON")?
+		);
+	}
+
+	for (file, keys) in FILES {
+		let mut contents = keys
+			.iter()
+			.map(|&key| (key.to_owned(), manifest.remove(key).unwrap_or_default()))
+			.collect::<Map<_, _>>();
+
+		if let [key] = keys {
+			write_file(&directory.join(file), &contents.remove(*key))?;
+		} else {
+			write_file(&directory.join(file), &contents)?;
+		}
+	}
+
+	write_file(&directory.join("entity.json"), &manifest)?;
+}
+
+/// Read an entity from a project directory written by `explode_entity`.
+#[try_fn]
+#[context("Failure imploding entity")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn implode_entity(directory: impl AsRef<Path>) -> Result<Entity, QuickEntityError> {
+	let directory = directory.as_ref();
+	let entities_directory = directory.join(ENTITIES_DIRECTORY);
+
+	let mut manifest: Map<String, Value> = read_file(&directory.join("entity.json"))?;
+
+	for (file, keys) in FILES {
+		if let [key] = keys {
+			manifest.insert((*key).to_owned(), read_file(&directory.join(file))?);
+		} else {
+			manifest.extend(read_file::<Map<String, Value>>(&directory.join(file))?);
+		}
+	}
+
+	let mut entities = IndexMap::new();
+
+	for file in fs::read_dir(&entities_directory)
+		.with_context(|| format!("Couldn't read directory {}", entities_directory.display()))?
+	{
+		let path = file.context("Couldn't read directory entry")?.path();
+
+		if path.extension().is_some_and(|x| x == "json") {
+			let entity_id = path
+				.file_stem()
+				.and_then(|x| x.to_str())
+				.with_context(|| format!("Invalid sub-entity file name: {}", path.display()))?
+				.to_owned();
+
+			entities.insert(entity_id, read_file::<SubEntity>(&path)?);
+		}
+	}
+
+	entities.sort_keys();
+
+	// Sub-entities missing from the order (for instance, because they were added in a merge) are left in order of ID after the others
+	if let Some(order) = manifest.remove(ENTITY_ORDER_KEY) {
+		let order: HashMap<String, usize> = from_value::<Vec<String>>(order)?
+			.into_iter()
+			.enumerate()
+			.map(|(index, entity_id)| (entity_id, index))
+			.collect();
+
+		entities.sort_by_cached_key(|entity_id, _| order.get(entity_id).copied().unwrap_or(order.len()));
+	}
+
+	manifest.insert(
+		"entities".to_owned(),
+		to_value(entities).context("Couldn't convert sub-entities to JSON")?
+	);
+
+	from_value(Value::Object(manifest))?
+}
+
+#[try_fn]
+fn write_file<T: ?Sized + serde::Serialize>(path: &Path, contents: &T) -> Result<(), QuickEntityError> {
+	fs::write(path, to_vec_canonical(contents)?).with_context(|| format!("Couldn't write {}", path.display()))?;
+}
+
+#[try_fn]
+fn read_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, QuickEntityError> {
+	from_slice(&fs::read(path).with_context(|| format!("Couldn't read {}", path.display()))?)
+		.with_context(|| format!("Couldn't parse {}", path.display()))?
+}
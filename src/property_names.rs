@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "rune")]
+pub fn rune_module() -> Result<rune::Module, rune::ContextError> {
+	let mut module = rune::Module::with_crate_item("quickentity_rs", ["property_names"])?;
+
+	module.ty::<PropertyNames>()?;
+	module.function_meta(PropertyNames::from_dictionary__meta)?;
+	module.function_meta(PropertyNames::insert__meta)?;
+	module.function_meta(PropertyNames::name__meta)?;
+	module.function_meta(property_id__meta)?;
+
+	Ok(module)
+}
+
+/// The table for the reflected CRC32 (IEEE) polynomial.
+const CRC32_TABLE: [u32; 256] = {
+	let mut table = [0; 256];
+
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 1 != 0 {
+				(crc >> 1) ^ 0xEDB8_8320
+			} else {
+				crc >> 1
+			};
+			bit += 1;
+		}
+
+		table[i] = crc;
+		i += 1;
+	}
+
+	table
+};
+
+/// Get the ID the game uses for a property name, which is the CRC32 of the name.
+#[cfg_attr(feature = "rune", rune::function(keep))]
+pub fn property_id(name: &str) -> u32 {
+	!name.bytes().fold(!0, |crc, byte| {
+		CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+	})
+}
+
+/// A dictionary of known property names, used to turn the numeric IDs of properties whose names aren't stored in RT files back into names.
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::property_names))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PropertyNames {
+	names: HashMap<u32, String>
+}
+
+impl PropertyNames {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a dictionary from the contents of a dictionary file, which lists one property name per line.
+	///
+	/// Blank lines and lines starting with `#` are ignored.
+	#[cfg_attr(feature = "rune", rune::function(keep, path = Self::from_dictionary))]
+	pub fn from_dictionary(dictionary: &str) -> Self {
+		let mut names = Self::new();
+
+		for line in dictionary.lines().map(str::trim) {
+			if !line.is_empty() && !line.starts_with('#') {
+				names.insert(line);
+			}
+		}
+
+		names
+	}
+
+	/// Add a property name to the dictionary.
+	#[cfg_attr(feature = "rune", rune::function(keep, instance))]
+	pub fn insert(&mut self, name: &str) {
+		self.names.insert(property_id(name), name.to_owned());
+	}
+
+	/// Get the name of the property with the given ID, if it's known.
+	#[cfg_attr(feature = "rune", rune::function(keep, instance))]
+	pub fn name(&self, id: u32) -> Option<String> {
+		self.names.get(&id).cloned()
+	}
+
+	pub fn len(&self) -> usize {
+		self.names.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.names.is_empty()
+	}
+}
@@ -1,9 +1,13 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
+use crate::hash::{HashList, HashListMode};
+use crate::property_names::PropertyNames;
+
 #[cfg(feature = "rune")]
 pub fn rune_module() -> Result<rune::Module, rune::ContextError> {
 	let mut module = rune::Module::with_crate_item("quickentity_rs", ["qn_structs"])?;
@@ -26,6 +30,10 @@ pub fn rune_module() -> Result<rune::Module, rune::ContextError> {
 	module.ty::<Ref>()?;
 	module.ty::<Dependency>()?;
 	module.ty::<DependencyWithFlag>()?;
+	module.ty::<ConvertOptions>()?;
+	module.ty::<TransformRepresentation>()?;
+	module.ty::<GuidCase>()?;
+	module.ty::<ColourRepresentation>()?;
 
 	Ok(module)
 }
@@ -775,3 +783,167 @@ pub struct DependencyWithFlag {
 	pub resource: String,
 	pub flag: String
 }
+
+/// Options controlling how entities are converted between RT and QuickEntity JSON.
+///
+/// Conversion to RT accepts every representation, and property names and hashes in either form, regardless of these options; it only uses `sort_entities`.
+#[cfg_attr(feature = "rune", serde_with::apply(_ => #[rune(get, set)]))]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::qn_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[cfg_attr(feature = "rune", rune(constructor))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+pub struct ConvertOptions {
+	/// The number of decimal places scales are rounded to when deciding whether they're insignificant (1) and can be left out.
+	///
+	/// If this is none, conversion is lossless: scales are only left out if they're exactly 1.
+	#[serde(rename = "scalePrecision")]
+	pub scale_precision: Option<u32>,
+
+	/// How SMatrix43 transforms are represented.
+	#[serde(rename = "transformRepresentation")]
+	pub transform_representation: TransformRepresentation,
+
+	/// The case of the hex digits in ZGuid values.
+	#[serde(rename = "guidCase")]
+	pub guid_case: GuidCase,
+
+	/// How SColorRGB and SColorRGBA values are represented.
+	#[serde(rename = "colourRepresentation")]
+	pub colour_representation: ColourRepresentation,
+
+	/// Whether to sort sub-entities by ID rather than keeping the order of the blueprint (or of the QuickEntity JSON, when converting to RT).
+	///
+	/// Entities are always written to files with their sub-entities sorted by ID (see `io::canonicalise`), so this only affects RT output and the order of `Entity::entities` in memory.
+	#[serde(rename = "sortEntities")]
+	pub sort_entities: bool,
+
+	/// Whether to group property overrides, so that properties overridden on the same entities and entities with the same overridden properties share a single override.
+	#[serde(rename = "groupPropertyOverrides")]
+	pub group_property_overrides: bool,
+
+	/// Known property names, used when converting to QN to name properties which RT only stores the ID of. Properties with unknown IDs keep their numeric IDs.
+	#[serde(skip)]
+	pub property_names: Option<PropertyNames>,
+
+	/// Known IOI paths, used when converting to QN to annotate or replace the runtime IDs of resources.
+	#[serde(skip)]
+	pub hash_list: Option<HashList>,
+
+	/// Whether to annotate runtime IDs with their paths from the hash list or replace them.
+	#[serde(rename = "hashListMode")]
+	pub hash_list_mode: HashListMode
+}
+
+impl Default for ConvertOptions {
+	fn default() -> Self {
+		Self {
+			scale_precision: Some(2),
+			transform_representation: TransformRepresentation::default(),
+			guid_case: GuidCase::default(),
+			colour_representation: ColourRepresentation::default(),
+			sort_entities: false,
+			group_property_overrides: true,
+			property_names: None,
+			hash_list: None,
+			hash_list_mode: HashListMode::default()
+		}
+	}
+}
+
+/// How SMatrix43 transforms are represented in QuickEntity JSON.
+///
+/// Conversion back to RT accepts any of them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::qn_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum TransformRepresentation {
+	/// A rotation in Euler angles (degrees), a position and a scale if there is one. Loses precision near gimbal lock, and can't represent shear.
+	#[default]
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Euler,
+
+	/// A rotation as a quaternion, a position and a scale if there is one. Can't represent shear.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Quaternion,
+
+	/// The 4x3 matrix exactly as the game stores it (`XAxis`, `YAxis`, `ZAxis` and `Trans`).
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Matrix
+}
+
+impl FromStr for TransformRepresentation {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"euler" => Ok(Self::Euler),
+			"quaternion" => Ok(Self::Quaternion),
+			"matrix" => Ok(Self::Matrix),
+			_ => Err(format!(
+				"Unknown transform representation {} (expected euler, quaternion or matrix)",
+				s
+			))
+		}
+	}
+}
+
+/// The case of hex digits in ZGuid values.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::qn_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum GuidCase {
+	#[default]
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Lower,
+
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Upper
+}
+
+impl FromStr for GuidCase {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"lower" => Ok(Self::Lower),
+			"upper" => Ok(Self::Upper),
+			_ => Err(format!("Unknown GUID case {} (expected lower or upper)", s))
+		}
+	}
+}
+
+/// How SColorRGB and SColorRGBA values are represented in QuickEntity JSON.
+///
+/// Conversion back to RT accepts either of them.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::qn_structs))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+pub enum ColourRepresentation {
+	/// A hex string such as `#ff8000`, with each channel rounded to a byte.
+	#[default]
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Hex,
+
+	/// An object of floats from 0 to 1 (`r`, `g`, `b` and, for SColorRGBA, `a`), exactly as the game stores it.
+	#[cfg_attr(feature = "rune", rune(constructor))]
+	Float
+}
+
+impl FromStr for ColourRepresentation {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"hex" => Ok(Self::Hex),
+			"float" => Ok(Self::Float),
+			_ => Err(format!("Unknown colour representation {} (expected hex or float)", s))
+		}
+	}
+}
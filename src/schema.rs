@@ -0,0 +1,357 @@
+use std::str::FromStr;
+
+use serde_json::{json, Map, Value};
+use specta::datatype::{
+	DataType, EnumRepr, EnumType, EnumVariants, Field, LiteralType, NamedFields, PrimitiveType, StructFields,
+	StructType
+};
+use specta::{NamedType, TypeCollection};
+
+use crate::patch_structs::Patch;
+use crate::qn_structs::Entity;
+
+/// The JSON Schema dialect that schemas are generated in.
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A format that a JSON Schema can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+	/// QuickEntity entities.
+	Entity,
+
+	/// QuickEntity patches.
+	Patch
+}
+
+impl FromStr for SchemaKind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"entity" => Ok(Self::Entity),
+			"patch" => Ok(Self::Patch),
+			_ => Err(format!("Unknown schema {} (expected entity or patch)", s))
+		}
+	}
+}
+
+/// Generate a JSON Schema (draft 2020-12) for entities or patches, for editors to validate and autocomplete QuickEntity files with.
+///
+/// The schema is derived from the types' specta definitions, so it follows their serde representation, and the types' doc comments become descriptions.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn generate_schema(kind: SchemaKind) -> Value {
+	match kind {
+		SchemaKind::Entity => schema_for::<Entity>(),
+		SchemaKind::Patch => schema_for::<Patch>()
+	}
+}
+
+fn schema_for<T: NamedType>() -> Value {
+	let mut types = TypeCollection::default();
+	types.register::<T>();
+
+	let root = types
+		.get(T::sid())
+		.expect("registered types are in the collection")
+		.name()
+		.to_string();
+
+	let mut definitions = Map::new();
+
+	for (_, ty) in &types {
+		let mut schema = convert(&ty.inner);
+		describe(&mut schema, ty.docs());
+		definitions.insert(ty.name().to_string(), schema);
+	}
+
+	json!({
+		"$schema": DIALECT,
+		"title": root,
+		"$ref": reference(&root),
+		"$defs": definitions
+	})
+}
+
+fn reference(name: &str) -> String {
+	format!("#/$defs/{}", name)
+}
+
+/// Add a description to a schema from a doc comment, if there is one.
+fn describe(schema: &mut Value, docs: &str) {
+	// Doc comments keep the space after each `///`
+	let docs = docs
+		.lines()
+		.map(|line| line.strip_prefix(' ').unwrap_or(line))
+		.collect::<Vec<_>>()
+		.join("\n");
+
+	let docs = docs.trim();
+
+	if docs.is_empty() {
+		return;
+	}
+
+	match schema {
+		Value::Object(schema) => {
+			schema.insert("description".into(), docs.into());
+		}
+
+		// Boolean schemas can't carry keywords
+		schema => {
+			*schema = json!({ "description": docs });
+		}
+	}
+}
+
+fn convert(ty: &DataType) -> Value {
+	match ty {
+		DataType::Any | DataType::Unknown | DataType::Generic(_) => json!({}),
+
+		DataType::Primitive(primitive) => convert_primitive(primitive),
+
+		DataType::Literal(literal) => convert_literal(literal),
+
+		DataType::List(list) => {
+			let mut schema = json!({
+				"type": "array",
+				"items": convert(list.ty())
+			});
+
+			if let Some(length) = list.length() {
+				schema["minItems"] = length.into();
+				schema["maxItems"] = length.into();
+			}
+
+			if list.unique() {
+				schema["uniqueItems"] = true.into();
+			}
+
+			schema
+		}
+
+		// Map keys are always strings in JSON, whatever they are in Rust
+		DataType::Map(map) => json!({
+			"type": "object",
+			"additionalProperties": convert(map.value_ty())
+		}),
+
+		DataType::Nullable(inner) => json!({
+			"anyOf": [convert(inner), { "type": "null" }]
+		}),
+
+		DataType::Struct(structure) => convert_struct(structure),
+
+		DataType::Enum(enumeration) => convert_enum(enumeration),
+
+		DataType::Tuple(tuple) => convert_tuple(tuple.elements().iter().collect()),
+
+		DataType::Reference(named) => json!({ "$ref": reference(named.name()) })
+	}
+}
+
+fn convert_primitive(primitive: &PrimitiveType) -> Value {
+	let integer = |minimum: Value, maximum: Value| {
+		json!({
+			"type": "integer",
+			"minimum": minimum,
+			"maximum": maximum
+		})
+	};
+
+	match primitive {
+		PrimitiveType::i8 => integer(i8::MIN.into(), i8::MAX.into()),
+		PrimitiveType::i16 => integer(i16::MIN.into(), i16::MAX.into()),
+		PrimitiveType::i32 => integer(i32::MIN.into(), i32::MAX.into()),
+		PrimitiveType::i64 | PrimitiveType::isize => integer(i64::MIN.into(), i64::MAX.into()),
+		PrimitiveType::u8 => integer(0.into(), u8::MAX.into()),
+		PrimitiveType::u16 => integer(0.into(), u16::MAX.into()),
+		PrimitiveType::u32 => integer(0.into(), u32::MAX.into()),
+		PrimitiveType::u64 | PrimitiveType::usize => integer(0.into(), u64::MAX.into()),
+		PrimitiveType::i128 | PrimitiveType::u128 => json!({ "type": "integer" }),
+		PrimitiveType::f32 | PrimitiveType::f64 => json!({ "type": "number" }),
+		PrimitiveType::bool => json!({ "type": "boolean" }),
+		PrimitiveType::char => json!({ "type": "string", "minLength": 1, "maxLength": 1 }),
+		PrimitiveType::String => json!({ "type": "string" })
+	}
+}
+
+fn convert_literal(literal: &LiteralType) -> Value {
+	match literal {
+		LiteralType::i8(x) => json!({ "const": x }),
+		LiteralType::i16(x) => json!({ "const": x }),
+		LiteralType::i32(x) => json!({ "const": x }),
+		LiteralType::u8(x) => json!({ "const": x }),
+		LiteralType::u16(x) => json!({ "const": x }),
+		LiteralType::u32(x) => json!({ "const": x }),
+		LiteralType::f32(x) => json!({ "const": x }),
+		LiteralType::f64(x) => json!({ "const": x }),
+		LiteralType::bool(x) => json!({ "const": x }),
+		LiteralType::String(x) => json!({ "const": x }),
+		LiteralType::char(x) => json!({ "const": x }),
+		LiteralType::None => json!({ "type": "null" }),
+		_ => json!({})
+	}
+}
+
+fn convert_struct(structure: &StructType) -> Value {
+	match structure.fields() {
+		StructFields::Unit => json!({ "type": "null" }),
+
+		StructFields::Unnamed(fields) => convert_tuple(fields.fields().iter().filter_map(Field::ty).collect()),
+
+		StructFields::Named(fields) => convert_object(
+			fields,
+			structure.tag().map(|tag| (tag.as_ref(), structure.name().as_ref()))
+		)
+	}
+}
+
+/// Convert a tuple, which serde represents as an array unless it has a single element (a newtype).
+fn convert_tuple(elements: Vec<&DataType>) -> Value {
+	match elements.as_slice() {
+		[] => json!({ "type": "null" }),
+
+		[element] => convert(element),
+
+		elements => json!({
+			"type": "array",
+			"prefixItems": elements.iter().map(|x| convert(x)).collect::<Vec<_>>(),
+			"minItems": elements.len(),
+			"maxItems": elements.len()
+		})
+	}
+}
+
+/// Convert a set of named fields to an object schema, with a tag property if given one (as the tag's key and value).
+fn convert_object(fields: &NamedFields, tag: Option<(&str, &str)>) -> Value {
+	let mut properties = Map::new();
+	let mut required = vec![];
+	let mut flattened = vec![];
+
+	if let Some((key, value)) = tag {
+		properties.insert(key.into(), json!({ "const": value }));
+		required.push(key.to_owned());
+	}
+
+	for (name, field) in fields.fields() {
+		let Some(ty) = field.ty() else {
+			continue;
+		};
+
+		if field.flatten() {
+			flattened.push(convert(ty));
+			continue;
+		}
+
+		let mut schema = convert(ty);
+		describe(&mut schema, field.docs());
+
+		if field.deprecated().is_some() {
+			schema["deprecated"] = true.into();
+		}
+
+		// Missing options are deserialised as None
+		if !field.optional() && !matches!(ty, DataType::Nullable(_)) {
+			required.push(name.to_string());
+		}
+
+		properties.insert(name.to_string(), schema);
+	}
+
+	let object = json!({
+		"type": "object",
+		"properties": properties,
+		"required": required
+	});
+
+	if flattened.is_empty() {
+		object
+	} else {
+		flattened.insert(0, object);
+		json!({ "allOf": flattened })
+	}
+}
+
+fn convert_enum(enumeration: &EnumType) -> Value {
+	let variants = enumeration
+		.variants()
+		.iter()
+		.filter(|(_, variant)| !variant.skip())
+		.collect::<Vec<_>>();
+
+	// Externally tagged enums with only unit variants are just strings
+	if matches!(enumeration.repr(), EnumRepr::External)
+		&& variants
+			.iter()
+			.all(|(_, variant)| matches!(variant.inner(), EnumVariants::Unit))
+	{
+		return json!({ "enum": variants.iter().map(|(name, _)| name).collect::<Vec<_>>() });
+	}
+
+	let schemas = variants
+		.into_iter()
+		.map(|(name, variant)| {
+			let mut schema = match (enumeration.repr(), variant.inner()) {
+				(EnumRepr::Untagged, inner) => convert_variant(inner),
+
+				(EnumRepr::External, EnumVariants::Unit) => json!({ "const": name }),
+
+				(EnumRepr::External, inner) => json!({
+					"type": "object",
+					"properties": { name.to_string(): convert_variant(inner) },
+					"required": [name],
+					"additionalProperties": false
+				}),
+
+				(EnumRepr::Internal { tag }, EnumVariants::Named(fields)) => convert_object(fields, Some((tag, name))),
+
+				(EnumRepr::Internal { tag }, inner) => {
+					let tagged = json!({
+						"type": "object",
+						"properties": { tag.to_string(): { "const": name } },
+						"required": [tag]
+					});
+
+					match inner {
+						EnumVariants::Unit => tagged,
+						inner => json!({ "allOf": [tagged, convert_variant(inner)] })
+					}
+				}
+
+				(EnumRepr::Adjacent { tag, .. }, EnumVariants::Unit) => json!({
+					"type": "object",
+					"properties": { tag.to_string(): { "const": name } },
+					"required": [tag]
+				}),
+
+				(EnumRepr::Adjacent { tag, content }, inner) => json!({
+					"type": "object",
+					"properties": {
+						tag.to_string(): { "const": name },
+						content.to_string(): convert_variant(inner)
+					},
+					"required": [tag, content]
+				})
+			};
+
+			describe(&mut schema, variant.docs());
+
+			if variant.deprecated().is_some() {
+				schema["deprecated"] = true.into();
+			}
+
+			schema
+		})
+		.collect::<Vec<_>>();
+
+	json!({ "anyOf": schemas })
+}
+
+/// Convert the contents of an enum variant, without its tag.
+fn convert_variant(variant: &EnumVariants) -> Value {
+	match variant {
+		EnumVariants::Unit => json!({ "type": "null" }),
+		EnumVariants::Unnamed(fields) => convert_tuple(fields.fields().iter().filter_map(Field::ty).collect()),
+		EnumVariants::Named(fields) => convert_object(fields, None)
+	}
+}
@@ -0,0 +1,74 @@
+use anyhow::Context;
+use fn_error_context::context;
+use specta::TypeCollection;
+use specta_typescript::{BigIntExportBehavior, Typescript};
+use tryvial::try_fn;
+
+use crate::error::QuickEntityError;
+use crate::patch_structs::{
+	ArrayPatchOperation, DiffAlgorithm, FloatTolerance, MergeConflict, MergeResult, Patch, PatchGenOptions,
+	PatchOperation, PatchOperationReport, PatchOperationStatus, PatchReport, PropertyOverrideConnection,
+	SetPlatformSpecificPropertyValue, SetPropertyValue, SubEntityOperation, ValuePatchOperation
+};
+use crate::qn_structs::{
+	ColourRepresentation, CommentEntity, ConvertOptions, Dependency, DependencyWithFlag, Entity, ExposedEntity,
+	FullRef, GuidCase, OverriddenProperty, PinConnectionOverride, PinConnectionOverrideDelete, Property, PropertyAlias,
+	PropertyOverride, Ref, RefMaybeConstantValue, RefWithConstantValue, SimpleProperty, SubEntity, SubType,
+	TransformRepresentation
+};
+
+/// Generate TypeScript definitions for all of the entity and patch types, as the contents of a single `.d.ts` file.
+///
+/// Types are exported as they're serialised, so untagged enums become unions and renamed fields keep their JSON names. Doc comments are kept as JSDoc comments.
+#[try_fn]
+#[context("Failure generating TypeScript definitions")]
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn generate_typescript() -> Result<String, QuickEntityError> {
+	let mut types = TypeCollection::default();
+
+	types
+		.register::<Entity>()
+		.register::<SubType>()
+		.register::<CommentEntity>()
+		.register::<SubEntity>()
+		.register::<RefMaybeConstantValue>()
+		.register::<RefWithConstantValue>()
+		.register::<Property>()
+		.register::<SimpleProperty>()
+		.register::<ExposedEntity>()
+		.register::<PropertyAlias>()
+		.register::<PinConnectionOverride>()
+		.register::<PinConnectionOverrideDelete>()
+		.register::<PropertyOverride>()
+		.register::<OverriddenProperty>()
+		.register::<FullRef>()
+		.register::<Ref>()
+		.register::<Dependency>()
+		.register::<DependencyWithFlag>()
+		.register::<ConvertOptions>()
+		.register::<TransformRepresentation>()
+		.register::<GuidCase>()
+		.register::<ColourRepresentation>()
+		.register::<Patch>()
+		.register::<PatchOperation>()
+		.register::<SubEntityOperation>()
+		.register::<SetPropertyValue>()
+		.register::<SetPlatformSpecificPropertyValue>()
+		.register::<ArrayPatchOperation>()
+		.register::<ValuePatchOperation>()
+		.register::<PropertyOverrideConnection>()
+		.register::<MergeResult>()
+		.register::<MergeConflict>()
+		.register::<PatchReport>()
+		.register::<PatchOperationReport>()
+		.register::<PatchOperationStatus>()
+		.register::<FloatTolerance>()
+		.register::<PatchGenOptions>()
+		.register::<DiffAlgorithm>();
+
+	// Numbers in entities are read by JSON.parse, so they're numbers whatever their Rust type
+	Typescript::new()
+		.bigint(BigIntExportBehavior::Number)
+		.export(&types)
+		.context("Couldn't export types")?
+}
@@ -0,0 +1,379 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_value, Value};
+use specta::Type;
+
+use crate::hash::hashes_match;
+use crate::normalise_entity_id;
+use crate::qn_structs::{Entity, FullRef, Property, Ref, RefMaybeConstantValue, SimpleProperty};
+
+#[cfg(feature = "rune")]
+pub fn rune_module() -> Result<rune::Module, rune::ContextError> {
+	let mut module = rune::Module::with_crate_item("quickentity_rs", ["validation"])?;
+
+	module.ty::<Diagnostic>()?;
+
+	Ok(module)
+}
+
+/// A problem found when validating an entity.
+#[cfg_attr(feature = "rune", serde_with::apply(_ => #[rune(get, set)]))]
+#[cfg_attr(feature = "rune", derive(better_rune_derive::Any))]
+#[cfg_attr(feature = "rune", rune(item = ::quickentity_rs::validation))]
+#[cfg_attr(feature = "rune", rune_derive(DEBUG_FMT))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+pub struct Diagnostic {
+	/// A JSON pointer to the problematic value in the entity JSON.
+	pub path: String,
+
+	/// A description of the problem.
+	pub message: String
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.path, self.message)
+	}
+}
+
+type Pins = IndexMap<String, IndexMap<String, Vec<RefMaybeConstantValue>>>;
+
+impl Entity {
+	/// Check the entity for problems that would cause conversion to fail, such as references to sub-entities that don't exist or property values that can't be parsed.
+	///
+	/// Unlike conversion, this doesn't stop at the first problem; every problem found is returned.
+	pub fn validate(&self) -> Vec<Diagnostic> {
+		let mut validator = Validator {
+			entity_ids: self
+				.entities
+				.keys()
+				.filter_map(|x| normalise_entity_id(x).ok())
+				.collect(),
+			external_scenes: &self.external_scenes,
+			diagnostics: vec![]
+		};
+
+		validator.check_local_id("/rootEntity", &self.root_entity);
+
+		for (entity_id, sub_entity) in &self.entities {
+			let path = pointer("/entities", entity_id);
+
+			if !is_valid_entity_id(entity_id) {
+				validator.report(&path, format!("Malformed entity ID: {}", entity_id));
+			}
+
+			validator.check_ref(&format!("{}/parent", path), &sub_entity.parent);
+
+			for (name, property) in sub_entity.properties.iter().flatten() {
+				validator.check_property(&pointer(&format!("{}/properties", path), name), property);
+			}
+
+			for (platform, properties) in sub_entity.platform_specific_properties.iter().flatten() {
+				let path = pointer(&format!("{}/platformSpecificProperties", path), platform);
+
+				for (name, property) in properties {
+					validator.check_property(&pointer(&path, name), property);
+				}
+			}
+
+			validator.check_pins(&format!("{}/events", path), sub_entity.events.as_ref());
+			validator.check_pins(&format!("{}/inputCopying", path), sub_entity.input_copying.as_ref());
+			validator.check_pins(&format!("{}/outputCopying", path), sub_entity.output_copying.as_ref());
+
+			for (alias, aliases) in sub_entity.property_aliases.iter().flatten() {
+				let path = pointer(&format!("{}/propertyAliases", path), alias);
+
+				for (index, alias) in aliases.iter().enumerate() {
+					validator.check_ref(&format!("{}/{}/originalEntity", path, index), &alias.original_entity);
+				}
+			}
+
+			for (name, exposed_entity) in sub_entity.exposed_entities.iter().flatten() {
+				let path = pointer(&format!("{}/exposedEntities", path), name);
+
+				for (index, reference) in exposed_entity.refers_to.iter().enumerate() {
+					validator.check_ref(&format!("{}/refersTo/{}", path, index), reference);
+				}
+			}
+
+			for (interface, implementor) in sub_entity.exposed_interfaces.iter().flatten() {
+				validator.check_local_id(&pointer(&format!("{}/exposedInterfaces", path), interface), implementor);
+			}
+
+			for (subset, entities) in sub_entity.subsets.iter().flatten() {
+				let path = pointer(&format!("{}/subsets", path), subset);
+
+				for (index, entity_id) in entities.iter().enumerate() {
+					validator.check_local_id(&format!("{}/{}", path, index), entity_id);
+				}
+			}
+		}
+
+		for (index, property_override) in self.property_overrides.iter().enumerate() {
+			let path = format!("/propertyOverrides/{}", index);
+
+			for (entity_index, reference) in property_override.entities.iter().enumerate() {
+				validator.check_ref(&format!("{}/entities/{}", path, entity_index), reference);
+			}
+
+			for (name, property) in &property_override.properties {
+				validator.check_value(
+					&format!("{}/value", pointer(&format!("{}/properties", path), name)),
+					&property.property_type,
+					&property.value
+				);
+			}
+		}
+
+		for (index, reference) in self.override_deletes.iter().enumerate() {
+			validator.check_ref(&format!("/overrideDeletes/{}", index), reference);
+		}
+
+		for (index, pin_connection_override) in self.pin_connection_overrides.iter().enumerate() {
+			let path = format!("/pinConnectionOverrides/{}", index);
+
+			validator.check_ref(&format!("{}/fromEntity", path), &pin_connection_override.from_entity);
+			validator.check_ref(&format!("{}/toEntity", path), &pin_connection_override.to_entity);
+
+			if let Some(value) = pin_connection_override.value.as_ref() {
+				validator.check_simple_property(&format!("{}/value", path), value);
+			}
+		}
+
+		for (index, pin_connection_override_delete) in self.pin_connection_override_deletes.iter().enumerate() {
+			let path = format!("/pinConnectionOverrideDeletes/{}", index);
+
+			validator.check_ref(
+				&format!("{}/fromEntity", path),
+				&pin_connection_override_delete.from_entity
+			);
+			validator.check_ref(&format!("{}/toEntity", path), &pin_connection_override_delete.to_entity);
+
+			if let Some(value) = pin_connection_override_delete.value.as_ref() {
+				validator.check_simple_property(&format!("{}/value", path), value);
+			}
+		}
+
+		for (index, comment) in self.comments.iter().enumerate() {
+			validator.check_ref(&format!("/comments/{}/parent", index), &comment.parent);
+		}
+
+		validator.diagnostics
+	}
+}
+
+struct Validator<'a> {
+	/// The normalised IDs of the entity's sub-entities.
+	entity_ids: HashSet<String>,
+
+	external_scenes: &'a [String],
+
+	diagnostics: Vec<Diagnostic>
+}
+
+impl Validator<'_> {
+	fn report(&mut self, path: &str, message: impl Into<String>) {
+		self.diagnostics.push(Diagnostic {
+			path: path.to_owned(),
+			message: message.into()
+		});
+	}
+
+	fn check_local_id(&mut self, path: &str, entity_id: &str) {
+		if !is_valid_entity_id(entity_id) {
+			self.report(path, format!("Malformed entity ID: {}", entity_id));
+		} else if !normalise_entity_id(entity_id).is_ok_and(|x| self.entity_ids.contains(&x)) {
+			self.report(path, format!("Reference to nonexistent entity: {}", entity_id));
+		}
+	}
+
+	fn check_ref(&mut self, path: &str, reference: &Ref) {
+		match reference {
+			Ref::Short(None) => {}
+
+			Ref::Short(Some(entity_id)) => self.check_local_id(path, entity_id),
+
+			Ref::Full(FullRef {
+				entity_ref,
+				external_scene: None,
+				..
+			}) => self.check_local_id(&format!("{}/ref", path), entity_ref),
+
+			Ref::Full(FullRef {
+				entity_ref,
+				external_scene: Some(external_scene),
+				..
+			}) => {
+				if !is_valid_entity_id(entity_ref) {
+					self.report(&format!("{}/ref", path), format!("Malformed entity ID: {}", entity_ref));
+				}
+
+				if !self.external_scenes.iter().any(|x| hashes_match(x, external_scene)) {
+					self.report(
+						&format!("{}/externalScene", path),
+						format!("Reference to external scene not in externalScenes: {}", external_scene)
+					);
+				}
+			}
+		}
+	}
+
+	fn check_pins(&mut self, path: &str, pins: Option<&Pins>) {
+		for (pin, targets) in pins.into_iter().flatten() {
+			let path = pointer(path, pin);
+
+			for (target_pin, references) in targets {
+				let path = pointer(&path, target_pin);
+
+				for (index, reference) in references.iter().enumerate() {
+					let path = format!("{}/{}", path, index);
+
+					match reference {
+						RefMaybeConstantValue::Ref(reference) => self.check_ref(&path, reference),
+
+						RefMaybeConstantValue::RefWithConstantValue(reference) => {
+							self.check_ref(&format!("{}/ref", path), &reference.entity_ref);
+							self.check_simple_property(&format!("{}/value", path), &reference.value);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	fn check_property(&mut self, path: &str, property: &Property) {
+		self.check_value(&format!("{}/value", path), &property.property_type, &property.value);
+	}
+
+	fn check_simple_property(&mut self, path: &str, property: &SimpleProperty) {
+		self.check_value(&format!("{}/value", path), &property.property_type, &property.value);
+	}
+
+	fn check_value(&mut self, path: &str, property_type: &str, value: &Value) {
+		if let Some(element_type) = property_type.strip_prefix("TArray<").and_then(|x| x.strip_suffix('>')) {
+			if let Some(elements) = value.as_array() {
+				for (index, element) in elements.iter().enumerate() {
+					self.check_value(&format!("{}/{}", path, index), element_type, element);
+				}
+			} else {
+				self.report(path, format!("{} value must be an array", property_type));
+			}
+
+			return;
+		}
+
+		match property_type {
+			"SEntityTemplateReference" => match from_value::<Ref>(value.to_owned()) {
+				Ok(reference) => self.check_ref(path, &reference),
+				Err(_) => self.report(path, "SEntityTemplateReference value must be a reference")
+			},
+
+			"ZGuid" => {
+				if !value.as_str().is_some_and(is_valid_guid) {
+					self.report(path, format!("Invalid ZGuid: {}", value));
+				}
+			}
+
+			"SColorRGB" | "SColorRGBA" => {
+				if let Some(obj) = value.as_object() {
+					let channels: &[&str] = if property_type == "SColorRGB" {
+						&["r", "g", "b"]
+					} else {
+						&["r", "g", "b", "a"]
+					};
+
+					for channel in channels {
+						if !obj.get(*channel).is_some_and(|x| x.is_number()) {
+							self.report(path, format!("{} must have a numeric {} value", property_type, channel));
+						}
+					}
+
+					return;
+				}
+
+				let digits = if property_type == "SColorRGB" { 6 } else { 8 };
+
+				if !value
+					.as_str()
+					.and_then(|x| x.strip_prefix('#'))
+					.is_some_and(|x| x.len() == digits && x.chars().all(|x| x.is_ascii_hexdigit()))
+				{
+					self.report(path, format!("Invalid {}: {}", property_type, value));
+				}
+			}
+
+			"SMatrix43" => {
+				if let Some(obj) = value.as_object() {
+					let components: &[(&str, &[&str], bool)] = if obj.contains_key("XAxis") {
+						&[
+							("XAxis", &["x", "y", "z"], true),
+							("YAxis", &["x", "y", "z"], true),
+							("ZAxis", &["x", "y", "z"], true),
+							("Trans", &["x", "y", "z"], true)
+						]
+					} else if obj.contains_key("quaternion") {
+						&[
+							("quaternion", &["x", "y", "z", "w"], true),
+							("position", &["x", "y", "z"], true),
+							("scale", &["x", "y", "z"], false)
+						]
+					} else {
+						&[
+							("rotation", &["x", "y", "z"], true),
+							("position", &["x", "y", "z"], true),
+							("scale", &["x", "y", "z"], false)
+						]
+					};
+
+					for &(component, axes, required) in components {
+						match obj.get(component) {
+							Some(vector) => {
+								for axis in axes {
+									if !vector.get(axis).is_some_and(|x| x.is_number()) {
+										self.report(
+											&format!("{}/{}", path, component),
+											format!("SMatrix43 {} must have a numeric {} value", component, axis)
+										);
+									}
+								}
+							}
+
+							None if required => {
+								self.report(path, format!("SMatrix43 is missing {}", component));
+							}
+
+							None => {}
+						}
+					}
+				} else {
+					self.report(path, "SMatrix43 value must be an object");
+				}
+			}
+
+			_ => {}
+		}
+	}
+}
+
+/// Whether an entity ID can be parsed as hex, as it must be to be converted.
+fn is_valid_entity_id(entity_id: &str) -> bool {
+	u64::from_str_radix(entity_id, 16).is_ok()
+}
+
+fn is_valid_guid(guid: &str) -> bool {
+	let groups = guid.split('-').collect::<Vec<_>>();
+
+	groups.len() == 5
+		&& groups
+			.iter()
+			.zip([8, 4, 4, 4, 12])
+			.all(|(group, len)| group.len() == len && group.chars().all(|x| x.is_ascii_hexdigit()))
+}
+
+/// Append a key to a JSON pointer, escaping it as necessary.
+pub(crate) fn pointer(path: &str, key: &str) -> String {
+	format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
@@ -0,0 +1,131 @@
+use quickentity_rs::error::QuickEntityError;
+use quickentity_rs::io::read_entity_from_slice;
+use quickentity_rs::patch_structs::{ArrayPatchOperation, DiffAlgorithm, PatchGenOptions};
+use quickentity_rs::qn_structs::Entity;
+use quickentity_rs::{apply_array_patch, apply_patch, generate_patch_with, invert_patch};
+use serde_json::{json, Value};
+
+const ENTITY: &[u8] = include_bytes!("data/binary_entity.json");
+
+const ROOT: &str = "fffffffffffffffe";
+
+/// Pairs of arrays to diff, most with repeated items.
+const ARRAYS: &[(&str, &str)] = &[
+	("[1, 2, 2, 3]", "[2, 2, 1, 3]"),
+	("[1, 2, 2, 3]", "[2, 1, 2]"),
+	("[1, 2, 2, 3]", "[1, 2, 2, 2, 2]"),
+	("[1, 2, 2, 3]", "[3, 2, 2, 1]"),
+	("[1, 2, 2, 3]", "[]"),
+	("[]", "[5, 5]"),
+	("[2, 2, 2]", "[2]"),
+	("[2]", "[2, 2, 2]"),
+	("[1, 1, 2, 2]", "[2, 2, 1, 1]"),
+	("[1, 2, 1, 2, 1, 2]", "[2, 1, 2, 1]"),
+	("[1, 2, 3, 1, 2, 3]", "[1, 2, 3, 4, 1, 2, 3]")
+];
+
+/// The result of applying an array patch to [1, 2, 2, 3].
+fn patched(operations: Value, permissive: bool) -> Result<Value, QuickEntityError> {
+	let mut array = json!([1, 2, 2, 3]);
+	let operations: Vec<ArrayPatchOperation> = serde_json::from_value(operations).unwrap();
+
+	apply_array_patch(&mut array, operations, permissive, false)?;
+
+	Ok(array)
+}
+
+/// The test entity with its root's m_aValues set to the given array.
+fn entity_with_values(values: &str) -> Entity {
+	let mut entity = read_entity_from_slice(ENTITY).unwrap();
+
+	entity.entities[ROOT].properties.as_mut().unwrap()["m_aValues"].value = serde_json::from_str(values).unwrap();
+
+	entity
+}
+
+#[test]
+fn insertions_are_anchored_to_occurrences() {
+	for (operations, expected) in [
+		(
+			json!([{ "InsertItemAfterOccurrence": [null, 9] }]),
+			json!([9, 1, 2, 2, 3])
+		),
+		(
+			json!([{ "InsertItemAfterOccurrence": [[2, 0], 9] }]),
+			json!([1, 2, 9, 2, 3])
+		),
+		(
+			json!([{ "InsertItemAfterOccurrence": [[2, 1], 9] }]),
+			json!([1, 2, 2, 9, 3])
+		),
+		(
+			json!([{ "InsertItemAfterOccurrence": [[3, 0], 9] }]),
+			json!([1, 2, 2, 3, 9])
+		),
+		(json!([{ "SetItemOccurrence": [2, 0, 9] }]), json!([1, 9, 2, 3])),
+		(json!([{ "SetItemOccurrence": [2, 1, 9] }]), json!([1, 2, 9, 3])),
+		(json!([{ "RemoveItemOccurrence": [2, 1] }]), json!([1, 2, 3])),
+		(
+			json!([
+				{ "InsertItemAfterOccurrence": [[2, 1], 2] },
+				{ "SetItemOccurrence": [2, 2, 9] }
+			]),
+			json!([1, 2, 2, 9, 3])
+		)
+	] {
+		assert_eq!(
+			patched(operations.to_owned(), false).unwrap(),
+			expected,
+			"{}",
+			operations
+		);
+	}
+}
+
+#[test]
+fn missing_occurrences_are_errors() {
+	for operations in [
+		json!([{ "InsertItemAfterOccurrence": [[2, 2], 9] }]),
+		json!([{ "InsertItemAfterOccurrence": [[4, 0], 9] }]),
+		json!([{ "SetItemOccurrence": [2, 2, 9] }]),
+		json!([{ "RemoveItemOccurrence": [3, 1] }])
+	] {
+		let error = patched(operations.to_owned(), false).unwrap_err();
+
+		assert!(
+			matches!(error.kind(), QuickEntityError::ArrayItemNotFound { .. }),
+			"{} gave {:?}",
+			operations,
+			error
+		);
+	}
+
+	assert_eq!(
+		patched(json!([{ "InsertItemAfterOccurrence": [[2, 2], 9] }]), true).unwrap(),
+		json!([1, 2, 2, 3, 9])
+	);
+}
+
+#[test]
+fn repeated_items_are_diffed_correctly() {
+	for algorithm in [DiffAlgorithm::Myers, DiffAlgorithm::Patience, DiffAlgorithm::Lcs] {
+		let options = PatchGenOptions {
+			algorithm,
+			..Default::default()
+		};
+
+		for (old, new) in ARRAYS {
+			let original = entity_with_values(old);
+			let modified = entity_with_values(new);
+
+			let patch = generate_patch_with(&original, &modified, &options).unwrap();
+
+			let mut patched = original.to_owned();
+			apply_patch(&mut patched, patch.to_owned(), false).unwrap();
+			assert_eq!(patched, modified, "{:?} patching {} to {}", algorithm, old, new);
+
+			apply_patch(&mut patched, invert_patch(&original, &patch).unwrap(), false).unwrap();
+			assert_eq!(patched, original, "{:?} inverting {} to {}", algorithm, old, new);
+		}
+	}
+}
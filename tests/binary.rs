@@ -0,0 +1,251 @@
+use hitman_commons::resourcelib::{
+	EntityBlueprint, EntityFactory, PlatformSpecificProperty, Property, PropertyID, PropertyValue
+};
+use quickentity_rs::binary::{
+	read_binary_blueprint, read_binary_factory, write_binary_blueprint, write_binary_factory
+};
+use quickentity_rs::convert_to_rt;
+use quickentity_rs::meta::{read_binary_meta, write_binary_meta};
+use quickentity_rs::qn_structs::{ConvertOptions, Entity};
+use serde_json::{from_slice, to_value, Value};
+
+const ENTITY: &[u8] = include_bytes!("data/binary_entity.json");
+const FACTORY: &[u8] = include_bytes!("data/factory.temp");
+const FACTORY_JSON: &[u8] = include_bytes!("data/factory.json");
+const BLUEPRINT: &[u8] = include_bytes!("data/blueprint.tblu");
+const BLUEPRINT_JSON: &[u8] = include_bytes!("data/blueprint.json");
+
+/// Turn whole floats into integers, since floats read from the binary aren't equal to the integers in the JSON.
+fn normalise_numbers(value: Value) -> Value {
+	match value {
+		Value::Number(number) if number.is_f64() && number.as_f64().unwrap().fract() == 0.0 => {
+			(number.as_f64().unwrap() as i64).into()
+		}
+
+		Value::Array(items) => items.into_iter().map(normalise_numbers).collect(),
+
+		Value::Object(fields) => fields
+			.into_iter()
+			.map(|(key, value)| (key, normalise_numbers(value)))
+			.collect(),
+
+		value => value
+	}
+}
+
+#[test]
+fn factory_reads() {
+	assert_eq!(
+		normalise_numbers(to_value(read_binary_factory(FACTORY).unwrap()).unwrap()),
+		normalise_numbers(to_value(from_slice::<EntityFactory>(FACTORY_JSON).unwrap()).unwrap())
+	);
+}
+
+#[test]
+fn blueprint_reads() {
+	assert_eq!(
+		normalise_numbers(to_value(read_binary_blueprint(BLUEPRINT).unwrap()).unwrap()),
+		normalise_numbers(to_value(from_slice::<EntityBlueprint>(BLUEPRINT_JSON).unwrap()).unwrap())
+	);
+}
+
+#[test]
+fn factory_round_trips() {
+	let factory: EntityFactory = from_slice(FACTORY_JSON).unwrap();
+
+	assert_eq!(write_binary_factory(&factory).unwrap(), FACTORY);
+}
+
+#[test]
+fn blueprint_round_trips() {
+	let blueprint: EntityBlueprint = from_slice(BLUEPRINT_JSON).unwrap();
+
+	assert_eq!(write_binary_blueprint(&blueprint).unwrap(), BLUEPRINT);
+}
+
+#[test]
+fn converted_entity_writes() {
+	let entity: Entity = from_slice(ENTITY).unwrap();
+
+	let (factory, _, blueprint, _) = convert_to_rt(&entity, &ConvertOptions::default()).unwrap();
+
+	// Properties named rather than given by ID are written by the hash of their name
+	assert_eq!(write_binary_factory(&factory).unwrap(), FACTORY);
+	assert_eq!(write_binary_blueprint(&blueprint).unwrap(), BLUEPRINT);
+}
+
+#[test]
+fn converted_metas_have_sizes() {
+	let entity: Entity = from_slice(ENTITY).unwrap();
+
+	let (_, factory_meta, _, blueprint_meta) = convert_to_rt(&entity, &ConvertOptions::default()).unwrap();
+
+	// The data follows the 16 byte header, and its length is the size in memory
+	for (meta, resource) in [(factory_meta, FACTORY), (blueprint_meta, BLUEPRINT)] {
+		assert_eq!(meta.hash_size_final as usize, resource.len());
+		assert_eq!(
+			meta.hash_size_in_memory,
+			u32::from_be_bytes(resource[8..12].try_into().unwrap())
+		);
+		assert_eq!(meta.hash_offset, 0);
+		assert_eq!(meta.hash_size, 0);
+	}
+}
+
+/// A property with the given type and value.
+fn property(name: &str, property_type: &str, value: Value) -> Property {
+	Property {
+		n_property_id: PropertyID::String(name.into()),
+		value: PropertyValue {
+			property_type: property_type.into(),
+			property_value: value
+		}
+	}
+}
+
+/// Write a factory and read it back, checking that its property values and platform-specific properties are unchanged.
+fn assert_factory_round_trips(factory: &EntityFactory) {
+	let read = read_binary_factory(&write_binary_factory(factory).unwrap()).unwrap();
+
+	// Properties are read back by ID rather than by name, so only their values are compared
+	for (read, written) in read.sub_entities.iter().zip(&factory.sub_entities) {
+		assert_eq!(
+			normalise_numbers(to_value(read.property_values.iter().map(|x| &x.value).collect::<Vec<_>>()).unwrap()),
+			normalise_numbers(to_value(written.property_values.iter().map(|x| &x.value).collect::<Vec<_>>()).unwrap())
+		);
+
+		assert_eq!(
+			read.platform_specific_property_values
+				.iter()
+				.map(|x| (
+					&x.platform,
+					x.post_init,
+					normalise_numbers(to_value(&x.property_value.value).unwrap())
+				))
+				.collect::<Vec<_>>(),
+			written
+				.platform_specific_property_values
+				.iter()
+				.map(|x| (
+					&x.platform,
+					x.post_init,
+					normalise_numbers(to_value(&x.property_value.value).unwrap())
+				))
+				.collect::<Vec<_>>()
+		);
+	}
+}
+
+#[test]
+fn enums_round_trip() {
+	let mut factory: EntityFactory = from_slice(FACTORY_JSON).unwrap();
+
+	factory.sub_entities[0]
+		.property_values
+		.push(property("m_eType", "ZHM5Item.EItemType", 3.into()));
+
+	factory.sub_entities[0].property_values.push(property(
+		"m_aTypes",
+		"TArray<EDamageResponse>",
+		vec![-1, 0, 7].into()
+	));
+
+	assert_factory_round_trips(&factory);
+}
+
+#[test]
+fn enum_names_are_rejected() {
+	let mut factory: EntityFactory = from_slice(FACTORY_JSON).unwrap();
+
+	factory.sub_entities[0]
+		.property_values
+		.push(property("m_eType", "ETestType", "Value".into()));
+
+	assert!(write_binary_factory(&factory).is_err());
+}
+
+#[test]
+fn platform_specific_properties_round_trip() {
+	let mut factory: EntityFactory = from_slice(FACTORY_JSON).unwrap();
+
+	factory.sub_entities[0].platform_specific_property_values = vec![
+		PlatformSpecificProperty {
+			property_value: property("m_bEnabled", "bool", false.into()),
+			platform: "2".into(),
+			post_init: false
+		},
+		PlatformSpecificProperty {
+			property_value: property("m_fValue", "float32", 0.5.into()),
+			platform: "5".into(),
+			post_init: true
+		},
+	];
+
+	assert_factory_round_trips(&factory);
+}
+
+#[test]
+fn named_platforms_are_rejected() {
+	let mut factory: EntityFactory = from_slice(FACTORY_JSON).unwrap();
+
+	factory.sub_entities[0].platform_specific_property_values = vec![PlatformSpecificProperty {
+		property_value: property("m_bEnabled", "bool", false.into()),
+		platform: "PS4".into(),
+		post_init: false
+	}];
+
+	assert!(write_binary_factory(&factory).is_err());
+}
+
+#[test]
+fn unsupported_types_are_rejected() {
+	let mut factory: EntityFactory = from_slice(FACTORY_JSON).unwrap();
+
+	factory.sub_entities[0]
+		.property_values
+		.push(property("m_curve", "ZCurve", Value::Null));
+
+	assert!(write_binary_factory(&factory).is_err());
+}
+
+#[test]
+fn truncated_resources_are_rejected() {
+	assert!(read_binary_factory(&FACTORY[..FACTORY.len() / 2]).is_err());
+	assert!(read_binary_blueprint(b"BIN1").is_err());
+	assert!(read_binary_blueprint(BLUEPRINT_JSON).is_err());
+}
+
+/// Round-trip every TEMP, TBLU and meta in the directory named by `QUICKENTITY_GAME_RESOURCES` byte for byte.
+///
+/// Resources extracted from the game can't be distributed with QuickEntity, so this is ignored by default; extract some (with their metas) and run it with `cargo test -- --ignored`.
+#[test]
+#[ignore]
+fn game_resources_round_trip() {
+	let directory = std::env::var("QUICKENTITY_GAME_RESOURCES")
+		.expect("QUICKENTITY_GAME_RESOURCES must be set to a directory of resources extracted from the game");
+
+	let mut checked = 0;
+
+	for entry in std::fs::read_dir(directory).unwrap() {
+		let path = entry.unwrap().path();
+		let data = std::fs::read(&path).unwrap();
+
+		let written = match path
+			.extension()
+			.and_then(|x| x.to_str())
+			.map(|x| x.to_lowercase())
+			.as_deref()
+		{
+			Some("temp") => write_binary_factory(&read_binary_factory(&data).unwrap()).unwrap(),
+			Some("tblu") => write_binary_blueprint(&read_binary_blueprint(&data).unwrap()).unwrap(),
+			Some("meta") => write_binary_meta(&read_binary_meta(&data).unwrap()).unwrap(),
+			_ => continue
+		};
+
+		assert!(written == data, "{} didn't round trip", path.display());
+
+		checked += 1;
+	}
+
+	assert_ne!(checked, 0, "No resources were found");
+}
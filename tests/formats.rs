@@ -0,0 +1,126 @@
+use quickentity_rs::io::{
+	from_slice_in_format, read_entity_from_slice, read_patch_from_slice, to_vec_canonical, to_vec_in_format, Format
+};
+use quickentity_rs::qn_structs::{
+	Dependency, DependencyWithFlag, FullRef, Ref, RefMaybeConstantValue, RefWithConstantValue, SimpleProperty
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+const ENTITY: &[u8] = include_bytes!("data/entity.json");
+const PATCH: &[u8] = include_bytes!("data/patch.json");
+
+/// Convert a value to another format and back, checking that it's unchanged and gives the same JSON.
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug>(value: &T, format: Format) {
+	let data = to_vec_in_format(value, format).unwrap();
+
+	let read: T = from_slice_in_format(&data, format).unwrap_or_else(|error| {
+		panic!(
+			"{:?} didn't read back: {}\n{}",
+			format,
+			error,
+			String::from_utf8_lossy(&data)
+		)
+	});
+
+	assert_eq!(&read, value, "{:?} changed the value", format);
+	assert_eq!(
+		String::from_utf8(to_vec_canonical(&read).unwrap()).unwrap(),
+		String::from_utf8(to_vec_canonical(value).unwrap()).unwrap(),
+		"{:?} changed the JSON",
+		format
+	);
+}
+
+#[test]
+fn entity_round_trips() {
+	let entity = read_entity_from_slice(ENTITY).unwrap();
+
+	for format in [Format::Yaml, Format::Ron] {
+		round_trip(&entity, format);
+	}
+}
+
+#[test]
+fn patch_round_trips() {
+	let patch = read_patch_from_slice(PATCH).unwrap();
+
+	for format in [Format::Yaml, Format::Ron] {
+		round_trip(&patch, format);
+	}
+}
+
+#[test]
+fn untagged_types_round_trip() {
+	let refs = vec![
+		Ref::Short(None),
+		Ref::Short(Some("abcdef0123456789".into())),
+		Ref::Full(FullRef {
+			entity_ref: "abcdef0123456789".into(),
+			external_scene: None,
+			exposed_entity: None
+		}),
+		Ref::Full(FullRef {
+			entity_ref: "0123456789abcdef".into(),
+			external_scene: Some("[assembly:/_pro/scenes/other.entity].entitytemplate".into()),
+			exposed_entity: Some("Target".into())
+		}),
+	];
+
+	let dependencies = vec![
+		Dependency::Short("00EEEEEEEEEEEEEE".into()),
+		Dependency::Full(DependencyWithFlag {
+			resource: "00FFFFFFFFFFFFFF".into(),
+			flag: "9F".into()
+		}),
+	];
+
+	let values = vec![
+		RefMaybeConstantValue::Ref(Ref::Short(None)),
+		RefMaybeConstantValue::Ref(refs[3].to_owned()),
+		RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
+			entity_ref: refs[1].to_owned(),
+			value: SimpleProperty {
+				property_type: "float32".into(),
+				value: 0.5.into()
+			}
+		}),
+		RefMaybeConstantValue::RefWithConstantValue(RefWithConstantValue {
+			entity_ref: refs[2].to_owned(),
+			value: SimpleProperty {
+				property_type: "ZString".into(),
+				value: "".into()
+			}
+		}),
+	];
+
+	for format in [Format::Json, Format::Yaml, Format::Ron] {
+		round_trip(&refs, format);
+		round_trip(&dependencies, format);
+		round_trip(&values, format);
+	}
+}
+
+#[test]
+fn yaml_and_ron_are_canonical() {
+	let entity = read_entity_from_slice(ENTITY).unwrap();
+
+	let mut reordered = entity.to_owned();
+	reordered.entities.reverse();
+
+	for sub_entity in reordered.entities.values_mut() {
+		for property in sub_entity.properties.iter_mut().flat_map(|x| x.values_mut()) {
+			if let Some(x) = property.value.as_i64() {
+				property.value = (x as f64).into();
+			}
+		}
+	}
+
+	for format in [Format::Yaml, Format::Ron] {
+		assert_eq!(
+			String::from_utf8(to_vec_in_format(&reordered, format).unwrap()).unwrap(),
+			String::from_utf8(to_vec_in_format(&entity, format).unwrap()).unwrap(),
+			"{:?} isn't canonical",
+			format
+		);
+	}
+}
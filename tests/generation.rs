@@ -0,0 +1,329 @@
+use quickentity_rs::io::read_entity_from_slice;
+use quickentity_rs::patch_structs::{DiffAlgorithm, FloatTolerance, Patch, PatchGenOptions};
+use quickentity_rs::qn_structs::Entity;
+use quickentity_rs::{apply_patch, generate_patch_with};
+use serde_json::{json, Value};
+
+const ENTITY: &[u8] = include_bytes!("data/binary_entity.json");
+
+fn entity() -> Entity {
+	read_entity_from_slice(ENTITY).unwrap()
+}
+
+/// The test entity with part of its JSON, given as a JSON pointer, set to a value.
+fn edited(entity: &Entity, pointer: &str, value: Value) -> Entity {
+	let mut json = serde_json::to_value(entity).unwrap();
+	*json.pointer_mut(pointer).unwrap() = value;
+	serde_json::from_value(json).unwrap()
+}
+
+/// The names of the operations in a patch, giving the inner operation of sub-entity operations.
+fn operation_names(patch: &Patch) -> Vec<String> {
+	serde_json::to_value(&patch.patch)
+		.unwrap()
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|x| {
+			let (name, operation) = x.as_object().unwrap().iter().next().unwrap();
+
+			match operation.pointer("/1").and_then(|x| x.as_object()) {
+				Some(sub_entity_operation) if name == "SubEntityOperation" => {
+					sub_entity_operation.keys().next().unwrap().to_owned()
+				}
+
+				_ => name.to_owned()
+			}
+		})
+		.collect()
+}
+
+/// Options with the given tolerance.
+fn with_tolerance(tolerance: FloatTolerance) -> PatchGenOptions {
+	PatchGenOptions {
+		tolerance,
+		..Default::default()
+	}
+}
+
+const ROOT_PROPERTIES: &str = "/entities/fffffffffffffffe/properties";
+
+#[test]
+fn values_within_the_tolerance_are_unchanged() {
+	let options = with_tolerance(FloatTolerance {
+		value: 0.001,
+		..Default::default()
+	});
+
+	for (pointer, value, changed) in [
+		("/m_fPrecise/value", json!(0.1004), json!(0.11)),
+		("/m_aValues/value/3", json!(3.2496), json!(3.3)),
+		("/m_vPosition/value/z", json!(1000000.0009), json!(1000000.01))
+	] {
+		let pointer = format!("{}{}", ROOT_PROPERTIES, pointer);
+
+		assert_eq!(
+			operation_names(&generate_patch_with(&entity(), &edited(&entity(), &pointer, value), &options).unwrap()),
+			Vec::<String>::new(),
+			"{}",
+			pointer
+		);
+
+		assert_ne!(
+			operation_names(&generate_patch_with(&entity(), &edited(&entity(), &pointer, changed), &options).unwrap()),
+			Vec::<String>::new(),
+			"{}",
+			pointer
+		);
+	}
+}
+
+#[test]
+fn constant_values_within_the_tolerance_are_unchanged() {
+	let options = with_tolerance(FloatTolerance {
+		value: 0.001,
+		..Default::default()
+	});
+
+	for (pointer, value, changed) in [
+		(
+			"/entities/fffffffffffffffe/events/OnX/DoY/1/value/value",
+			json!(0.5004),
+			json!(0.6)
+		),
+		("/pinConnectionOverrides/0/value/value", json!(-7.0004), json!(-8)),
+		(
+			"/propertyOverrides/0/properties/m_bVisible",
+			json!({ "type": "float32", "value": 0.0004 }),
+			json!({ "type": "float32", "value": 0.1 })
+		)
+	] {
+		// Property overrides are compared as a whole, so both entities need a numeric override
+		let original = if pointer.starts_with("/propertyOverrides") {
+			edited(&entity(), pointer, json!({ "type": "float32", "value": 0 }))
+		} else {
+			entity()
+		};
+
+		assert_eq!(
+			operation_names(&generate_patch_with(&original, &edited(&original, pointer, value), &options).unwrap()),
+			Vec::<String>::new(),
+			"{}",
+			pointer
+		);
+
+		assert_ne!(
+			operation_names(&generate_patch_with(&original, &edited(&original, pointer, changed), &options).unwrap()),
+			Vec::<String>::new(),
+			"{}",
+			pointer
+		);
+	}
+}
+
+#[test]
+fn transforms_use_their_own_tolerances() {
+	let tolerance = FloatTolerance {
+		value: 0.0,
+		position: 0.01,
+		rotation: 0.1,
+		scale: 0.001
+	};
+
+	let transform = format!("{}/m_mTransform/value", ROOT_PROPERTIES);
+
+	for (value, within) in [
+		(
+			json!({ "rotation": { "x": 0, "y": 0, "z": 90 }, "position": { "x": 1.505, "y": 2, "z": -3 } }),
+			true
+		),
+		(
+			json!({ "rotation": { "x": 0, "y": 0, "z": 90 }, "position": { "x": 1.52, "y": 2, "z": -3 } }),
+			false
+		),
+		(
+			json!({ "rotation": { "x": 0.05, "y": 360, "z": -269.95 }, "position": { "x": 1.5, "y": 2, "z": -3 } }),
+			true
+		),
+		(
+			json!({ "rotation": { "x": 0, "y": 0, "z": 90.2 }, "position": { "x": 1.5, "y": 2, "z": -3 } }),
+			false
+		),
+		(
+			json!({
+				"rotation": { "x": 0, "y": 0, "z": 90 },
+				"position": { "x": 1.5, "y": 2, "z": -3 },
+				"scale": { "x": 1, "y": 1.0005, "z": 1 }
+			}),
+			true
+		),
+		(
+			json!({
+				"rotation": { "x": 0, "y": 0, "z": 90 },
+				"position": { "x": 1.5, "y": 2, "z": -3 },
+				"scale": { "x": 1, "y": 1.01, "z": 1 }
+			}),
+			false
+		)
+	] {
+		let patch = generate_patch_with(
+			&entity(),
+			&edited(&entity(), &transform, value.to_owned()),
+			&with_tolerance(tolerance)
+		)
+		.unwrap();
+
+		assert_eq!(patch.patch.is_empty(), within, "{}", value);
+	}
+}
+
+/// The names of the operations in the patch between the test entity and an edited copy.
+fn edit_operations(original: &Entity, pointer: &str, value: Value, options: &PatchGenOptions) -> Vec<String> {
+	operation_names(&generate_patch_with(original, &edited(original, pointer, value), options).unwrap())
+}
+
+#[test]
+fn arrays_can_be_replaced() {
+	let pointer = format!("{}/m_aValues/value", ROOT_PROPERTIES);
+
+	assert_eq!(
+		edit_operations(&entity(), &pointer, json!([1, 2, 3.25]), &Default::default()),
+		["PatchArrayPropertyValue"]
+	);
+
+	assert_eq!(
+		edit_operations(
+			&entity(),
+			&pointer,
+			json!([1, 2, 3.25]),
+			&PatchGenOptions {
+				diff_arrays: false,
+				..Default::default()
+			}
+		),
+		["SetPropertyValue"]
+	);
+}
+
+#[test]
+fn parts_of_the_entity_can_be_ignored() {
+	for (pointer, value, expected, options) in [
+		(
+			"/comments",
+			json!([{ "parent": "fffffffffffffffe", "name": "Note", "text": "Added" }]),
+			"AddComment",
+			PatchGenOptions {
+				diff_comments: false,
+				..Default::default()
+			}
+		),
+		(
+			"/extraFactoryDependencies",
+			json!(["00ABABABABABABAB"]),
+			"AddExtraFactoryDependency",
+			PatchGenOptions {
+				diff_dependencies: false,
+				..Default::default()
+			}
+		),
+		(
+			"/extraBlueprintDependencies",
+			json!(["00CDCDCDCDCDCDCD"]),
+			"AddExtraBlueprintDependency",
+			PatchGenOptions {
+				diff_dependencies: false,
+				..Default::default()
+			}
+		),
+		(
+			"/externalScenes",
+			json!([]),
+			"RemoveExternalScene",
+			PatchGenOptions {
+				diff_external_scenes: false,
+				..Default::default()
+			}
+		)
+	] {
+		assert_eq!(
+			edit_operations(&entity(), pointer, value.to_owned(), &Default::default()),
+			[expected]
+		);
+
+		assert_eq!(
+			edit_operations(&entity(), pointer, value, &options),
+			Vec::<String>::new(),
+			"{}",
+			pointer
+		);
+	}
+}
+
+#[test]
+fn entity_ids_can_be_normalised() {
+	let sub_entity = json!({
+		"parent": "fffffffffffffffe",
+		"name": "Short",
+		"factory": "00CCCCCCCCCCCCCC",
+		"blueprint": "00DDDDDDDDDDDDDD"
+	});
+
+	let mut short = serde_json::to_value(entity()).unwrap();
+	short["entities"]["ab"] = sub_entity.to_owned();
+
+	let mut long = serde_json::to_value(entity()).unwrap();
+	long["entities"]["00000000000000ab"] = sub_entity;
+
+	let (short, long): (Entity, Entity) = (
+		serde_json::from_value(short).unwrap(),
+		serde_json::from_value(long).unwrap()
+	);
+
+	assert_eq!(
+		operation_names(&generate_patch_with(&short, &long, &Default::default()).unwrap()),
+		["RemoveEntityByID", "AddEntity"]
+	);
+
+	assert_eq!(
+		operation_names(
+			&generate_patch_with(
+				&short,
+				&long,
+				&PatchGenOptions {
+					normalise_entity_ids: true,
+					..Default::default()
+				}
+			)
+			.unwrap()
+		),
+		Vec::<String>::new()
+	);
+}
+
+#[test]
+fn every_algorithm_gives_a_working_patch() {
+	let original = entity();
+	let modified = edited(
+		&original,
+		&format!("{}/m_aNames/value", ROOT_PROPERTIES),
+		json!(["abc", "", "b", "ab", "a", ""])
+	);
+
+	for algorithm in [DiffAlgorithm::Myers, DiffAlgorithm::Patience, DiffAlgorithm::Lcs] {
+		let patch = generate_patch_with(
+			&original,
+			&modified,
+			&PatchGenOptions {
+				algorithm,
+				..Default::default()
+			}
+		)
+		.unwrap();
+
+		assert_eq!(operation_names(&patch), ["PatchArrayPropertyValue"], "{:?}", algorithm);
+
+		let mut patched = original.to_owned();
+		apply_patch(&mut patched, patch, false).unwrap();
+		assert_eq!(patched, modified, "{:?}", algorithm);
+	}
+}
@@ -0,0 +1,143 @@
+use quickentity_rs::hash::{
+	apply_hash_list, hashes_match, normalise_hash, parse_hash, path_to_runtime_id, strip_annotation, strip_annotations,
+	HashList, HashListMode
+};
+use quickentity_rs::io::read_entity_from_slice;
+use quickentity_rs::qn_structs::Entity;
+
+const ENTITY: &[u8] = include_bytes!("data/binary_entity.json");
+
+const CHILD: &str = "abcdef0123456789";
+
+/// Paths and their runtime IDs, worked out independently from the MD5 of each lowercased path.
+const PATHS: &[(&str, &str)] = &[
+	("[modules:/zentity.class].pc_entitytype", "0031B66C16E8815D"),
+	(
+		"[assembly:/_pro/scenes/other.entity].entitytemplate",
+		"002DB570DFD3A2BC"
+	)
+];
+
+const HASH_LIST: &str = "
+# Test hashes
+00CCCCCCCCCCCCCC.TEMP,[assembly:/_pro/child.entity].pc_entitytype
+00EEEEEEEEEEEEEE,[assembly:/_pro/resource.entity].pc_entityresource
+00DDDDDDDDDDDDDD.TBLU,
+";
+
+fn entity() -> Entity {
+	read_entity_from_slice(ENTITY).unwrap()
+}
+
+#[test]
+fn paths_give_runtime_ids() {
+	for (path, id) in PATHS {
+		assert_eq!(path_to_runtime_id(path), *id);
+		assert_eq!(path_to_runtime_id(&path.to_uppercase()), *id);
+		assert_eq!(parse_hash(path).unwrap(), u64::from_str_radix(id, 16).unwrap());
+	}
+}
+
+#[test]
+fn paths_match_their_runtime_ids() {
+	for (path, id) in PATHS {
+		assert!(hashes_match(path, id));
+		assert!(hashes_match(id, path));
+		assert!(hashes_match(&id.to_lowercase(), path));
+		assert!(hashes_match(&format!("{} {}", id, path), path));
+		assert!(!hashes_match(path, "00CCCCCCCCCCCCCC"));
+	}
+
+	assert!(hashes_match("2DB570DFD3A2BC", "002DB570DFD3A2BC"));
+	assert!(!hashes_match(PATHS[0].0, PATHS[1].0));
+}
+
+#[test]
+fn hashes_are_normalised() {
+	for (hash, normalised) in [
+		(PATHS[0].0, PATHS[0].1),
+		("2db570dfd3a2bc", "002DB570DFD3A2BC"),
+		("00CCCCCCCCCCCCCC", "00CCCCCCCCCCCCCC"),
+		(
+			"00CCCCCCCCCCCCCC [assembly:/_pro/child.entity].pc_entitytype",
+			"00CCCCCCCCCCCCCC"
+		),
+		("not a hash", "not a hash")
+	] {
+		assert_eq!(normalise_hash(hash), normalised, "{}", hash);
+	}
+}
+
+#[test]
+fn only_annotations_are_stripped() {
+	for (hash, stripped) in [
+		(
+			"00CCCCCCCCCCCCCC [assembly:/_pro/child.entity].pc_entitytype",
+			"00CCCCCCCCCCCCCC"
+		),
+		("00CCCCCCCCCCCCCC", "00CCCCCCCCCCCCCC"),
+		(
+			"[assembly:/_pro/a b.entity].pc_entitytype",
+			"[assembly:/_pro/a b.entity].pc_entitytype"
+		),
+		(
+			"CCCCCCCCCCCCCC [assembly:/_pro/child.entity].pc_entitytype",
+			"CCCCCCCCCCCCCC [assembly:/_pro/child.entity].pc_entitytype"
+		)
+	] {
+		assert_eq!(strip_annotation(hash), stripped, "{}", hash);
+	}
+}
+
+#[test]
+fn hash_lists_annotate_or_replace_ids() {
+	let hash_list = HashList::from_hash_list(HASH_LIST).unwrap();
+
+	assert_eq!(hash_list.len(), 2);
+	assert_eq!(
+		hash_list.path("cccccccccccccc").as_deref(),
+		Some("[assembly:/_pro/child.entity].pc_entitytype")
+	);
+
+	let mut annotated = entity();
+	apply_hash_list(&mut annotated, &hash_list, HashListMode::Annotate);
+
+	let mut replaced = entity();
+	apply_hash_list(&mut replaced, &hash_list, HashListMode::Replace);
+
+	assert_eq!(
+		annotated.entities[CHILD].factory,
+		"00CCCCCCCCCCCCCC [assembly:/_pro/child.entity].pc_entitytype"
+	);
+	assert_eq!(
+		replaced.entities[CHILD].factory,
+		"[assembly:/_pro/child.entity].pc_entitytype"
+	);
+
+	// Hashes which aren't in the list are left alone
+	assert_eq!(annotated.entities[CHILD].blueprint, "00DDDDDDDDDDDDDD");
+	assert_eq!(replaced.entities[CHILD].blueprint, "00DDDDDDDDDDDDDD");
+
+	let resource = |entity: &Entity| {
+		entity.entities["fffffffffffffffe"].properties.as_ref().unwrap()["m_pResource"]
+			.value
+			.to_owned()
+	};
+
+	assert_eq!(
+		resource(&annotated),
+		"00EEEEEEEEEEEEEE [assembly:/_pro/resource.entity].pc_entityresource"
+	);
+	assert_eq!(
+		resource(&replaced),
+		"[assembly:/_pro/resource.entity].pc_entityresource"
+	);
+
+	// Annotating twice doesn't annotate the annotations
+	let mut reannotated = annotated.to_owned();
+	apply_hash_list(&mut reannotated, &hash_list, HashListMode::Annotate);
+	assert_eq!(reannotated, annotated);
+
+	strip_annotations(&mut annotated);
+	assert_eq!(annotated, entity());
+}
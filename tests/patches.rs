@@ -0,0 +1,603 @@
+use quickentity_rs::error::QuickEntityError;
+use quickentity_rs::io::read_entity_from_slice;
+use quickentity_rs::migration::{upgrade_patch, CURRENT_PATCH_VERSION};
+use quickentity_rs::patch_structs::ValuePatchOperation;
+use quickentity_rs::patch_structs::{MergeResult, Patch, PatchGenOptions};
+use quickentity_rs::qn_structs::{CommentEntity, Entity, Ref, RefMaybeConstantValue};
+use quickentity_rs::{
+	apply_patch, apply_value_patch, compose_patches, generate_patch, generate_patch_with, invert_patch, merge_patches
+};
+use serde_json::{from_value, json, Value};
+
+const ENTITY: &[u8] = include_bytes!("data/binary_entity.json");
+const MERGE_CONFLICTS: &[u8] = include_bytes!("data/merge_conflicts.json");
+
+const ROOT: &str = "fffffffffffffffe";
+const CHILD: &str = "abcdef0123456789";
+
+/// A named change to the test entity. Changes do nothing if the part of the entity they change is missing, so that
+/// they can be made one after another.
+type Mutation = (&'static str, fn(&mut Entity));
+
+const MUTATIONS: &[Mutation] = &[
+	("rename the root", |entity| {
+		if let Some(root) = entity.entities.get_mut(ROOT) {
+			root.name = "Renamed".into();
+		}
+	}),
+	("set a property", |entity| {
+		set_value(entity, ROOT, "m_sName", "", json!("Changed"))
+	}),
+	("move the root", |entity| {
+		set_value(entity, ROOT, "m_mTransform", "/position/x", json!(10))
+	}),
+	("remove a property", |entity| {
+		if let Some(properties) = entity.entities.get_mut(ROOT).and_then(|x| x.properties.as_mut()) {
+			properties.shift_remove("m_nSmall");
+		}
+	}),
+	("add a property", |entity| {
+		if let Some(properties) = entity.entities.get_mut(ROOT).and_then(|x| x.properties.as_mut()) {
+			properties.insert(
+				"m_bAdded".into(),
+				from_value(json!({ "type": "bool", "value": true })).unwrap()
+			);
+		}
+	}),
+	("remove an event connection", |entity| {
+		if let Some(connections) = event_connections(entity) {
+			connections.remove(1);
+		}
+	}),
+	("add an event connection", |entity| {
+		if let Some(connections) = event_connections(entity) {
+			connections.push(RefMaybeConstantValue::Ref(Ref::Short(Some(ROOT.into()))));
+		}
+	}),
+	("add a duplicate array item", |entity| {
+		set_value(entity, ROOT, "m_aValues", "", json!([1, 2, 2, 2, 3.25]))
+	}),
+	("remove a duplicate array item", |entity| {
+		set_value(entity, ROOT, "m_aValues", "", json!([1, 2, 3.25]))
+	}),
+	("remove the child", |entity| {
+		entity.entities.shift_remove(CHILD);
+	}),
+	("edit the child", |entity| {
+		set_value(entity, CHILD, "m_bEnabled", "", json!(false))
+	}),
+	("add an entity", |entity| {
+		entity.entities.insert(
+			"0000000000000001".into(),
+			from_value(json!({
+				"parent": ROOT,
+				"name": "Added",
+				"factory": "00CCCCCCCCCCCCCC",
+				"blueprint": "00DDDDDDDDDDDDDD"
+			}))
+			.unwrap()
+		);
+	}),
+	("add a comment", |entity| {
+		entity.comments.push(CommentEntity {
+			parent: Ref::Short(Some(ROOT.into())),
+			name: "Note".into(),
+			text: "Added".into()
+		});
+	})
+];
+
+fn entity() -> Entity {
+	read_entity_from_slice(ENTITY).unwrap()
+}
+
+/// The test entity with its root's transform also given as a platform-specific property.
+fn entity_with_platform_transform() -> Entity {
+	let mut entity = entity();
+
+	let root = &mut entity.entities[ROOT];
+	let transform = root.properties.as_ref().unwrap()["m_mTransform"].to_owned();
+
+	root.platform_specific_properties
+		.get_or_insert_with(Default::default)
+		.entry("PS4".into())
+		.or_default()
+		.insert("m_mTransform".into(), transform);
+
+	entity
+}
+
+/// Move an entity's root along the X axis, in its properties and in its platform-specific properties if it has any.
+fn move_root(entity: &Entity) -> Entity {
+	let mut entity = entity.to_owned();
+	let root = &mut entity.entities[ROOT];
+
+	for properties in root.properties.iter_mut().chain(
+		root.platform_specific_properties
+			.iter_mut()
+			.flat_map(|x| x.values_mut())
+	) {
+		properties["m_mTransform"].value["position"]["x"] = json!(10);
+	}
+
+	entity
+}
+
+/// Set part of a property's value, given as a JSON pointer.
+fn set_value(entity: &mut Entity, sub_entity: &str, property: &str, pointer: &str, value: Value) {
+	if let Some(found) = entity
+		.entities
+		.get_mut(sub_entity)
+		.and_then(|x| x.properties.as_mut())
+		.and_then(|x| x.get_mut(property))
+		.and_then(|x| x.value.pointer_mut(pointer))
+	{
+		*found = value;
+	}
+}
+
+/// The root's connections from OnX to DoY.
+fn event_connections(entity: &mut Entity) -> Option<&mut Vec<RefMaybeConstantValue>> {
+	entity
+		.entities
+		.get_mut(ROOT)?
+		.events
+		.as_mut()?
+		.get_mut("OnX")?
+		.get_mut("DoY")
+}
+
+/// The test entity with a mutation made.
+fn mutated(mutation: &Mutation) -> Entity {
+	let mut entity = entity();
+	(mutation.1)(&mut entity);
+	entity
+}
+
+/// Apply patches one after another.
+fn apply_all(base: &Entity, patches: &[Patch]) -> Entity {
+	let mut entity = base.to_owned();
+
+	for patch in patches {
+		apply_patch(&mut entity, patch.to_owned(), false).unwrap();
+	}
+
+	entity
+}
+
+/// The names of the sub-entity operations in a patch.
+fn operation_names(patch: &Patch) -> Vec<String> {
+	serde_json::to_value(&patch.patch)
+		.unwrap()
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|x| {
+			x["SubEntityOperation"][1]
+				.as_object()
+				.unwrap()
+				.keys()
+				.next()
+				.unwrap()
+				.to_owned()
+		})
+		.collect()
+}
+
+/// A patch for the test entity with the given operations.
+fn patch(operations: Value) -> Patch {
+	versioned_patch(operations, CURRENT_PATCH_VERSION)
+}
+
+/// A patch for the test entity with the given operations and patch version.
+fn versioned_patch(operations: Value, version: u8) -> Patch {
+	from_value(json!({
+		"tempHash": "00AAAAAAAAAAAAAA",
+		"tbluHash": "00BBBBBBBBBBBBBB",
+		"patch": operations,
+		"patchVersion": version
+	}))
+	.unwrap()
+}
+
+/// The error a failed patch operation gave.
+fn operation_error(error: QuickEntityError) -> QuickEntityError {
+	match error.kind() {
+		QuickEntityError::PatchOperationFailed { .. } => match error {
+			QuickEntityError::Context { source, .. } => operation_error(*source),
+			QuickEntityError::PatchOperationFailed { source, .. } => *source,
+			_ => unreachable!()
+		},
+		_ => panic!("Expected a failed patch operation, got {:?}", error)
+	}
+}
+
+#[test]
+fn missing_platform_is_not_a_missing_property() {
+	let error = apply_patch(
+		&mut entity(),
+		patch(json!([
+			{ "SubEntityOperation": ["abcdef0123456789", { "SetPlatformSpecificPropertyPostInit": ["PS4", "m_bEnabled", true] }] }
+		])),
+		false
+	)
+	.unwrap_err();
+
+	assert!(matches!(
+		operation_error(error).kind(),
+		QuickEntityError::PlatformNotFound { platform, .. } if platform == "PS4"
+	));
+}
+
+#[test]
+fn old_patch_versions_are_upgraded() {
+	let operations = json!([
+		{ "AddPropertyOverride": { "entities": ["abcdef0123456789"], "properties": { "m_bEnabled": { "type": "bool", "value": false } } } }
+	]);
+
+	for version in [3, 5] {
+		let upgraded = upgrade_patch(versioned_patch(operations.clone(), version)).unwrap();
+
+		assert_eq!(upgraded.patch_version, CURRENT_PATCH_VERSION);
+		assert_eq!(
+			serde_json::to_value(&upgraded.patch).unwrap(),
+			json!([
+				{ "AddPropertyOverrideConnection": { "entity": "abcdef0123456789", "propertyName": "m_bEnabled", "propertyOverride": { "type": "bool", "value": false } } }
+			])
+		);
+
+		apply_patch(&mut entity(), versioned_patch(operations.clone(), version), false).unwrap();
+	}
+}
+
+#[test]
+fn newer_patch_versions_are_rejected() {
+	let error = apply_patch(
+		&mut entity(),
+		versioned_patch(json!([]), CURRENT_PATCH_VERSION + 1),
+		false
+	)
+	.unwrap_err();
+
+	assert!(matches!(
+		error.kind(),
+		QuickEntityError::UnsupportedPatchVersion { version } if *version == CURRENT_PATCH_VERSION + 1
+	));
+}
+
+#[test]
+fn patches_for_other_entities_are_not_composed_or_merged() {
+	let mut other = patch(json!([]));
+	other.blueprint_hash = "00EEEEEEEEEEEEEE".into();
+
+	let patches = [patch(json!([])), other];
+
+	for error in [
+		compose_patches(&entity(), &patches).unwrap_err(),
+		merge_patches(&entity(), &patches).unwrap_err()
+	] {
+		assert!(matches!(
+			error.kind(),
+			QuickEntityError::PatchEntityMismatch { index: 1, found, .. } if found == "00EEEEEEEEEEEEEE"
+		));
+	}
+}
+
+#[test]
+fn partial_values_can_be_replaced() {
+	let original = entity_with_platform_transform();
+	let modified = move_root(&original);
+
+	assert_eq!(
+		operation_names(&generate_patch(&original, &modified).unwrap()),
+		["PatchPropertyValue", "PatchPlatformSpecificPropertyValue"]
+	);
+
+	assert_eq!(
+		operation_names(
+			&generate_patch_with(
+				&original,
+				&modified,
+				&PatchGenOptions {
+					partial_values: false,
+					..Default::default()
+				}
+			)
+			.unwrap()
+		),
+		["SetPropertyValue", "SetPlatformSpecificPropertyValue"]
+	);
+}
+
+#[test]
+fn platform_specific_partial_values_apply_and_invert() {
+	let original = entity_with_platform_transform();
+	let modified = move_root(&original);
+
+	let patch = generate_patch(&original, &modified).unwrap();
+
+	let mut patched = original.to_owned();
+	apply_patch(&mut patched, patch.to_owned(), false).unwrap();
+	assert_eq!(patched, modified);
+
+	apply_patch(&mut patched, invert_patch(&original, &patch).unwrap(), false).unwrap();
+	assert_eq!(patched, original);
+}
+
+#[test]
+fn platform_specific_partial_values_merge() {
+	let original = entity_with_platform_transform();
+
+	let mut moved_y = original.to_owned();
+	moved_y.entities[ROOT].platform_specific_properties.as_mut().unwrap()["PS4"]["m_mTransform"].value["position"]
+		["y"] = json!(20);
+
+	let patches = [
+		generate_patch(&original, &move_root(&original)).unwrap(),
+		generate_patch(&original, &moved_y).unwrap()
+	];
+
+	match merge_patches(&original, &patches).unwrap() {
+		MergeResult::Merged(merged) => {
+			let mut patched = original.to_owned();
+			apply_patch(&mut patched, merged, false).unwrap();
+
+			let transform =
+				&patched.entities[ROOT].platform_specific_properties.as_ref().unwrap()["PS4"]["m_mTransform"].value;
+
+			assert_eq!(transform["position"]["x"], json!(10));
+			assert_eq!(transform["position"]["y"], json!(20));
+		}
+
+		MergeResult::Conflicts(conflicts) => panic!("Expected the patches to merge, got {:?}", conflicts)
+	}
+}
+
+#[test]
+fn merge_conflict_fixtures() {
+	let cases: Vec<Value> = serde_json::from_slice(MERGE_CONFLICTS).unwrap();
+
+	for case in cases {
+		let patches: Vec<Patch> = case["patches"].as_array().unwrap().iter().cloned().map(patch).collect();
+
+		match merge_patches(&entity(), &patches).unwrap() {
+			MergeResult::Merged(merged) => {
+				assert!(case["merges"].as_bool().unwrap(), "{} merged", case["name"]);
+				assert_eq!(
+					apply_all(&entity(), &[merged]),
+					apply_all(&entity(), &patches),
+					"{} merged incorrectly",
+					case["name"]
+				);
+			}
+
+			MergeResult::Conflicts(conflicts) => {
+				assert!(
+					!case["merges"].as_bool().unwrap(),
+					"{} conflicted: {:?}",
+					case["name"],
+					conflicts
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn merged_patches_make_both_changes() {
+	let mut conflicting = vec![];
+
+	for (index, first) in MUTATIONS.iter().enumerate() {
+		for second in &MUTATIONS[index + 1..] {
+			let patches = [
+				generate_patch(&entity(), &mutated(first)).unwrap(),
+				generate_patch(&entity(), &mutated(second)).unwrap()
+			];
+
+			match merge_patches(&entity(), &patches).unwrap() {
+				MergeResult::Merged(merged) => {
+					let mut expected = mutated(first);
+					(second.1)(&mut expected);
+
+					assert_eq!(
+						apply_all(&entity(), &[merged]),
+						expected,
+						"merging {} and {}",
+						first.0,
+						second.0
+					);
+				}
+
+				MergeResult::Conflicts(_) => conflicting.push((first.0, second.0))
+			}
+		}
+	}
+
+	assert_eq!(
+		conflicting,
+		[
+			("add a duplicate array item", "remove a duplicate array item"),
+			("remove the child", "edit the child")
+		]
+	);
+}
+
+/// Check that applying a patch and then its inverse gives back the test entity, in the same order.
+fn assert_inverts(patch: &Patch, description: &str) {
+	let mut patched = entity();
+	apply_patch(&mut patched, patch.to_owned(), false).unwrap();
+
+	let inverse = invert_patch(&entity(), patch).unwrap();
+	apply_patch(&mut patched, inverse, false).unwrap();
+
+	assert_eq!(
+		serde_json::to_string(&patched).unwrap(),
+		serde_json::to_string(&entity()).unwrap(),
+		"inverting {}",
+		description
+	);
+}
+
+#[test]
+fn inverted_patches_undo_every_change() {
+	for mutation in MUTATIONS {
+		assert_inverts(&generate_patch(&entity(), &mutated(mutation)).unwrap(), mutation.0);
+	}
+
+	for (index, first) in MUTATIONS.iter().enumerate() {
+		for second in &MUTATIONS[index + 1..] {
+			let mut modified = mutated(first);
+			(second.1)(&mut modified);
+
+			assert_inverts(
+				&generate_patch(&entity(), &modified).unwrap(),
+				&format!("{} and {}", first.0, second.0)
+			);
+		}
+	}
+}
+
+#[test]
+fn inverted_removals_restore_positions() {
+	assert_inverts(
+		&patch(json!([
+			{ "SubEntityOperation": [ROOT, { "RemovePropertyByName": "m_nSmall" }] },
+			{ "SubEntityOperation": [ROOT, { "RemovePropertyByName": "m_mTransform" }] }
+		])),
+		"property removals"
+	);
+
+	assert_inverts(
+		&patch(json!([
+			{ "SubEntityOperation": [ROOT, { "RemoveEventConnection": ["OnX", "DoY", { "ref": CHILD, "value": { "type": "float32", "value": 0.5 } }] }] }
+		])),
+		"an event connection removal"
+	);
+
+	assert_inverts(
+		&patch(json!([
+			{ "SubEntityOperation": [ROOT, { "RemoveEventConnection": ["OnX", "DoY", CHILD] }] },
+			{ "SubEntityOperation": [ROOT, { "RemoveEventConnection": ["OnX", "DoY", { "ref": CHILD, "value": { "type": "ZString", "value": "" } }] }] }
+		])),
+		"several event connection removals"
+	);
+}
+
+#[test]
+fn composed_patches_match_applying_each_patch() {
+	for first in MUTATIONS {
+		for second in MUTATIONS {
+			let intermediate = mutated(first);
+
+			let mut modified = intermediate.to_owned();
+			(second.1)(&mut modified);
+
+			let patches = [
+				generate_patch(&entity(), &intermediate).unwrap(),
+				generate_patch(&intermediate, &modified).unwrap()
+			];
+
+			let composed = compose_patches(&entity(), &patches).unwrap();
+
+			assert_eq!(
+				serde_json::to_string(&apply_all(&entity(), &[composed])).unwrap(),
+				serde_json::to_string(&apply_all(&entity(), &patches)).unwrap(),
+				"composing {} then {}",
+				first.0,
+				second.0
+			);
+		}
+	}
+
+	let mut patches = vec![];
+	let mut previous = entity();
+
+	for mutation in MUTATIONS {
+		let mut modified = previous.to_owned();
+		(mutation.1)(&mut modified);
+
+		patches.push(generate_patch(&previous, &modified).unwrap());
+		previous = modified;
+	}
+
+	let composed = compose_patches(&entity(), &patches).unwrap();
+
+	assert_eq!(
+		serde_json::to_string(&apply_all(&entity(), &[composed])).unwrap(),
+		serde_json::to_string(&previous).unwrap()
+	);
+}
+
+/// Apply value patch operations to a copy of a value.
+fn patched_value(value: &Value, operations: Value, permissive: bool) -> Result<Value, QuickEntityError> {
+	let mut value = value.to_owned();
+	let operations: Vec<ValuePatchOperation> = from_value(operations).unwrap();
+
+	apply_value_patch(&mut value, operations, permissive)?;
+
+	Ok(value)
+}
+
+#[test]
+fn value_patches_set_and_remove_nested_values() {
+	let value = json!({ "a": { "b": 1, "c": [1, 2] }, "x/y": { "~z": 1 }, "e": 2 });
+
+	assert_eq!(
+		patched_value(
+			&value,
+			json!([
+				{ "SetValue": ["/a/b", 2] },
+				{ "SetValue": ["/a/d", { "f": 3 }] },
+				{ "RemoveValue": "/a/c" },
+				{ "SetValue": ["/x~1y/~0z", 5] },
+				{ "RemoveValue": "/e" }
+			]),
+			false
+		)
+		.unwrap(),
+		json!({ "a": { "b": 2, "d": { "f": 3 } }, "x/y": { "~z": 5 } })
+	);
+
+	for operations in [
+		json!([{ "RemoveValue": "/a/missing" }]),
+		json!([{ "SetValue": ["/missing/b", 1] }])
+	] {
+		assert!(patched_value(&value, operations, false).is_err());
+	}
+
+	assert_eq!(
+		patched_value(&value, json!([{ "RemoveValue": "/a/missing" }]), true).unwrap(),
+		value
+	);
+}
+
+#[test]
+fn value_patches_escape_keys() {
+	let mut original = entity();
+	set_value(
+		&mut original,
+		ROOT,
+		"m_vPosition",
+		"",
+		json!({ "x": 0.1, "a/b": { "c~d": 1, "~1": 2 }, "e": 3 })
+	);
+
+	let mut modified = original.to_owned();
+	set_value(
+		&mut modified,
+		ROOT,
+		"m_vPosition",
+		"",
+		json!({ "x": 0.1, "a/b": { "c~d": 4, "f/~g": 5 }, "~0": 6 })
+	);
+
+	let patch = generate_patch(&original, &modified).unwrap();
+	assert_eq!(operation_names(&patch), ["PatchPropertyValue"]);
+
+	let mut patched = original.to_owned();
+	apply_patch(&mut patched, patch.to_owned(), false).unwrap();
+	assert_eq!(patched, modified);
+
+	apply_patch(&mut patched, invert_patch(&original, &patch).unwrap(), false).unwrap();
+	assert_eq!(patched, original);
+}
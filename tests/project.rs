@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use quickentity_rs::io::{read_entity_from_slice, to_vec_canonical};
+use quickentity_rs::project::{explode_entity, implode_entity};
+use quickentity_rs::qn_structs::Entity;
+
+const ENTITIES: &[(&str, &[u8])] = &[
+	("entity", include_bytes!("data/entity.json")),
+	("binary_entity", include_bytes!("data/binary_entity.json"))
+];
+
+/// A fresh temporary directory for a test.
+fn temp_directory(name: &str) -> PathBuf {
+	let directory = std::env::temp_dir().join(format!("quickentity-project-{}-{}", std::process::id(), name));
+
+	if directory.exists() {
+		fs::remove_dir_all(&directory).unwrap();
+	}
+
+	directory
+}
+
+/// Explode an entity into a directory and implode it again.
+fn round_trip(entity: &Entity, directory: &Path) -> Entity {
+	explode_entity(entity, directory).unwrap();
+	implode_entity(directory).unwrap()
+}
+
+/// Check that an entity is unchanged by a round trip through a project. Like every QN file, project files are
+/// canonical, so the order of keys within sub-entities isn't kept; the order of the sub-entities themselves is.
+fn assert_round_trips(entity: &Entity, directory: &Path, description: &str) {
+	let imploded = round_trip(entity, directory);
+
+	assert_eq!(&imploded, entity, "{}", description);
+	assert_eq!(
+		String::from_utf8(to_vec_canonical(&imploded).unwrap()).unwrap(),
+		String::from_utf8(to_vec_canonical(entity).unwrap()).unwrap(),
+		"{}",
+		description
+	);
+	assert!(
+		imploded.entities.keys().eq(entity.entities.keys()),
+		"{} changed the order of the sub-entities",
+		description
+	);
+}
+
+#[test]
+fn entities_round_trip() {
+	for (name, data) in ENTITIES {
+		let entity = read_entity_from_slice(data).unwrap();
+
+		let mut reordered = entity.to_owned();
+		reordered.entities.reverse();
+
+		for (variant, entity) in [("ordered", entity), ("reordered", reordered)] {
+			let directory = temp_directory(&format!("{}-{}", name, variant));
+
+			assert_round_trips(&entity, &directory, &format!("{} ({})", name, variant));
+
+			fs::remove_dir_all(&directory).unwrap();
+		}
+	}
+}
+
+#[test]
+fn removed_entities_are_removed_from_projects() {
+	let entity = read_entity_from_slice(ENTITIES[1].1).unwrap();
+	let directory = temp_directory("removed");
+
+	explode_entity(&entity, &directory).unwrap();
+
+	let mut smaller = entity.to_owned();
+	smaller.entities.shift_remove("abcdef0123456789");
+
+	assert_round_trips(&smaller, &directory, "removing a sub-entity");
+
+	fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,115 @@
+use quickentity_rs::io::read_entity_from_slice;
+use quickentity_rs::qn_structs::{ConvertOptions, Entity, TransformRepresentation};
+use quickentity_rs::{convert_to_qn, convert_to_rt};
+use serde_json::{json, Value};
+
+const ENTITY: &[u8] = include_bytes!("data/binary_entity.json");
+
+const ROOT: &str = "fffffffffffffffe";
+
+/// The test entity with its root's transform set to the given value.
+fn entity_with_transform(transform: Value) -> Entity {
+	let mut entity = read_entity_from_slice(ENTITY).unwrap();
+
+	entity.entities[ROOT].properties.as_mut().unwrap()["m_mTransform"].value = transform;
+
+	entity
+}
+
+/// Convert an entity to RT and back with the given transform representation, giving the root's transform.
+fn convert_transform(entity: &Entity, transform_representation: TransformRepresentation) -> Value {
+	let options = ConvertOptions {
+		transform_representation,
+		..Default::default()
+	};
+
+	let (factory, factory_meta, blueprint, blueprint_meta) = convert_to_rt(entity, &options).unwrap();
+
+	convert_to_qn(&factory, &factory_meta, &blueprint, &blueprint_meta, &options)
+		.unwrap()
+		.entities[ROOT]
+		.properties
+		.as_ref()
+		.unwrap()["m_mTransform"]
+		.value
+		.to_owned()
+}
+
+fn assert_close(value: &Value, expected: [f64; 3]) {
+	for (axis, expected) in ["x", "y", "z"].into_iter().zip(expected) {
+		let found = value[axis].as_f64().unwrap();
+
+		assert!(
+			(found - expected).abs() < 1e-6,
+			"{} was {} rather than {} in {}",
+			axis,
+			found,
+			expected,
+			value
+		);
+	}
+}
+
+#[test]
+fn scales_are_the_same_in_every_representation() {
+	let entity = entity_with_transform(json!({
+		"rotation": { "x": 10, "y": 20, "z": 30 },
+		"position": { "x": 1, "y": 2, "z": 3 },
+		"scale": { "x": 2, "y": 3, "z": 0.5 }
+	}));
+
+	for representation in [TransformRepresentation::Euler, TransformRepresentation::Quaternion] {
+		let transform = convert_transform(&entity, representation);
+
+		assert_close(&transform["scale"], [2.0, 3.0, 0.5]);
+		assert_close(&transform["position"], [1.0, 2.0, 3.0]);
+	}
+
+	assert_close(
+		&convert_transform(&entity, TransformRepresentation::Euler)["rotation"],
+		[10.0, 20.0, 30.0]
+	);
+}
+
+#[test]
+fn scaled_transforms_round_trip() {
+	let entity = entity_with_transform(json!({
+		"rotation": { "x": -45, "y": 5, "z": 120 },
+		"position": { "x": 0, "y": 0, "z": 0 },
+		"scale": { "x": -1.5, "y": 1, "z": 4 }
+	}));
+
+	let matrix = convert_transform(&entity, TransformRepresentation::Matrix);
+
+	for representation in [TransformRepresentation::Euler, TransformRepresentation::Quaternion] {
+		let converted = entity_with_transform(convert_transform(&entity, representation));
+
+		let round_tripped = convert_transform(&converted, TransformRepresentation::Matrix);
+
+		for axis in ["XAxis", "YAxis", "ZAxis", "Trans"] {
+			let expected = ["x", "y", "z"].map(|x| matrix[axis][x].as_f64().unwrap());
+
+			assert_close(&round_tripped[axis], expected);
+		}
+	}
+}
+
+#[test]
+fn zero_scales_keep_the_matrix() {
+	let entity = entity_with_transform(json!({
+		"rotation": { "x": 0, "y": 0, "z": 0 },
+		"position": { "x": 1, "y": 2, "z": 3 },
+		"scale": { "x": 0, "y": 1, "z": 1 }
+	}));
+
+	for representation in [TransformRepresentation::Euler, TransformRepresentation::Quaternion] {
+		let transform = convert_transform(&entity, representation);
+
+		assert!(
+			transform.get("XAxis").is_some(),
+			"{:?} gave {}",
+			representation,
+			transform
+		);
+	}
+}